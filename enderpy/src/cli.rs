@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use enderpy_python_type_checker::settings::{PythonPlatform, PythonVersion, Settings};
 
 /// Enderpy CLI
 #[derive(Parser)]
@@ -23,7 +24,12 @@ pub enum Commands {
         file: PathBuf,
     },
     /// Type check
-    Check { path: PathBuf },
+    Check {
+        /// Files or directories to check, defaults to the `include` setting or the project root
+        paths: Vec<PathBuf>,
+        #[command(flatten)]
+        overrides: SettingsOverrides,
+    },
     ///  Symbol table
     Symbols { path: PathBuf },
 
@@ -31,6 +37,59 @@ pub enum Commands {
     Watch,
 }
 
+/// Flags that take precedence over the `[tool.enderpy]` settings in pyproject.toml
+#[derive(Args)]
+pub struct SettingsOverrides {
+    /// Python version used to resolve imports, e.g. 3.11
+    #[arg(long)]
+    pub python_version: Option<PythonVersion>,
+    /// Platform used to resolve imports: Linux, Darwin or Windows
+    #[arg(long)]
+    pub python_platform: Option<PythonPlatform>,
+    /// Path to the python interpreter
+    #[arg(long)]
+    pub python_executable: Option<PathBuf>,
+    /// Additional path used to resolve imports, can be repeated
+    #[arg(long = "extra-path")]
+    pub extra_paths: Vec<PathBuf>,
+    /// Glob pattern of the paths to skip, can be repeated
+    #[arg(long)]
+    pub exclude: Vec<String>,
+    /// Path to custom typings (stub) modules
+    #[arg(long)]
+    pub stub_path: Option<PathBuf>,
+    /// Path to the virtual environment
+    #[arg(long)]
+    pub venv: Option<PathBuf>,
+}
+
+impl SettingsOverrides {
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(python_version) = self.python_version {
+            settings.python_version = Some(python_version);
+        }
+        if let Some(python_platform) = self.python_platform {
+            settings.python_platform = Some(python_platform);
+        }
+        if let Some(python_executable) = &self.python_executable {
+            settings.import_discovery.python_executable = Some(python_executable.clone());
+        }
+        if !self.extra_paths.is_empty() {
+            settings.extra_paths = self.extra_paths.clone();
+        }
+        if !self.exclude.is_empty() {
+            settings.exclude = self.exclude.clone();
+        }
+        if let Some(stub_path) = &self.stub_path {
+            settings.stub_path = Some(stub_path.clone());
+        }
+        if let Some(venv) = &self.venv {
+            settings.venv_path = venv.parent().map(|p| p.to_path_buf());
+            settings.venv = venv.file_name().map(PathBuf::from);
+        }
+    }
+}
+
 #[test]
 fn verify_cli() {
     use clap::CommandFactory;
@@ -1,11 +1,11 @@
 use anyhow::{bail, Result};
 use clap::Parser as ClapParser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, SettingsOverrides};
 use enderpy_python_parser::{token, Lexer, Parser};
 use enderpy_python_type_checker::{
     build::{BuildManager, BuildSource},
-    project::find_project_root,
-    settings::{ImportDiscovery, Settings},
+    project::{find_project_root, find_source_files},
+    settings::{Settings, Severity},
 };
 use std::{fs, path::PathBuf};
 
@@ -16,7 +16,7 @@ fn main() -> Result<()> {
     match &cli.command {
         Commands::Tokenize { file } => tokenize(file),
         Commands::Parse { file } => parse(file),
-        Commands::Check { path } => check(path),
+        Commands::Check { paths, overrides } => check(paths, overrides),
         Commands::Watch => watch(),
         Commands::Symbols { path } => symbols(path),
     }
@@ -30,13 +30,8 @@ fn symbols(path: &PathBuf) -> std::result::Result<(), anyhow::Error> {
         source,
         followed: false,
     };
-    let dir_of_path = path.parent().unwrap();
-    let python_executable = Some(get_python_executable()?);
-    let settings = Settings {
-        debug: false,
-        root: dir_of_path.to_path_buf(),
-        import_discovery: ImportDiscovery { python_executable },
-    };
+    let root = find_project_root(path);
+    let settings = Settings::from_project_root(root)?;
 
     let mut manager = BuildManager::new(vec![initial_source], settings);
     manager.build();
@@ -49,15 +44,6 @@ fn symbols(path: &PathBuf) -> std::result::Result<(), anyhow::Error> {
     Ok(())
 }
 
-fn get_python_executable() -> Result<PathBuf> {
-    let output = std::process::Command::new("python")
-        .arg("-c")
-        .arg("import sys; print(sys.executable)")
-        .output()?;
-    let path = String::from_utf8(output.stdout)?;
-    Ok(PathBuf::from(path))
-}
-
 fn tokenize(file: &PathBuf) -> Result<()> {
     let source = fs::read_to_string(file)?;
     let mut lexer = Lexer::new(&source);
@@ -83,34 +69,68 @@ fn parse(file: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn check(path: &PathBuf) -> Result<()> {
-    if path.is_dir() {
-        bail!("Path must be a file");
-    }
-    let source = fs::read_to_string(path)?;
-    let initial_source = BuildSource {
-        path: path.to_owned(),
-        module: String::from("test"),
-        source,
-        followed: false,
+fn check(paths: &[PathBuf], overrides: &SettingsOverrides) -> Result<()> {
+    let root = match paths.first() {
+        Some(path) => find_project_root(path).to_path_buf(),
+        None => find_project_root(&std::env::current_dir()?).to_path_buf(),
     };
-    let root = find_project_root(path);
-    let python_executable = Some(get_python_executable()?);
-    let settings = Settings {
-        debug: true,
-        root: PathBuf::from(root),
-        import_discovery: ImportDiscovery { python_executable },
-    };
-    let mut build_manager = BuildManager::new(vec![initial_source], settings);
+    let mut settings = Settings::from_project_root(&root)?;
+    overrides.apply(&mut settings);
+    settings.debug = true;
+
+    let files = find_source_files(paths, &settings)?;
+    if files.is_empty() {
+        bail!("No python files found");
+    }
+    let mut sources = vec![];
+    for file in files {
+        sources.push(BuildSource::from_path(file, false)?);
+    }
+    let mut build_manager = BuildManager::new(sources, settings);
     build_manager.type_check();
 
-    for err in build_manager.get_errors() {
-        println!("{:#?}", err);
+    let errors = build_manager.get_errors();
+    for err in errors.iter() {
+        println!(
+            "{}:{}:{}: {}: {} [{}]",
+            err.path.display(),
+            err.range.start.line + 1,
+            err.range.start.col + 1,
+            severity_name(err.severity),
+            err.msg,
+            err.code
+        );
+        if let Some(advice) = &err.advice {
+            println!("  help: {}", advice);
+        }
+    }
+
+    let count = |severity: Severity| errors.iter().filter(|err| err.severity == severity).count();
+    let (error_count, warning_count) = (count(Severity::Error), count(Severity::Warning));
+    if warning_count > 0 {
+        println!(
+            "Found {} errors and {} warnings",
+            error_count, warning_count
+        );
+    } else if error_count > 0 {
+        println!("Found {} errors", error_count);
+    }
+    if error_count > 0 {
+        std::process::exit(1);
     }
 
     Ok(())
 }
 
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Information => "info",
+        Severity::Off => "off",
+    }
+}
+
 fn watch() -> Result<()> {
     todo!()
 }
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use enderpy_python_type_checker::ide::inlay_hints::InlayHintOptions;
+use enderpy_python_type_checker::settings::{
+    resolve_python_executable, PythonVersion, Settings, Severity,
+};
+use serde::Deserialize;
+
+/// Settings of the language server that clients send in `initializationOptions`
+/// and `workspace/didChangeConfiguration`.
+/// Missing settings keep their default values.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    pub inlay_hints: InlayHintsConfig,
+    /// The interpreter whose packages are used to resolve third party imports
+    pub python_executable: Option<PathBuf>,
+    pub python_version: Option<PythonVersion>,
+    /// Paths used to resolve imports after the extra paths of the project
+    pub extra_paths: Vec<PathBuf>,
+    pub diagnostic_mode: DiagnosticMode,
+    /// Severity of each rule, the rules in `pyproject.toml` take precedence
+    pub rules: HashMap<String, Severity>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticMode {
+    /// Diagnostics are published for the documents that are open in the editor
+    #[default]
+    OpenFilesOnly,
+    /// Diagnostics are published for all the source files of the projects
+    Workspace,
+}
+
+impl Config {
+    /// Adds the settings of the editor to the settings of a project.
+    /// The editor settings are only used when the project does not set them,
+    /// and their relative paths are relative to the project root.
+    /// A python executable without a directory, like `python3`, is looked up on `PATH`.
+    pub fn apply(&self, settings: &mut Settings) {
+        let root = settings.root.clone();
+        if settings.import_discovery.python_executable.is_none() {
+            settings.import_discovery.python_executable = self
+                .python_executable
+                .as_ref()
+                .map(|path| resolve_python_executable(&root, path, |key| std::env::var_os(key)));
+        }
+        if settings.python_version.is_none() {
+            settings.python_version = self.python_version;
+        }
+        settings
+            .extra_paths
+            .extend(self.extra_paths.iter().map(|path| root.join(path)));
+        for (rule, severity) in &self.rules {
+            settings.rules.entry(rule.clone()).or_insert(*severity);
+        }
+    }
+
+    /// Whether the projects have to be built again with the new config,
+    /// the other settings only change how the results are shown
+    pub fn changes_settings(&self, other: &Config) -> bool {
+        self.python_executable != other.python_executable
+            || self.python_version != other.python_version
+            || self.extra_paths != other.extra_paths
+            || self.rules != other.rules
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InlayHintsConfig {
+    pub variable_types: bool,
+    pub return_types: bool,
+    pub parameter_names: bool,
+}
+
+impl Default for InlayHintsConfig {
+    fn default() -> Self {
+        let options = InlayHintOptions::default();
+        Self {
+            variable_types: options.variable_types,
+            return_types: options.return_types,
+            parameter_names: options.parameter_names,
+        }
+    }
+}
+
+impl From<InlayHintsConfig> for InlayHintOptions {
+    fn from(config: InlayHintsConfig) -> Self {
+        Self {
+            variable_types: config.variable_types,
+            return_types: config.return_types,
+            parameter_names: config.parameter_names,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enderpy_python_type_checker::settings::Rule;
+
+    use super::*;
+
+    #[test]
+    fn apply_config_to_settings() {
+        let config = serde_json::from_value::<Config>(serde_json::json!({
+            "pythonExecutable": ".venv/bin/python",
+            "pythonVersion": "3.11",
+            "extraPaths": ["vendor", "/opt/lib"],
+            "diagnosticMode": "workspace",
+            "rules": { "unused-import": "off", "type-error": "warning" },
+        }))
+        .unwrap();
+        assert_eq!(config.diagnostic_mode, DiagnosticMode::Workspace);
+        assert!(config.inlay_hints.variable_types);
+
+        let root = PathBuf::from("/project");
+        let mut settings = Settings {
+            root: root.clone(),
+            extra_paths: vec![root.join("src")],
+            rules: HashMap::from([("type-error".to_string(), Severity::Error)]),
+            ..Settings::default()
+        };
+        config.apply(&mut settings);
+        assert_eq!(
+            settings.import_discovery.python_executable,
+            Some(root.join(".venv/bin/python"))
+        );
+        assert_eq!(settings.python_version, Some(PythonVersion::Py311));
+        assert_eq!(
+            settings.extra_paths,
+            vec![
+                root.join("src"),
+                root.join("vendor"),
+                PathBuf::from("/opt/lib")
+            ]
+        );
+        assert_eq!(settings.rule_severity(Rule::UnusedImport), Severity::Off);
+        assert_eq!(settings.rule_severity(Rule::TypeError), Severity::Error);
+    }
+}
@@ -0,0 +1,101 @@
+use enderpy_python_parser::line_index::LineCol;
+use enderpy_python_parser::LineIndex;
+use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent};
+
+/// Contents of a document that is open in the editor.
+/// The contents may not be saved to disk yet.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub text: String,
+    pub version: i32,
+    pub line_index: LineIndex,
+}
+
+impl Document {
+    pub fn new(text: String, version: i32) -> Self {
+        let line_index = LineIndex::new(&text);
+        Self {
+            text,
+            version,
+            line_index,
+        }
+    }
+
+    /// Applies the changes in order, each change is relative to the text
+    /// after the previous change.
+    pub fn apply_changes(&mut self, changes: Vec<TextDocumentContentChangeEvent>, version: i32) {
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let start = self.offset(range.start);
+                    let end = self.offset(range.end).max(start);
+                    self.text.replace_range(start..end, &change.text);
+                }
+                None => self.text = change.text,
+            }
+            self.line_index = LineIndex::new(&self.text);
+        }
+        self.version = version;
+    }
+
+    /// Returns the byte offset of an LSP position, positions past the end of
+    /// a line or the document are clamped.
+    pub fn offset(&self, position: Position) -> usize {
+        self.line_index
+            .offset_utf16(LineCol {
+                line: position.line,
+                col: position.character,
+            })
+            .unwrap_or(self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Range;
+
+    fn change(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position::new(start.0, start.1),
+                end: Position::new(end.0, end.1),
+            }),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn incremental_changes() {
+        let mut document = Document::new("x = 1\ny = 'é'\n".to_string(), 1);
+        document.apply_changes(
+            vec![
+                change((0, 4), (0, 5), "42"),
+                // 'é' is one UTF-16 code unit but two bytes
+                change((1, 7), (1, 7), " + x"),
+                change((2, 0), (2, 0), "z = y\n"),
+            ],
+            2,
+        );
+        assert_eq!(document.text, "x = 42\ny = 'é' + x\nz = y\n");
+        assert_eq!(document.version, 2);
+        assert_eq!(document.line_index.line_count(), 4);
+    }
+
+    #[test]
+    fn full_change() {
+        let mut document = Document::new("x = 1\n".to_string(), 1);
+        document.apply_changes(
+            vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "y = 2\n".to_string(),
+            }],
+            2,
+        );
+        assert_eq!(document.text, "y = 2\n");
+        assert_eq!(document.offset(Position::new(0, 100)), 5);
+        assert_eq!(document.offset(Position::new(10, 0)), 6);
+    }
+}
@@ -1,76 +1,1144 @@
-use std::path::PathBuf;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
+use dashmap::DashMap;
 use env_logger::Builder;
 use log::{info, LevelFilter};
-use tower_lsp::jsonrpc::Result;
-use tower_lsp::lsp_types::*;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::OwnedRwLockReadGuard;
+use tokio::task::JoinHandle;
+use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::{
+    GotoDeclarationParams, GotoDeclarationResponse, WorkDoneProgressCreate,
+};
+use tower_lsp::lsp_types::{self, *};
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use enderpy_python_type_checker::build::BuildManager;
-use enderpy_python_type_checker::project::find_project_root;
-use enderpy_python_type_checker::settings::{ImportDiscovery, Settings};
+use enderpy_python_parser::ast::Node;
+use enderpy_python_parser::line_index::{LineCol, LineColRange};
+use enderpy_python_parser::LineIndex;
+use enderpy_python_type_checker::build::{
+    BuildError, BuildManager, BuildPhase, BuildSource, PythonHost,
+};
+use enderpy_python_type_checker::ide;
+use enderpy_python_type_checker::ide::completion::CompletionKind;
+use enderpy_python_type_checker::ide::hierarchy::HierarchyItem;
+use enderpy_python_type_checker::ide::semantic_tokens::SemanticTokenKind;
+use enderpy_python_type_checker::ide::symbols::SymbolKind;
+use enderpy_python_type_checker::project::{find_project_root, find_source_files};
+use enderpy_python_type_checker::settings::{Settings, Severity};
 
-#[derive(Debug)]
+mod config;
+mod document;
+
+use crate::config::{Config, DiagnosticMode};
+use crate::document::Document;
+
+// How long the typing must pause before a changed document is checked
+const CHECK_DELAY: Duration = Duration::from_millis(250);
+// Checks that end sooner do not show their progress
+const PROGRESS_DELAY: Duration = Duration::from_millis(500);
+// The least time between two progress reports of a check
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// How long a check can keep a project locked before the waiting requests can use it
+const CHECK_SLICE: Duration = Duration::from_millis(20);
+
+// A project model, the requests read it while the checks lock it a few modules at a time
+type Project = Arc<tokio::sync::RwLock<BuildManager>>;
+type ProjectRef = OwnedRwLockReadGuard<BuildManager>;
+
+// Clones of the backend share its state, the tasks that outlive a notification use a clone
+#[derive(Debug, Clone)]
 struct Backend {
     client: Client,
+    // Documents that are open in the editor, by uri
+    documents: Arc<DashMap<Url, Document>>,
+    // Project models by project root, they are kept between requests so only
+    // the changed modules and their dependents are checked again
+    projects: Arc<DashMap<PathBuf, Project>>,
+    // Workspace folders of the editor, each folder is the root of a project
+    folders: Arc<RwLock<Vec<PathBuf>>>,
+    config: Arc<RwLock<Config>>,
+    // The features that are registered after the initialization depend on the client
+    client_capabilities: Arc<RwLock<ClientCapabilities>>,
+    // The cancel flags of the checks whose progress is shown, by progress token
+    progress: Arc<DashMap<ProgressToken, Arc<AtomicBool>>>,
+    next_progress_id: Arc<AtomicU32>,
+    // The checks of the changed documents that wait for the typing to pause, by uri
+    delayed_checks: Arc<DashMap<Url, JoinHandle<()>>>,
+    // The file watchers that are registered in the client, nothing is registered
+    // before the initialization. The lock is held while the client registers them.
+    file_watchers: Arc<tokio::sync::Mutex<Option<Vec<FileSystemWatcher>>>>,
+}
+
+// Sets the flag when it is dropped, checks that run on other threads stop when it is set
+#[derive(Default)]
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
 }
 
 impl Backend {
-    async fn check_file(&self, path: &PathBuf) -> Vec<Diagnostic> {
-        let root = PathBuf::from(find_project_root(path));
-        let python_executable = None;
-        let settings = Settings {
-            debug: false,
-            root,
-            import_discovery: ImportDiscovery { python_executable },
-        };
-
-        let mut manager = BuildManager::new(vec![], settings);
-        manager.add_source(path);
-        manager.type_check();
-        let errors = manager.get_errors();
-        let mut diagnostics = Vec::new();
-        for err in errors {
-            diagnostics.push(Diagnostic {
-                range: Range {
-                    start: Position {
-                        line: err.line,
-                        character: err.start,
-                    },
-                    end: Position {
-                        line: err.line,
-                        character: err.end,
-                    },
-                },
-                severity: Some(DiagnosticSeverity::ERROR),
-                code: None,
-                code_description: None,
-                source: Some("Enderpy".to_string()),
-                message: err.msg,
-                related_information: None,
-                tags: None,
-                data: None,
+    // Returns the root of the project that contains path, which is the innermost workspace
+    // folder that contains it. Paths outside of the folders use the `pyproject.toml` around them.
+    fn root_of(&self, path: &PathBuf) -> PathBuf {
+        let folders = self.folders.read().unwrap();
+        let folder = folders
+            .iter()
+            .filter(|folder| path.starts_with(folder))
+            .max_by_key(|folder| folder.components().count());
+        match folder {
+            Some(folder) => folder.clone(),
+            None => PathBuf::from(find_project_root(path)),
+        }
+    }
+
+    // Returns the root of the project that contains path and creates its model if needed
+    async fn project_root(&self, path: &PathBuf) -> PathBuf {
+        let root = self.root_of(path);
+        if self.projects.contains_key(&root) {
+            return root;
+        }
+        let project = self.new_project(&root).await;
+        self.projects.entry(root.clone()).or_insert(project);
+        self.update_file_watchers().await;
+        root
+    }
+
+    fn project(&self, root: &Path) -> Option<Project> {
+        self.projects.get(root).map(|project| Arc::clone(&project))
+    }
+
+    // The projects are locked after they are taken out of the map,
+    // so the map is not locked while a project is used
+    fn all_projects(&self) -> Vec<Project> {
+        self.projects
+            .iter()
+            .map(|project| Arc::clone(project.value()))
+            .collect()
+    }
+
+    // Creates an empty model of the project with the settings of the project and the editor
+    async fn new_project(&self, root: &Path) -> Project {
+        let mut settings = match Settings::from_project_root(root) {
+            Ok(settings) => settings,
+            Err(e) => {
+                self.client
+                    .log_message(MessageType::ERROR, format!("invalid settings: {}", e))
+                    .await;
+                Settings {
+                    root: root.to_path_buf(),
+                    ..Settings::default()
+                }
+            }
+        };
+        self.config.read().unwrap().apply(&mut settings);
+        // Creating the model runs the python interpreter of the project
+        let manager = tokio::task::spawn_blocking(move || BuildManager::new(vec![], settings));
+        Arc::new(tokio::sync::RwLock::new(manager.await.unwrap()))
+    }
+
+    // Creates the models of the projects again after the settings changed,
+    // the files that were in the old models are added to the new ones
+    async fn reload_projects(&self) {
+        let roots = self
+            .projects
+            .iter()
+            .map(|project| project.key().clone())
+            .collect::<Vec<_>>();
+        for root in roots {
+            self.reload_project(&root).await;
+        }
+        self.update_file_watchers().await;
+    }
+
+    async fn reload_project(&self, root: &Path) {
+        // The interpreter is queried again, it may be another one or have other search paths
+        PythonHost::invalidate(root);
+        let project = self.new_project(root).await;
+        let Some(previous) = self.projects.insert(root.to_path_buf(), project) else {
+            return;
+        };
+        let files = previous.read().await.checked_files();
+        for path in files {
+            if let Ok(uri) = Url::from_file_path(&path) {
+                self.update_file(&uri, &path).await;
+            }
+        }
+    }
+
+    // Returns the features to register after the initialization that the client supports
+    // The watchers are registered separately, they change with the projects
+    fn registrations(&self) -> Vec<Registration> {
+        let capabilities = self.client_capabilities.read().unwrap().clone();
+        let mut registrations = vec![];
+        // The capabilities of lsp-types have no type hierarchy provider
+        let type_hierarchy = capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.type_hierarchy)
+            .and_then(|type_hierarchy| type_hierarchy.dynamic_registration);
+        if type_hierarchy == Some(true) {
+            registrations.push(Registration {
+                id: "typeHierarchy".to_string(),
+                method: "textDocument/prepareTypeHierarchy".to_string(),
+                register_options: Some(serde_json::json!({
+                    "documentSelector": [{ "language": "python" }],
+                })),
             });
         }
-        diagnostics
+
+        registrations
+    }
+
+    // Returns None when the client cannot register file watchers
+    async fn file_watchers(&self) -> Option<Vec<FileSystemWatcher>> {
+        let watched_files = self
+            .client_capabilities
+            .read()
+            .unwrap()
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files);
+        if watched_files.and_then(|watched_files| watched_files.dynamic_registration) != Some(true)
+        {
+            return None;
+        }
+        let mut watchers = ["**/*.py", "**/*.pyi", "**/pyproject.toml"]
+            .map(|pattern| FileSystemWatcher {
+                glob_pattern: GlobPattern::String(pattern.to_string()),
+                kind: None,
+            })
+            .to_vec();
+        // The installed packages are usually outside of the workspace
+        if watched_files.and_then(|watched_files| watched_files.relative_pattern_support)
+            == Some(true)
+        {
+            let mut directories = vec![];
+            for project in self.all_projects() {
+                directories.extend(project.read().await.external_search_roots());
+            }
+            // Sorting puts the directories before the ones inside them, which are watched already
+            directories.sort();
+            let mut watched: Vec<PathBuf> = vec![];
+            for directory in directories {
+                if !watched.iter().any(|parent| directory.starts_with(parent)) {
+                    watched.push(directory);
+                }
+            }
+            watchers.extend(watched.into_iter().filter_map(|directory| {
+                Some(FileSystemWatcher {
+                    glob_pattern: GlobPattern::Relative(RelativePattern {
+                        base_uri: OneOf::Right(Url::from_directory_path(directory).ok()?),
+                        pattern: "**/*.{py,pyi,pth}".to_string(),
+                    }),
+                    kind: None,
+                })
+            }));
+        }
+        Some(watchers)
+    }
+
+    // Registers the file watchers again when the projects or the directories that
+    // their imports resolve to have changed, the old registration is removed first
+    async fn update_file_watchers(&self) {
+        let mut registered = self.file_watchers.lock().await;
+        let Some(previous) = registered.as_ref() else {
+            return;
+        };
+        let Some(watchers) = self.file_watchers().await else {
+            return;
+        };
+        if *previous == watchers {
+            return;
+        }
+        if !previous.is_empty() {
+            let unregistration = Unregistration {
+                id: "watchedFiles".to_string(),
+                method: "workspace/didChangeWatchedFiles".to_string(),
+            };
+            if let Err(e) = self
+                .client
+                .unregister_capability(vec![unregistration])
+                .await
+            {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("cannot unregister file watchers: {}", e),
+                    )
+                    .await;
+            }
+        }
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: watchers.clone(),
+        };
+        let registration = Registration {
+            id: "watchedFiles".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        match self.client.register_capability(vec![registration]).await {
+            Ok(()) => *registered = Some(watchers),
+            Err(e) => {
+                // Nothing is registered, so the next change registers the watchers again
+                *registered = Some(vec![]);
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("cannot register file watchers: {}", e),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    // Updates the module of the file in its project, the contents of the open
+    // document are used instead of the file on disk
+    async fn update_file(&self, uri: &Url, path: &PathBuf) {
+        let root = self.project_root(path).await;
+        let build_source = match self.documents.get(uri) {
+            Some(document) => BuildSource {
+                path: path.clone(),
+                module: BuildManager::get_module_name(path),
+                source: document.text.clone(),
+                followed: false,
+            },
+            None => match BuildSource::from_path(path.clone(), false) {
+                Ok(build_source) => build_source,
+                Err(e) => {
+                    self.client
+                        .log_message(MessageType::ERROR, format!("cannot read file: {}", e))
+                        .await;
+                    return;
+                }
+            },
+        };
+        if let Some(project) = self.project(&root) {
+            project.write().await.add_build_source(build_source);
+        }
+    }
+
+    // Returns the project of the file after building it, language features
+    // use the symbol tables of the project
+    async fn built_project(&self, uri: &Url, path: &PathBuf) -> Option<ProjectRef> {
+        let root = self.project_root(path).await;
+        let project = self.project(&root)?;
+        if !project.read().await.contains_file(path) {
+            self.update_file(uri, path).await;
+        }
+        self.build(project).await
+    }
+
+    // Builds the project off the async executor if it changed since the last build, a few
+    // modules at a time like the checks, so the other requests can use the project in between.
+    // The requests do not wait for the checks of the project to end.
+    // Returns nothing if the request that started the build was cancelled.
+    async fn build(&self, project: Project) -> Option<ProjectRef> {
+        // The future of a request is dropped when the client cancels it
+        let cancel_on_drop = CancelOnDrop::default();
+        loop {
+            let built = Arc::clone(&project).read_owned().await;
+            if built.is_built() {
+                return Some(built);
+            }
+            drop(built);
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let build = {
+                let project = Arc::clone(&project);
+                let cancelled = Arc::clone(&cancel_on_drop.0);
+                tokio::task::spawn_blocking(move || {
+                    build_in_slices(&project, || cancelled.load(Ordering::Relaxed), &sender)
+                })
+            };
+            let report = self.report_progress(receiver, Arc::clone(&cancel_on_drop.0));
+            let (built, ()) = tokio::join!(build, report);
+            if !built.unwrap_or(false) {
+                return None;
+            }
+        }
+    }
+
+    // Finds a location from the position in a document, like the definition of the name under the cursor
+    async fn find_location(
+        &self,
+        params: TextDocumentPositionParams,
+        find: impl Fn(&BuildManager, &Path, usize) -> Option<ide::Location>,
+    ) -> Option<Location> {
+        let uri = params.text_document.uri;
+        let path = uri.to_file_path().ok()?;
+        let project = self.built_project(&uri, &path).await?;
+        let offset = to_offset(project.line_index(&path)?, params.position)?;
+        let location = find(&project, &path, offset)?;
+        to_lsp_location(&project, location)
+    }
+
+    // Returns the semantic tokens of the document, or of the range of it
+    async fn semantic_tokens(&self, uri: Url, range: Option<Range>) -> Option<SemanticTokens> {
+        let path = uri.to_file_path().ok()?;
+        let project = self.built_project(&uri, &path).await?;
+        let line_index = project.line_index(&path)?;
+        let range = match range {
+            Some(range) => Some(Node::new(
+                to_offset(line_index, range.start)?,
+                to_offset(line_index, range.end)?,
+            )),
+            None => None,
+        };
+        let tokens = project.semantic_tokens(&path, range);
+        Some(SemanticTokens {
+            result_id: None,
+            data: encode_semantic_tokens(line_index, &tokens),
+        })
+    }
+
+    // Checks the modules of the project that changed off the async executor, a few modules
+    // at a time so the requests can use the project in between.
+    // Returns the checked project, or nothing if the request that started the check was
+    // cancelled, or the document that it was started for has a newer version.
+    async fn check_project(
+        &self,
+        root: &Path,
+        document: Option<(&Url, i32)>,
+    ) -> Option<ProjectRef> {
+        let project = self.project(root)?;
+        let documents = Arc::clone(&self.documents);
+        let document = document.map(|(uri, version)| (uri.clone(), version));
+        // The future of a request is dropped when the client cancels it
+        let cancel_on_drop = CancelOnDrop::default();
+        let cancelled = Arc::clone(&cancel_on_drop.0);
+        let is_stale = move || {
+            cancelled.load(Ordering::Relaxed)
+                || document.as_ref().is_some_and(|(uri, version)| {
+                    documents.get(uri).map(|document| document.version) != Some(*version)
+                })
+        };
+        loop {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let check = {
+                let project = Arc::clone(&project);
+                let is_stale = is_stale.clone();
+                tokio::task::spawn_blocking(move || check_in_slices(&project, is_stale, sender))
+            };
+            let report = self.report_progress(receiver, Arc::clone(&cancel_on_drop.0));
+            let (checked, ()) = tokio::join!(check, report);
+            if !checked.unwrap_or(false) {
+                return None;
+            }
+            // Other files can change between the check and the request reading it
+            let checked = Arc::clone(&project).read_owned().await;
+            if checked.unchecked_files().is_empty() {
+                return Some(checked);
+            }
+        }
+    }
+
+    // Shows the progress of a check in the client until the check ends, if the check takes
+    // a while. Cancelling the progress in the client stops the check.
+    async fn report_progress(
+        &self,
+        mut receiver: UnboundedReceiver<(&'static str, usize, usize)>,
+        cancelled: Arc<AtomicBool>,
+    ) {
+        let supported = self
+            .client_capabilities
+            .read()
+            .unwrap()
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress);
+        if supported != Some(true) {
+            return;
+        }
+        let start = Instant::now();
+        let mut token = None;
+        let mut last_report: Option<Instant> = None;
+        while let Some((step, done, total)) = receiver.recv().await {
+            if start.elapsed() < PROGRESS_DELAY
+                || last_report.is_some_and(|last| last.elapsed() < PROGRESS_INTERVAL)
+            {
+                continue;
+            }
+            let message = Some(format!("{} {}/{} files", step, done, total));
+            let progress = match &token {
+                Some(_) => WorkDoneProgress::Report(WorkDoneProgressReport {
+                    cancellable: Some(true),
+                    message,
+                    percentage: None,
+                }),
+                None => {
+                    let id = self.next_progress_id.fetch_add(1, Ordering::Relaxed);
+                    let new_token = ProgressToken::String(format!("enderpy/check/{}", id));
+                    let params = WorkDoneProgressCreateParams {
+                        token: new_token.clone(),
+                    };
+                    if self
+                        .client
+                        .send_request::<WorkDoneProgressCreate>(params)
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    self.progress
+                        .insert(new_token.clone(), Arc::clone(&cancelled));
+                    token = Some(new_token);
+                    WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                        title: "Checking the project".to_string(),
+                        cancellable: Some(true),
+                        message,
+                        percentage: None,
+                    })
+                }
+            };
+            if let Some(token) = &token {
+                self.client
+                    .send_notification::<Progress>(ProgressParams {
+                        token: token.clone(),
+                        value: ProgressParamsValue::WorkDone(progress),
+                    })
+                    .await;
+            }
+            last_report = Some(Instant::now());
+        }
+        if let Some(token) = token {
+            self.progress.remove(&token);
+            self.client
+                .send_notification::<Progress>(ProgressParams {
+                    token,
+                    value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(
+                        WorkDoneProgressEnd { message: None },
+                    )),
+                })
+                .await;
+        }
+    }
+
+    // Stops the check that reports its progress with the token,
+    // the notification is not part of the `LanguageServer` trait
+    async fn work_done_progress_cancel(&self, params: WorkDoneProgressCancelParams) {
+        if let Some(cancelled) = self.progress.get(&params.token) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Returns the project of the file after checking it, or nothing if the check is stale
+    async fn checked_project(
+        &self,
+        uri: &Url,
+        path: &PathBuf,
+        version: Option<i32>,
+    ) -> Option<ProjectRef> {
+        let root = self.project_root(path).await;
+        let contains_file = match self.project(&root) {
+            Some(project) => project.read().await.contains_file(path),
+            None => false,
+        };
+        if !contains_file {
+            self.update_file(uri, path).await;
+        }
+        self.check_project(&root, version.map(|version| (uri, version)))
+            .await
+    }
+
+    // Returns the diagnostics of the file, or nothing if the document changed during the check
+    async fn check_file(
+        &self,
+        uri: &Url,
+        path: &PathBuf,
+        version: Option<i32>,
+    ) -> Option<Vec<Diagnostic>> {
+        let project = self.checked_project(uri, path, version).await?;
+        Some(
+            project
+                .get_file_errors(path)
+                .into_iter()
+                .map(|err| to_lsp_diagnostic(&project, err))
+                .collect(),
+        )
+    }
+
+    // Adds the source files of the project that are not in its model yet,
+    // so the whole project is checked and not only the open files
+    async fn add_project_files(&self, root: &Path) {
+        // The files that were only found by following imports are added as checked files
+        let Some(project) = self.project(root) else {
+            return;
+        };
+        let (settings, checked_files) = {
+            let project = project.read().await;
+            let checked_files = project.checked_files().into_iter().collect::<HashSet<_>>();
+            (project.settings().clone(), checked_files)
+        };
+        let files = match find_source_files(&[], &settings) {
+            Ok(files) => files,
+            Err(e) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("cannot find source files: {}", e),
+                    )
+                    .await;
+                return;
+            }
+        };
+        for path in files {
+            if checked_files.contains(&path) {
+                continue;
+            }
+            if let Ok(uri) = Url::from_file_path(&path) {
+                self.update_file(&uri, &path).await;
+            }
+        }
+    }
+
+    // Publishes the diagnostics of the open documents, a document that changes
+    // during its check is skipped because its own check publishes them
+    async fn publish_open_documents(&self) {
+        let documents = self
+            .documents
+            .iter()
+            .map(|document| (document.key().clone(), document.version))
+            .collect::<Vec<_>>();
+        for (uri, version) in documents {
+            let Ok(path) = uri.to_file_path() else {
+                continue;
+            };
+            if let Some(diagnostics) = self.check_file(&uri, &path, Some(version)).await {
+                self.client
+                    .publish_diagnostics(uri, diagnostics, Some(version))
+                    .await;
+            }
+        }
+    }
+
+    // Publishes the diagnostics of all the source files of the project in the workspace
+    // diagnostic mode, the changes of a file can add or remove errors in the files that import it
+    // Returns true if the file is checked when it is not open, which is when the diagnostics
+    // of the workspace are published and the file is a source file of its project
+    async fn is_workspace_file(&self, root: &Path, path: &PathBuf) -> bool {
+        if self.config.read().unwrap().diagnostic_mode != DiagnosticMode::Workspace {
+            return false;
+        }
+        let Some(project) = self.project(root) else {
+            return false;
+        };
+        let settings = project.read().await.settings().clone();
+        find_source_files(&[], &settings).is_ok_and(|files| files.contains(path))
+    }
+
+    async fn publish_workspace_diagnostics(&self, root: &Path, document: Option<(&Url, i32)>) {
+        if self.config.read().unwrap().diagnostic_mode != DiagnosticMode::Workspace {
+            return;
+        }
+        self.add_project_files(root).await;
+        let Some(project) = self.check_project(root, document).await else {
+            return;
+        };
+        let mut published = vec![];
+        for path in project.checked_files() {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let diagnostics = project
+                .get_file_errors(&path)
+                .into_iter()
+                .map(|err| to_lsp_diagnostic(&project, err))
+                .collect::<Vec<_>>();
+            let version = self.documents.get(&uri).map(|document| document.version);
+            published.push((uri, diagnostics, version));
+        }
+        drop(project);
+        for (uri, diagnostics, version) in published {
+            self.client
+                .publish_diagnostics(uri, diagnostics, version)
+                .await;
+        }
+    }
+
+    // Clears the diagnostics of the files that are not open, after the diagnostic
+    // mode changes from the workspace to the open files
+    async fn clear_closed_file_diagnostics(&self) {
+        let mut uris = vec![];
+        for project in self.all_projects() {
+            let files = project.read().await.checked_files();
+            uris.extend(
+                files
+                    .into_iter()
+                    .filter_map(|path| Url::from_file_path(path).ok())
+                    .filter(|uri| !self.documents.contains_key(uri)),
+            );
+        }
+        uris.sort();
+        for uri in uris {
+            self.client.publish_diagnostics(uri, vec![], None).await;
+        }
+    }
+
+    // Returns the built project of a call or type hierarchy item with all its source files,
+    // the callers and subclasses of the item can be in files that are not open
+    async fn hierarchy_project(
+        &self,
+        uri: &Url,
+        data: Option<&serde_json::Value>,
+    ) -> Option<ProjectRef> {
+        // The items of libraries are outside of the project, so the items keep its root
+        let root = match data.and_then(serde_json::Value::as_str) {
+            Some(root) => PathBuf::from(root),
+            None => self.project_root(&uri.to_file_path().ok()?).await,
+        };
+        self.add_project_files(&root).await;
+        self.build(self.project(&root)?).await
+    }
+}
+
+// Builds and checks the project on a blocking thread, the project is unlocked whenever a slice
+// of the build or the check of a module ends. Returns false if the check is stale.
+fn check_in_slices(
+    project: &Project,
+    is_stale: impl Fn() -> bool,
+    sender: UnboundedSender<(&'static str, usize, usize)>,
+) -> bool {
+    if !build_in_slices(project, &is_stale, &sender) {
+        return false;
+    }
+    let unchecked = project.blocking_read().unchecked_files();
+    let total = unchecked.len();
+    for (done, path) in unchecked.into_iter().enumerate() {
+        if is_stale() {
+            return false;
+        }
+        project.blocking_write().type_check_file(&path);
+        let _ = sender.send(("Checking", done + 1, total));
+    }
+    !is_stale()
+}
+
+// Builds the project on a blocking thread, the project is unlocked whenever a slice
+// of the build ends. Returns false if the build is stale.
+fn build_in_slices(
+    project: &Project,
+    is_stale: impl Fn() -> bool,
+    sender: &UnboundedSender<(&'static str, usize, usize)>,
+) -> bool {
+    // A stopped build continues from the same module in the next slice but counts
+    // from zero, so the modules that the previous slices finished are added to the counts
+    let mut finished = (BuildPhase::Gathering, 0);
+    loop {
+        let mut project = project.blocking_write();
+        let slice = Instant::now();
+        let mut reported = finished;
+        let built = project.build_with_progress(|phase, done, total| {
+            let step = match phase {
+                BuildPhase::Gathering => "Resolving imports",
+                BuildPhase::Analyzing => "Analyzing",
+            };
+            let before = if phase == finished.0 { finished.1 } else { 0 };
+            reported = (phase, before + done);
+            let _ = sender.send((step, before + done, before + total));
+            if is_stale() || slice.elapsed() > CHECK_SLICE {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        if built.is_continue() {
+            return true;
+        }
+        drop(project);
+        if is_stale() {
+            return false;
+        }
+        finished = reported;
+    }
+}
+
+fn to_lsp_diagnostic(project: &BuildManager, err: BuildError) -> Diagnostic {
+    // Clients count the columns in UTF-16 code units
+    let range = match project.line_index(&err.path) {
+        Some(line_index) => LineColRange {
+            start: line_index.to_utf16(err.range.start),
+            end: line_index.to_utf16(err.range.end),
+        },
+        None => err.range,
+    };
+    // Clients fade out the code that can be removed
+    let tags = (err.code == "unused-import").then(|| vec![DiagnosticTag::UNNECESSARY]);
+    Diagnostic {
+        range: to_lsp_range(range),
+        severity: Some(match err.severity {
+            Severity::Error | Severity::Off => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Information => DiagnosticSeverity::INFORMATION,
+        }),
+        code: Some(NumberOrString::String(err.code.to_string())),
+        code_description: None,
+        source: Some("Enderpy".to_string()),
+        message: match err.advice {
+            Some(advice) => format!("{}\n{}", err.msg, advice),
+            None => err.msg,
+        },
+        related_information: Some(
+            err.related
+                .into_iter()
+                .filter_map(|related| {
+                    Some(DiagnosticRelatedInformation {
+                        location: to_lsp_location(project, related.location)?,
+                        message: related.msg,
+                    })
+                })
+                .collect(),
+        )
+        .filter(|related: &Vec<_>| !related.is_empty()),
+        tags,
+        data: None,
+    }
+}
+
+// Identifies the diagnostics of a file, clients send it back to learn if they changed
+fn diagnostics_result_id(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(diagnostics)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+enum DocumentReport {
+    Full(FullDocumentDiagnosticReport),
+    Unchanged(UnchangedDocumentDiagnosticReport),
+}
+
+// Reports the diagnostics of a file, or that they did not change since the client got them
+fn document_report(
+    diagnostics: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> DocumentReport {
+    let result_id = diagnostics_result_id(&diagnostics);
+    if previous_result_id == Some(result_id.as_str()) {
+        return DocumentReport::Unchanged(UnchangedDocumentDiagnosticReport { result_id });
+    }
+    DocumentReport::Full(FullDocumentDiagnosticReport {
+        result_id: Some(result_id),
+        items: diagnostics,
+    })
+}
+
+fn to_lsp_range(range: LineColRange) -> Range {
+    Range {
+        start: Position {
+            line: range.start.line,
+            character: range.start.col,
+        },
+        end: Position {
+            line: range.end.line,
+            character: range.end.col,
+        },
+    }
+}
+
+// Converts the byte offsets to a range with UTF-16 columns
+fn to_lsp_range_utf16(line_index: &LineIndex, start: usize, end: usize) -> Range {
+    to_lsp_range(line_index.range_utf16(start, end))
+}
+
+// Converts a location of the project to an LSP location
+fn to_lsp_location(project: &BuildManager, location: ide::Location) -> Option<Location> {
+    let uri = Url::from_file_path(&location.path).ok()?;
+    let range = match project.line_index(&location.path) {
+        Some(line_index) => to_lsp_range_utf16(line_index, location.start, location.end),
+        None => Range::default(),
+    };
+    Some(Location { uri, range })
+}
+
+fn to_lsp_symbol_kind(kind: SymbolKind) -> lsp_types::SymbolKind {
+    match kind {
+        SymbolKind::Variable => lsp_types::SymbolKind::VARIABLE,
+        SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
+        SymbolKind::Method => lsp_types::SymbolKind::METHOD,
+        SymbolKind::Class => lsp_types::SymbolKind::CLASS,
+        SymbolKind::Module => lsp_types::SymbolKind::MODULE,
+    }
+}
+
+fn from_lsp_symbol_kind(kind: lsp_types::SymbolKind) -> Option<SymbolKind> {
+    match kind {
+        lsp_types::SymbolKind::VARIABLE => Some(SymbolKind::Variable),
+        lsp_types::SymbolKind::FUNCTION => Some(SymbolKind::Function),
+        lsp_types::SymbolKind::METHOD => Some(SymbolKind::Method),
+        lsp_types::SymbolKind::CLASS => Some(SymbolKind::Class),
+        lsp_types::SymbolKind::MODULE => Some(SymbolKind::Module),
+        _ => None,
+    }
+}
+
+// Converts an item of the call or type hierarchy, the root of the project is kept in the data
+fn to_lsp_hierarchy_item(
+    project: &BuildManager,
+    root: &Path,
+    item: HierarchyItem,
+) -> Option<CallHierarchyItem> {
+    let uri = Url::from_file_path(&item.path).ok()?;
+    let line_index = project.line_index(&item.path)?;
+    Some(CallHierarchyItem {
+        name: item.name,
+        kind: to_lsp_symbol_kind(item.kind),
+        tags: None,
+        detail: item.detail,
+        uri,
+        range: to_lsp_range_utf16(line_index, item.start, item.end),
+        selection_range: to_lsp_range_utf16(line_index, item.name_start, item.name_end),
+        data: Some(serde_json::Value::String(
+            root.to_string_lossy().to_string(),
+        )),
+    })
+}
+
+fn to_lsp_type_hierarchy_item(
+    project: &BuildManager,
+    root: &Path,
+    item: HierarchyItem,
+) -> Option<TypeHierarchyItem> {
+    let item = to_lsp_hierarchy_item(project, root, item)?;
+    Some(TypeHierarchyItem {
+        name: item.name,
+        kind: item.kind,
+        tags: None,
+        detail: item.detail,
+        uri: item.uri,
+        range: item.range,
+        selection_range: item.selection_range,
+        data: item.data,
+    })
+}
+
+// Converts an item that the client sends back, the declaration is found again from the range of its name
+fn from_lsp_hierarchy_item(
+    project: &BuildManager,
+    name: String,
+    kind: lsp_types::SymbolKind,
+    uri: &Url,
+    range: Range,
+    selection_range: Range,
+) -> Option<HierarchyItem> {
+    let path = uri.to_file_path().ok()?;
+    let line_index = project.line_index(&path)?;
+    Some(HierarchyItem {
+        name,
+        kind: from_lsp_symbol_kind(kind)?,
+        detail: None,
+        start: to_offset(line_index, range.start)?,
+        end: to_offset(line_index, range.end)?,
+        name_start: to_offset(line_index, selection_range.start)?,
+        name_end: to_offset(line_index, selection_range.end)?,
+        path,
+    })
+}
+
+fn to_lsp_document_symbol(
+    line_index: &LineIndex,
+    symbol: ide::symbols::DocumentSymbol,
+) -> DocumentSymbol {
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: symbol.name,
+        detail: None,
+        kind: to_lsp_symbol_kind(symbol.kind),
+        tags: None,
+        deprecated: None,
+        range: to_lsp_range_utf16(line_index, symbol.start, symbol.end),
+        selection_range: to_lsp_range_utf16(line_index, symbol.name_start, symbol.name_end),
+        children: Some(
+            symbol
+                .children
+                .into_iter()
+                .map(|child| to_lsp_document_symbol(line_index, child))
+                .collect(),
+        ),
+    }
+}
+
+// The indexes of the types and modifiers are used in the encoded tokens
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::CLASS,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::METHOD,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::NAMESPACE,
+    SemanticTokenType::PROPERTY,
+];
+
+const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DECLARATION,
+    SemanticTokenModifier::READONLY,
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+];
+
+// Encodes the tokens relative to the previous token, as the protocol requires
+fn encode_semantic_tokens(
+    line_index: &LineIndex,
+    tokens: &[ide::semantic_tokens::SemanticToken],
+) -> Vec<SemanticToken> {
+    let mut data = vec![];
+    let mut previous = LineCol { line: 0, col: 0 };
+    for token in tokens {
+        let range = line_index.range_utf16(token.start, token.end);
+        // Identifiers are on one line
+        if range.start.line != range.end.line {
+            continue;
+        }
+        let token_type = match token.kind {
+            SemanticTokenKind::Class => 0,
+            SemanticTokenKind::Function => 1,
+            SemanticTokenKind::Method => 2,
+            SemanticTokenKind::Parameter => 3,
+            SemanticTokenKind::Variable => 4,
+            SemanticTokenKind::Module => 5,
+            SemanticTokenKind::Property => 6,
+        };
+        let modifiers = [
+            token.modifiers.declaration,
+            token.modifiers.readonly,
+            token.modifiers.default_library,
+        ];
+        let token_modifiers_bitset = modifiers
+            .iter()
+            .enumerate()
+            .filter(|(_, enabled)| **enabled)
+            .fold(0, |bits, (index, _)| bits | 1 << index);
+        let delta_line = range.start.line - previous.line;
+        data.push(SemanticToken {
+            delta_line,
+            delta_start: if delta_line == 0 {
+                range.start.col - previous.col
+            } else {
+                range.start.col
+            },
+            length: range.end.col - range.start.col,
+            token_type,
+            token_modifiers_bitset,
+        });
+        previous = range.start;
     }
+    data
+}
+
+// Returns the byte offset of a position with a UTF-16 column
+fn to_offset(line_index: &LineIndex, position: Position) -> Option<usize> {
+    line_index.offset_utf16(LineCol {
+        line: position.line,
+        col: position.character,
+    })
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        if let Some(options) = params.initialization_options {
+            match serde_json::from_value::<Config>(options) {
+                Ok(config) => *self.config.write().unwrap() = config,
+                Err(err) => {
+                    self.client
+                        .log_message(
+                            MessageType::WARNING,
+                            format!("invalid initialization options: {}", err),
+                        )
+                        .await;
+                }
+            }
+        }
+        // The projects of the workspace are known before any file is opened, for workspace diagnostics
+        let folders = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|folder| folder.uri).collect(),
+            None => params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        *self.folders.write().unwrap() = folders
+            .into_iter()
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect();
+        let folders = self.folders.read().unwrap().clone();
+        for folder in folders {
+            self.project_root(&folder).await;
+        }
+        *self.client_capabilities.write().unwrap() = params.capabilities;
         Ok(InitializeResult {
             server_info: None,
             offset_encoding: None,
             capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
+                        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                        ..TextDocumentSyncOptions::default()
+                    },
+                )),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                completion_provider: Some(CompletionOptions {
+                    trigger_characters: Some(vec![".".to_string()]),
+                    ..CompletionOptions::default()
+                }),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: Some(vec![")".to_string()]),
+                    work_done_progress_options: Default::default(),
+                }),
+                definition_provider: Some(OneOf::Left(true)),
+                declaration_provider: Some(DeclarationCapability::Simple(true)),
+                references_provider: Some(OneOf::Left(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            legend: SemanticTokensLegend {
+                                token_types: TOKEN_TYPES.to_vec(),
+                                token_modifiers: TOKEN_MODIFIERS.to_vec(),
+                            },
+                            range: Some(true),
+                            full: Some(SemanticTokensFullOptions::Bool(true)),
+                            work_done_progress_options: Default::default(),
+                        },
+                    ),
+                ),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                workspace: Some(WorkspaceServerCapabilities {
+                    workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(OneOf::Left(true)),
+                    }),
+                    file_operations: None,
+                }),
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                        ..CodeActionOptions::default()
+                    },
                 )),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
                 diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
                     DiagnosticOptions {
                         identifier: Some("typechecker".to_string()),
                         inter_file_dependencies: true,
-                        workspace_diagnostics: false,
+                        workspace_diagnostics: true,
                         work_done_progress_options: Default::default(),
                     },
                 )),
@@ -84,6 +1152,223 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "server initialized!")
             .await;
+        let registrations = self.registrations();
+        if !registrations.is_empty() {
+            if let Err(e) = self.client.register_capability(registrations).await {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("cannot register capabilities: {}", e),
+                    )
+                    .await;
+            }
+        }
+        *self.file_watchers.lock().await = Some(vec![]);
+        self.update_file_watchers().await;
+        let roots = self
+            .projects
+            .iter()
+            .map(|project| project.key().clone())
+            .collect::<Vec<_>>();
+        for root in roots {
+            self.publish_workspace_diagnostics(&root, None).await;
+        }
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let removed = params
+            .event
+            .removed
+            .into_iter()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect::<Vec<_>>();
+        let added = params
+            .event
+            .added
+            .into_iter()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect::<Vec<_>>();
+        {
+            let mut folders = self.folders.write().unwrap();
+            folders.retain(|folder| !removed.contains(folder));
+            for folder in &added {
+                if !folders.contains(folder) {
+                    folders.push(folder.clone());
+                }
+            }
+        }
+
+        // The projects of the removed folders and the projects with files that now belong
+        // to another folder are dropped, their files are added to the right projects again
+        let roots = self
+            .projects
+            .iter()
+            .map(|project| project.key().clone())
+            .collect::<Vec<_>>();
+        let mut moved_files = vec![];
+        for root in roots {
+            let files = match self.project(&root) {
+                Some(project) => project.read().await.checked_files(),
+                None => continue,
+            };
+            let is_moved =
+                removed.contains(&root) || files.iter().any(|path| self.root_of(path) != root);
+            if is_moved {
+                self.projects.remove(&root);
+                moved_files.extend(files);
+            }
+        }
+        for folder in &added {
+            self.project_root(folder).await;
+        }
+        // The removed projects can leave directories that are not searched anymore
+        self.update_file_watchers().await;
+        moved_files.sort();
+        for path in moved_files {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let root = self.root_of(&path);
+            let in_folder = self.folders.read().unwrap().contains(&root);
+            if in_folder || self.documents.contains_key(&uri) {
+                self.update_file(&uri, &path).await;
+            } else {
+                // The closed files outside of the folders are not checked anymore
+                self.client.publish_diagnostics(uri, vec![], None).await;
+            }
+        }
+
+        self.publish_open_documents().await;
+        for folder in &added {
+            self.publish_workspace_diagnostics(folder, None).await;
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let mut reload_roots = HashSet::new();
+        let mut changed_roots = HashSet::new();
+        let mut created_or_deleted = false;
+        for change in params.changes {
+            let Ok(path) = change.uri.to_file_path() else {
+                continue;
+            };
+            // The settings of a project are read when its model is created
+            if path
+                .file_name()
+                .is_some_and(|name| name == "pyproject.toml")
+            {
+                if let Some(root) = path
+                    .parent()
+                    .filter(|root| self.projects.contains_key(*root))
+                {
+                    reload_roots.insert(root.to_path_buf());
+                }
+                continue;
+            }
+            // Path configuration files of installed packages add directories to `sys.path`
+            if path.extension().is_some_and(|ext| ext == "pth") {
+                for project in self.all_projects() {
+                    let project = project.read().await;
+                    let is_search_root = project
+                        .external_search_roots()
+                        .iter()
+                        .any(|root| path.parent() == Some(root.as_path()));
+                    if is_search_root {
+                        reload_roots.insert(project.settings().root.clone());
+                    }
+                }
+                continue;
+            }
+            // Open documents use the contents of the editor
+            if self.documents.contains_key(&change.uri) {
+                continue;
+            }
+            created_or_deleted |= change.typ != FileChangeType::CHANGED;
+            for project in self.all_projects() {
+                let mut project = project.write().await;
+                let is_changed = match change.typ {
+                    FileChangeType::DELETED => project.remove_file(&path),
+                    _ => project.reload_file(&path),
+                };
+                if is_changed {
+                    changed_roots.insert(project.settings().root.clone());
+                }
+            }
+        }
+        // New and deleted files can change the files that the imports resolve to
+        if created_or_deleted {
+            for project in self.all_projects() {
+                project.write().await.refresh_imports();
+            }
+        }
+        for root in &reload_roots {
+            self.reload_project(root).await;
+        }
+        if !reload_roots.is_empty() {
+            self.update_file_watchers().await;
+        }
+        if !created_or_deleted && changed_roots.is_empty() && reload_roots.is_empty() {
+            return;
+        }
+
+        // The open documents that depend on the changed files are checked again
+        self.publish_open_documents().await;
+        let roots = self
+            .projects
+            .iter()
+            .map(|project| project.key().clone())
+            .collect::<Vec<_>>();
+        for root in roots {
+            self.publish_workspace_diagnostics(&root, None).await;
+        }
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // Clients send the settings of the server alone or in its section of all the settings
+        let mut settings = params.settings;
+        if let Some(section) = settings.get_mut("enderpy").map(serde_json::Value::take) {
+            settings = section;
+        }
+        // Clients that only notify about the change send no settings
+        if settings.is_null() {
+            return;
+        }
+        let config = match serde_json::from_value::<Config>(settings) {
+            Ok(config) => config,
+            Err(err) => {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("invalid configuration: {}", err),
+                    )
+                    .await;
+                return;
+            }
+        };
+        let previous = std::mem::replace(&mut *self.config.write().unwrap(), config.clone());
+        if previous == config {
+            return;
+        }
+        if config.changes_settings(&previous) {
+            self.reload_projects().await;
+            self.publish_open_documents().await;
+        }
+        match (previous.diagnostic_mode, config.diagnostic_mode) {
+            (_, DiagnosticMode::Workspace) => {
+                let roots = self
+                    .projects
+                    .iter()
+                    .map(|project| project.key().clone())
+                    .collect::<Vec<_>>();
+                for root in roots {
+                    self.publish_workspace_diagnostics(&root, None).await;
+                }
+            }
+            (DiagnosticMode::Workspace, DiagnosticMode::OpenFilesOnly) => {
+                self.clear_closed_file_diagnostics().await;
+            }
+            (DiagnosticMode::OpenFilesOnly, DiagnosticMode::OpenFilesOnly) => {}
+        }
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
@@ -93,75 +1378,843 @@ impl LanguageServer for Backend {
         let uri = params.text_document.uri;
         let path = uri.to_file_path();
         if let Ok(path) = path {
-            let diagnostics = self.check_file(&path).await;
-            self.client
-                .publish_diagnostics(uri, diagnostics, None)
-                .await;
+            let version = self.documents.get(&uri).map(|document| document.version);
+            if let Some(diagnostics) = self.check_file(&uri, &path, version).await {
+                self.client
+                    .publish_diagnostics(uri.clone(), diagnostics, version)
+                    .await;
+                let root = self.project_root(&path).await;
+                let document = version.map(|version| (&uri, version));
+                self.publish_workspace_diagnostics(&root, document).await;
+            }
         }
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        // The notifications are handled concurrently, the document is added before
+        // anything is awaited so the changes that follow find it
+        let uri = params.text_document.uri;
+        let version = params.text_document.version;
+        self.documents.insert(
+            uri.clone(),
+            Document::new(params.text_document.text, version),
+        );
         self.client
             .log_message(MessageType::INFO, "file opened!")
             .await;
-        let uri = params.text_document.uri;
         let path = uri.to_file_path();
         if let Ok(path) = path {
-            let diagnostics = self.check_file(&path).await;
-            self.client
-                .publish_diagnostics(uri, diagnostics, None)
-                .await;
+            self.update_file(&uri, &path).await;
+            if let Some(diagnostics) = self.check_file(&uri, &path, Some(version)).await {
+                self.client
+                    .publish_diagnostics(uri.clone(), diagnostics, Some(version))
+                    .await;
+                let root = self.project_root(&path).await;
+                self.publish_workspace_diagnostics(&root, Some((&uri, version)))
+                    .await;
+            }
         }
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        self.client
-            .log_message(MessageType::INFO, "file changed!")
-            .await;
         let uri = params.text_document.uri;
+        let version = params.text_document.version;
+        match self.documents.get_mut(&uri) {
+            Some(mut document) => document.apply_changes(params.content_changes, version),
+            None => {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("changed a document that is not open: {}", uri),
+                    )
+                    .await;
+                return;
+            }
+        }
         let path = uri.to_file_path();
         if let Ok(path) = path {
-            let diagnostics = self.check_file(&path).await;
-            self.client
-                .publish_diagnostics(uri, diagnostics, None)
-                .await;
+            // The model is updated right away for the other requests, but the check waits
+            // for the typing to pause in a task, a newer version replaces the waiting check
+            self.update_file(&uri, &path).await;
+            let backend = self.clone();
+            let delayed_uri = uri.clone();
+            let check = tokio::spawn(async move {
+                tokio::time::sleep(CHECK_DELAY).await;
+                // The changes are handled concurrently, so the check that is left can be
+                // the one of an older change and it checks the latest version
+                let Some(version) = backend.documents.get(&uri).map(|document| document.version)
+                else {
+                    return;
+                };
+                if let Some(diagnostics) = backend.check_file(&uri, &path, Some(version)).await {
+                    backend
+                        .client
+                        .publish_diagnostics(uri.clone(), diagnostics, Some(version))
+                        .await;
+                    let root = backend.project_root(&path).await;
+                    backend
+                        .publish_workspace_diagnostics(&root, Some((&uri, version)))
+                        .await;
+                }
+            });
+            if let Some(previous) = self.delayed_checks.insert(delayed_uri, check) {
+                previous.abort();
+            }
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.remove(&uri);
+        if let Some((_, check)) = self.delayed_checks.remove(&uri) {
+            check.abort();
+        }
+        let Ok(path) = uri.to_file_path() else {
+            return;
+        };
+        let root = self.project_root(&path).await;
+        if self.is_workspace_file(&root, &path).await {
+            // Unsaved changes are discarded, so the project uses the file on disk again
+            self.update_file(&uri, &path).await;
+            self.publish_workspace_diagnostics(&root, None).await;
+        } else {
+            // Closed files are not checked anymore, so the project does not grow with every
+            // file that was opened. Their diagnostics are not kept up to date, so they are cleared.
+            if let Some(project) = self.project(&root) {
+                project.write().await.release_file(&path);
+            }
+            self.client.publish_diagnostics(uri, vec![], None).await;
+        }
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&path) else {
+            return Ok(None);
+        };
+        let Some(offset) = to_offset(line_index, params.text_document_position_params.position)
+        else {
+            return Ok(None);
+        };
+        Ok(project.hover(&path, offset).map(|hover| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: hover.contents,
+            }),
+            range: Some(to_lsp_range_utf16(line_index, hover.start, hover.end)),
+        }))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = params.text_document_position.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(offset) = project
+            .line_index(&path)
+            .and_then(|line_index| to_offset(line_index, params.text_document_position.position))
+        else {
+            return Ok(None);
+        };
+        let items = project
+            .completion(&path, offset)
+            .into_iter()
+            .map(|item| CompletionItem {
+                label: item.label,
+                kind: Some(match item.kind {
+                    CompletionKind::Variable
+                    | CompletionKind::Parameter
+                    | CompletionKind::KeywordArgument => CompletionItemKind::VARIABLE,
+                    CompletionKind::Function => CompletionItemKind::FUNCTION,
+                    CompletionKind::Method => CompletionItemKind::METHOD,
+                    CompletionKind::Class => CompletionItemKind::CLASS,
+                    CompletionKind::Module => CompletionItemKind::MODULE,
+                }),
+                detail: item.detail,
+                ..CompletionItem::default()
+            })
+            .collect();
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(offset) = project
+            .line_index(&path)
+            .and_then(|line_index| to_offset(line_index, position.position))
+        else {
+            return Ok(None);
+        };
+        let Some(help) = project.signature_help(&path, offset) else {
+            return Ok(None);
+        };
+        // Parameters are given by their UTF-16 offsets in the label
+        let utf16_offset = |offset: usize| help.label[..offset].encode_utf16().count() as u32;
+        let parameters = help
+            .parameters
+            .iter()
+            .map(|parameter| ParameterInformation {
+                label: ParameterLabel::LabelOffsets([
+                    utf16_offset(parameter.start),
+                    utf16_offset(parameter.end),
+                ]),
+                documentation: None,
+            })
+            .collect();
+        let signature = SignatureInformation {
+            label: help.label.clone(),
+            documentation: help.documentation.map(|documentation| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: documentation,
+                })
+            }),
+            parameters: Some(parameters),
+            active_parameter: help.active_parameter.map(|index| index as u32),
+        };
+        Ok(Some(SignatureHelp {
+            signatures: vec![signature],
+            active_signature: Some(0),
+            active_parameter: help.active_parameter.map(|index| index as u32),
+        }))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let location = self
+            .find_location(position, |project, path, offset| {
+                project.definition(path, offset)
+            })
+            .await;
+        Ok(location.map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn goto_declaration(
+        &self,
+        params: GotoDeclarationParams,
+    ) -> Result<Option<GotoDeclarationResponse>> {
+        let position = params.text_document_position_params;
+        let location = self
+            .find_location(position, |project, path, offset| {
+                project.declaration(path, offset)
+            })
+            .await;
+        Ok(location.map(GotoDeclarationResponse::Scalar))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(offset) = project
+            .line_index(&path)
+            .and_then(|line_index| to_offset(line_index, position.position))
+        else {
+            return Ok(None);
+        };
+        let locations = project
+            .references(&path, offset, params.context.include_declaration)
+            .into_iter()
+            .filter_map(|location| to_lsp_location(&project, location))
+            .collect();
+        Ok(Some(locations))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&path) else {
+            return Ok(None);
+        };
+        let symbols = project
+            .document_symbols(&path)
+            .into_iter()
+            .map(|symbol| to_lsp_document_symbol(line_index, symbol))
+            .collect();
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let mut symbols = vec![];
+        for project in self.all_projects() {
+            let Some(project) = self.build(project).await else {
+                continue;
+            };
+            for symbol in project.workspace_symbols(&params.query) {
+                let Some(location) = to_lsp_location(&project, symbol.location) else {
+                    continue;
+                };
+                #[allow(deprecated)]
+                symbols.push(SymbolInformation {
+                    name: symbol.name,
+                    kind: to_lsp_symbol_kind(symbol.kind),
+                    tags: None,
+                    deprecated: None,
+                    location,
+                    container_name: symbol.container,
+                });
+            }
+        }
+        Ok(Some(symbols))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let tokens = self.semantic_tokens(params.text_document.uri, None).await;
+        Ok(tokens.map(SemanticTokensResult::Tokens))
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>> {
+        let tokens = self
+            .semantic_tokens(params.text_document.uri, Some(params.range))
+            .await;
+        Ok(tokens.map(SemanticTokensRangeResult::Tokens))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&path) else {
+            return Ok(None);
+        };
+        let (Some(start), Some(end)) = (
+            to_offset(line_index, params.range.start),
+            to_offset(line_index, params.range.end),
+        ) else {
+            return Ok(None);
+        };
+        let options = self.config.read().unwrap().inlay_hints.into();
+        let hints = project
+            .inlay_hints(&path, Node::new(start, end), options)
+            .into_iter()
+            .map(|hint| {
+                let position = to_lsp_range_utf16(line_index, hint.offset, hint.offset).start;
+                // Return type hints are separated from the parameters like their annotations
+                let padding_left = hint.insert_text.as_ref().map(|text| text.starts_with(' '));
+                InlayHint {
+                    position,
+                    label: InlayHintLabel::String(hint.label),
+                    kind: Some(match hint.kind {
+                        ide::inlay_hints::InlayHintKind::Type => InlayHintKind::TYPE,
+                        ide::inlay_hints::InlayHintKind::Parameter => InlayHintKind::PARAMETER,
+                    }),
+                    // Double clicking a hint applies its edits
+                    text_edits: hint.insert_text.map(|new_text| {
+                        vec![TextEdit {
+                            range: Range::new(position, position),
+                            new_text,
+                        }]
+                    }),
+                    tooltip: None,
+                    padding_left,
+                    padding_right: Some(hint.kind == ide::inlay_hints::InlayHintKind::Parameter),
+                    data: None,
+                }
+            })
+            .collect();
+        Ok(Some(hints))
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&path) else {
+            return Ok(None);
+        };
+        let ranges = project
+            .folding_ranges(&path)
+            .into_iter()
+            .map(|folding_range| {
+                let range = to_lsp_range_utf16(line_index, folding_range.start, folding_range.end);
+                FoldingRange {
+                    start_line: range.start.line,
+                    start_character: None,
+                    end_line: range.end.line,
+                    end_character: None,
+                    kind: match folding_range.kind {
+                        ide::folding_ranges::FoldingRangeKind::Docstring => {
+                            Some(FoldingRangeKind::Comment)
+                        }
+                        _ => None,
+                    },
+                    collapsed_text: None,
+                }
+            })
+            .collect();
+        Ok(Some(ranges))
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&path) else {
+            return Ok(None);
+        };
+        // Each position gets a range, the positions outside of the module only select themselves
+        let selections = params
+            .positions
+            .into_iter()
+            .map(|position| {
+                let ranges = match to_offset(line_index, position) {
+                    Some(offset) => project.selection_ranges(&path, offset),
+                    None => vec![],
+                };
+                let mut selection = SelectionRange {
+                    range: Range::new(position, position),
+                    parent: None,
+                };
+                for (index, range) in ranges.iter().rev().enumerate() {
+                    let range = to_lsp_range_utf16(line_index, range.start, range.end);
+                    selection = SelectionRange {
+                        range,
+                        parent: (index > 0).then(|| Box::new(selection)),
+                    };
+                }
+                selection
+            })
+            .collect();
+        Ok(Some(selections))
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let params = params.text_document_position_params;
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(offset) = project
+            .line_index(&path)
+            .and_then(|line_index| to_offset(line_index, params.position))
+        else {
+            return Ok(None);
+        };
+        let item = project
+            .prepare_call_hierarchy(&path, offset)
+            .and_then(|item| to_lsp_hierarchy_item(&project, &project.settings().root, item));
+        Ok(item.map(|item| vec![item]))
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        let item = params.item;
+        let Some(project) = self.hierarchy_project(&item.uri, item.data.as_ref()).await else {
+            return Ok(None);
+        };
+        let Some(item) = from_lsp_hierarchy_item(
+            &project,
+            item.name,
+            item.kind,
+            &item.uri,
+            item.range,
+            item.selection_range,
+        ) else {
+            return Ok(None);
+        };
+        // The ranges of the calls are in the callers
+        let calls = project
+            .incoming_calls(&item)
+            .into_iter()
+            .filter_map(|call| {
+                let line_index = project.line_index(&call.item.path)?;
+                let from_ranges = call
+                    .ranges
+                    .iter()
+                    .map(|range| to_lsp_range_utf16(line_index, range.start, range.end))
+                    .collect();
+                let from = to_lsp_hierarchy_item(&project, &project.settings().root, call.item)?;
+                Some(CallHierarchyIncomingCall { from, from_ranges })
+            })
+            .collect();
+        Ok(Some(calls))
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        let item = params.item;
+        let Some(project) = self.hierarchy_project(&item.uri, item.data.as_ref()).await else {
+            return Ok(None);
+        };
+        let Some(item) = from_lsp_hierarchy_item(
+            &project,
+            item.name,
+            item.kind,
+            &item.uri,
+            item.range,
+            item.selection_range,
+        ) else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&item.path) else {
+            return Ok(None);
+        };
+        // The ranges of the calls are in the item
+        let calls = project
+            .outgoing_calls(&item)
+            .into_iter()
+            .filter_map(|call| {
+                let from_ranges = call
+                    .ranges
+                    .iter()
+                    .map(|range| to_lsp_range_utf16(line_index, range.start, range.end))
+                    .collect();
+                let to = to_lsp_hierarchy_item(&project, &project.settings().root, call.item)?;
+                Some(CallHierarchyOutgoingCall { to, from_ranges })
+            })
+            .collect();
+        Ok(Some(calls))
+    }
+
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let params = params.text_document_position_params;
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(offset) = project
+            .line_index(&path)
+            .and_then(|line_index| to_offset(line_index, params.position))
+        else {
+            return Ok(None);
+        };
+        let item = project
+            .prepare_type_hierarchy(&path, offset)
+            .and_then(|item| to_lsp_type_hierarchy_item(&project, &project.settings().root, item));
+        Ok(item.map(|item| vec![item]))
+    }
+
+    async fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let item = params.item;
+        let Some(project) = self.hierarchy_project(&item.uri, item.data.as_ref()).await else {
+            return Ok(None);
+        };
+        let Some(item) = from_lsp_hierarchy_item(
+            &project,
+            item.name,
+            item.kind,
+            &item.uri,
+            item.range,
+            item.selection_range,
+        ) else {
+            return Ok(None);
+        };
+        let items = project
+            .supertypes(&item)
+            .into_iter()
+            .filter_map(|item| to_lsp_type_hierarchy_item(&project, &project.settings().root, item))
+            .collect();
+        Ok(Some(items))
+    }
+
+    async fn subtypes(
+        &self,
+        params: TypeHierarchySubtypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let item = params.item;
+        let Some(project) = self.hierarchy_project(&item.uri, item.data.as_ref()).await else {
+            return Ok(None);
+        };
+        let Some(item) = from_lsp_hierarchy_item(
+            &project,
+            item.name,
+            item.kind,
+            &item.uri,
+            item.range,
+            item.selection_range,
+        ) else {
+            return Ok(None);
+        };
+        let items = project
+            .subtypes(&item)
+            .into_iter()
+            .filter_map(|item| to_lsp_type_hierarchy_item(&project, &project.settings().root, item))
+            .collect();
+        Ok(Some(items))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.checked_project(&uri, &path, None).await else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&path) else {
+            return Ok(None);
+        };
+        let (Some(start), Some(end)) = (
+            to_offset(line_index, params.range.start),
+            to_offset(line_index, params.range.end),
+        ) else {
+            return Ok(None);
+        };
+        let mut actions = vec![];
+        for err in project.get_file_errors(&path) {
+            // The errors that touch the range, like the one under the cursor
+            if (err.end as usize) < start || end < err.start as usize {
+                continue;
+            }
+            let fixes = project.fixes(&err);
+            let diagnostic = to_lsp_diagnostic(&project, err);
+            for fix in fixes {
+                let edits = fix
+                    .edits
+                    .into_iter()
+                    .map(|edit| TextEdit {
+                        range: to_lsp_range_utf16(line_index, edit.start, edit.end),
+                        new_text: edit.new_text,
+                    })
+                    .collect();
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: fix.title,
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri.clone(), edits)])),
+                        ..WorkspaceEdit::default()
+                    }),
+                    ..CodeAction::default()
+                }));
+            }
+        }
+        Ok(Some(actions))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let uri = params.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(line_index) = project.line_index(&path) else {
+            return Ok(None);
+        };
+        let Some(offset) = to_offset(line_index, params.position) else {
+            return Ok(None);
+        };
+        let location = project
+            .prepare_rename(&path, offset)
+            .map_err(|err| Error::invalid_params(err.to_string()))?;
+        Ok(Some(PrepareRenameResponse::Range(to_lsp_range_utf16(
+            line_index,
+            location.start,
+            location.end,
+        ))))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
+        let Some(project) = self.built_project(&uri, &path).await else {
+            return Ok(None);
+        };
+        let Some(offset) = project
+            .line_index(&path)
+            .and_then(|line_index| to_offset(line_index, position.position))
+        else {
+            return Ok(None);
+        };
+        let edits = project
+            .rename(&path, offset, &params.new_name)
+            .map_err(|err| Error::invalid_params(err.to_string()))?;
+        let mut changes = HashMap::new();
+        // A rename that leaves out a file would break the code, so the whole rename fails
+        for (path, edits) in edits {
+            let (Ok(uri), Some(line_index)) =
+                (Url::from_file_path(&path), project.line_index(&path))
+            else {
+                return Err(Error {
+                    code: ErrorCode::InternalError,
+                    message: format!("cannot rename in {}", path.display()).into(),
+                    data: None,
+                });
+            };
+            let edits = edits
+                .into_iter()
+                .map(|edit| TextEdit {
+                    range: to_lsp_range_utf16(line_index, edit.start, edit.end),
+                    new_text: edit.new_text,
+                })
+                .collect();
+            changes.insert(uri, edits);
         }
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        }))
     }
 
     async fn diagnostic(
         &self,
         params: DocumentDiagnosticParams,
     ) -> Result<DocumentDiagnosticReportResult> {
-        self.client
-            .log_message(MessageType::INFO, "diagnostic!")
-            .await;
         let uri = params.text_document.uri;
-        let path = uri.to_file_path();
-
+        let Ok(path) = uri.to_file_path() else {
+            return Err(Error::invalid_params(format!("not a file: {}", uri)));
+        };
         info!("diagnostic: {:?}", path);
-        match path {
-            Ok(path) => {
-                let diagnostics = self.check_file(&path).await;
-                info!("diagnostics: {:?}", diagnostics);
-                Ok(DocumentDiagnosticReportResult::Report(
-                    DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
-                        related_documents: None,
-                        full_document_diagnostic_report: FullDocumentDiagnosticReport {
-                            result_id: None,
-                            items: diagnostics,
-                        },
-                    }),
-                ))
+        let diagnostics = self.check_file(&uri, &path, None).await.unwrap_or_default();
+        let report = match document_report(diagnostics, params.previous_result_id.as_deref()) {
+            DocumentReport::Full(report) => {
+                DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                    related_documents: None,
+                    full_document_diagnostic_report: report,
+                })
             }
-            Err(_) => Ok(DocumentDiagnosticReportResult::Report(
+            DocumentReport::Unchanged(report) => {
                 DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
                     related_documents: None,
-                    unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
-                        result_id: "typechecker".to_string(),
-                    },
-                }),
-            )),
+                    unchanged_document_diagnostic_report: report,
+                })
+            }
+        };
+        Ok(DocumentDiagnosticReportResult::Report(report))
+    }
+
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> Result<WorkspaceDiagnosticReportResult> {
+        let previous_result_ids = params
+            .previous_result_ids
+            .into_iter()
+            .map(|previous| (previous.uri, previous.value))
+            .collect::<HashMap<Url, String>>();
+        let roots = self
+            .projects
+            .iter()
+            .map(|project| project.key().clone())
+            .collect::<Vec<_>>();
+        let mut items = vec![];
+        for root in roots {
+            self.add_project_files(&root).await;
+            let Some(project) = self.check_project(&root, None).await else {
+                continue;
+            };
+            let mut paths = project.checked_files();
+            paths.sort();
+            for path in paths {
+                let Ok(uri) = Url::from_file_path(&path) else {
+                    continue;
+                };
+                let diagnostics = project
+                    .get_file_errors(&path)
+                    .into_iter()
+                    .map(|err| to_lsp_diagnostic(&project, err))
+                    .collect();
+                let version = self
+                    .documents
+                    .get(&uri)
+                    .map(|document| i64::from(document.version));
+                let previous_result_id = previous_result_ids.get(&uri).map(String::as_str);
+                items.push(match document_report(diagnostics, previous_result_id) {
+                    DocumentReport::Full(report) => WorkspaceDocumentDiagnosticReport::Full(
+                        WorkspaceFullDocumentDiagnosticReport {
+                            uri,
+                            version,
+                            full_document_diagnostic_report: report,
+                        },
+                    ),
+                    DocumentReport::Unchanged(report) => {
+                        WorkspaceDocumentDiagnosticReport::Unchanged(
+                            WorkspaceUnchangedDocumentDiagnosticReport {
+                                uri,
+                                version,
+                                unchanged_document_diagnostic_report: report,
+                            },
+                        )
+                    }
+                });
+            }
         }
+        Ok(WorkspaceDiagnosticReportResult::Report(
+            WorkspaceDiagnosticReport { items },
+        ))
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -179,6 +2232,22 @@ async fn main() {
 
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
-    let (service, socket) = LspService::new(|client| Backend { client });
+    let (service, socket) = LspService::build(|client| Backend {
+        client,
+        documents: Arc::new(DashMap::new()),
+        projects: Arc::new(DashMap::new()),
+        folders: Arc::new(RwLock::new(vec![])),
+        config: Arc::new(RwLock::new(Config::default())),
+        client_capabilities: Arc::new(RwLock::new(ClientCapabilities::default())),
+        progress: Arc::new(DashMap::new()),
+        next_progress_id: Arc::new(AtomicU32::new(0)),
+        delayed_checks: Arc::new(DashMap::new()),
+        file_watchers: Arc::new(tokio::sync::Mutex::new(None)),
+    })
+    .custom_method(
+        "window/workDoneProgress/cancel",
+        Backend::work_done_progress_cancel,
+    )
+    .finish();
     Server::new(stdin, stdout, socket).serve(service).await;
 }
@@ -1,7 +1,11 @@
 use miette::{self, Diagnostic};
 use thiserror::{self, Error};
 
-#[derive(Debug, Error, Diagnostic)]
-#[error("Syntax error {0}")]
+#[derive(Debug, Clone, Error, Diagnostic)]
+#[error("Syntax error {msg}")]
 #[diagnostic()]
-pub struct SyntaxError(pub &'static str);
+pub struct SyntaxError {
+    pub msg: String,
+    #[label("here")]
+    pub span: (usize, usize),
+}
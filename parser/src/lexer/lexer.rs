@@ -29,6 +29,8 @@ pub struct Lexer {
 
     // TODO: Hacky way to handle emitting multiple de indents
     next_token_is_dedent: u8,
+    /// Errors that the lexer recovered from, the tokens are still produced
+    pub errors: Vec<SyntaxError>,
 }
 
 impl Lexer {
@@ -42,6 +44,7 @@ impl Lexer {
             fstring_stack: vec![],
             inside_fstring_bracket: 0,
             next_token_is_dedent: 0,
+            errors: vec![],
         }
     }
 
@@ -56,7 +59,17 @@ impl Lexer {
             });
         }
         let start = self.current;
-        let kind = self.next_kind()?;
+        let kind = self
+            .next_kind()
+            .map_err(|err| match err.downcast::<SyntaxError>() {
+                // Errors cover the whole token
+                Ok(err) => SyntaxError {
+                    msg: err.msg,
+                    span: (start, self.current),
+                }
+                .into(),
+                Err(err) => err,
+            })?;
 
         // Ignore whitespace
         if kind == Kind::WhiteSpace {
@@ -82,6 +95,7 @@ impl Lexer {
         let start_of_line = self.start_of_line;
         let inside_fstring_bracket = self.inside_fstring_bracket;
         let next_token_is_dedent = self.next_token_is_dedent;
+        let errors_len = self.errors.len();
         let token = self.next_token();
         self.current = current;
         self.nesting = nesting;
@@ -89,6 +103,7 @@ impl Lexer {
         self.start_of_line = start_of_line;
         self.inside_fstring_bracket = inside_fstring_bracket;
         self.next_token_is_dedent = next_token_is_dedent;
+        self.errors.truncate(errors_len);
         token
     }
 
@@ -103,14 +118,15 @@ impl Lexer {
             // and let the other tokens be matched
             return None;
         }
+        // The quotes that end the innermost fstring, like `"` or `'''`
+        let quotes = self.fstring_stack.last()?.chars().collect::<Vec<_>>();
         let mut consumed_str_in_fstring = String::new();
         while let Some(curr) = self.next() {
-            let str_finisher = self.fstring_stack.last().unwrap();
             match curr {
                 '{' => {
                     if self.peek() == Some('{') {
                         consumed_str_in_fstring.push(curr);
-                        consumed_str_in_fstring.push(self.peek().unwrap());
+                        consumed_str_in_fstring.push('{');
                         self.double_next();
                         continue;
                     }
@@ -119,17 +135,17 @@ impl Lexer {
                 }
                 _ => {}
             }
-            match str_finisher.len() {
+            match quotes.len() {
                 1 => {
-                    if curr == str_finisher.chars().next().unwrap() {
+                    if curr == quotes[0] {
                         self.fstring_stack.pop();
                         return Some(Kind::FStringEnd);
                     }
                 }
                 3 => {
-                    if curr == str_finisher.chars().next().unwrap()
-                        && self.peek() == Some(str_finisher.chars().nth(1).unwrap())
-                        && self.double_peek() == Some(str_finisher.chars().nth(2).unwrap())
+                    if curr == quotes[0]
+                        && self.peek() == Some(quotes[1])
+                        && self.double_peek() == Some(quotes[2])
                     {
                         self.fstring_stack.pop();
                         self.double_next();
@@ -148,16 +164,16 @@ impl Lexer {
                 consumed_str_in_fstring.push(curr);
                 continue;
             }
-            match str_finisher.len() {
+            match quotes.len() {
                 1 => {
-                    if peeked_char == str_finisher.chars().next().unwrap() {
+                    if peeked_char == quotes[0] {
                         return Some(Kind::FStringMiddle);
                     }
                 }
                 3 => {
-                    if peeked_char == str_finisher.chars().next().unwrap()
-                        && self.double_peek() == Some(str_finisher.chars().nth(1).unwrap())
-                        && self.triple_peek() == Some(str_finisher.chars().nth(2).unwrap())
+                    if peeked_char == quotes[0]
+                        && self.double_peek() == Some(quotes[1])
+                        && self.triple_peek() == Some(quotes[2])
                     {
                         return Some(Kind::FStringMiddle);
                     }
@@ -613,57 +629,45 @@ impl Lexer {
         }
 
         if !string_terminated {
-            Err(SyntaxError("String not terminated").into())
+            Err(SyntaxError {
+                msg: "String not terminated".to_string(),
+                span: (self.current, self.current),
+            }
+            .into())
         } else {
             Ok(())
         }
     }
 
     fn match_numeric_literal(&mut self) -> Kind {
+        // The first digit is already consumed
+        let start = self.current - 1;
         match self.peek() {
             Some('b') | Some('B') => {
                 self.next();
-                while let Some(c) = self.peek() {
-                    match c {
-                        '0' | '1' => {
-                            self.next();
-                        }
-                        '_' => {
-                            self.next();
-                        }
-                        _ => panic!("Binary literals must be 0 or 1"),
-                    }
-                }
+                self.match_radix_digits(
+                    start,
+                    |c| matches!(c, '0' | '1'),
+                    "Binary literals must be 0 or 1",
+                );
                 return Kind::Binary;
             }
             Some('o') | Some('O') => {
                 self.next();
-                while let Some(c) = self.peek() {
-                    match c {
-                        '0'..='7' => {
-                            self.next();
-                        }
-                        '_' => {
-                            self.next();
-                        }
-                        _ => panic!("Octal literals must be 0-7"),
-                    }
-                }
+                self.match_radix_digits(
+                    start,
+                    |c| matches!(c, '0'..='7'),
+                    "Octal literals must be 0-7",
+                );
                 return Kind::Octal;
             }
             Some('x') | Some('X') => {
                 self.next();
-                while let Some(c) = self.peek() {
-                    match c {
-                        '0'..='9' | 'a'..='f' | 'A'..='F' => {
-                            self.next();
-                        }
-                        '_' => {
-                            self.next();
-                        }
-                        _ => panic!("Hexadecimal literals must be 0-9, a-f, A-F"),
-                    }
-                }
+                self.match_radix_digits(
+                    start,
+                    |c| c.is_ascii_hexdigit(),
+                    "Hexadecimal literals must be 0-9, a-f, A-F",
+                );
                 return Kind::Hexadecimal;
             }
             _ => {}
@@ -686,12 +690,7 @@ impl Lexer {
                             'e' | 'E' => {
                                 has_exponent = true;
                                 self.next();
-                                match self.peek() {
-                                    Some('+') | Some('-') => {
-                                        self.next();
-                                    }
-                                    _ => panic!("Exponent must be + or -"),
-                                }
+                                self.match_exponent(start);
                             }
                             'j' | 'J' => {
                                 is_imaginary = true;
@@ -714,12 +713,7 @@ impl Lexer {
                 }
                 'e' | 'E' => {
                     self.next();
-                    match self.peek() {
-                        Some('+') | Some('-') => {
-                            self.next();
-                        }
-                        _ => {}
-                    }
+                    self.match_exponent(start);
                     while let Some(c) = self.peek() {
                         match c {
                             '0'..='9' | '_' => {
@@ -760,6 +754,42 @@ impl Lexer {
         Kind::Integer
     }
 
+    // Consumes the digits of a binary, octal or hexadecimal literal.
+    // Invalid digits are consumed as part of the literal and reported as an error.
+    fn match_radix_digits(&mut self, start: usize, is_digit: fn(char) -> bool, msg: &str) {
+        let mut has_digits = false;
+        let mut has_invalid_digits = false;
+        while let Some(c) = self.peek() {
+            if is_digit(c) {
+                has_digits = true;
+            } else if c.is_ascii_alphanumeric() {
+                has_invalid_digits = true;
+            } else if c != '_' {
+                break;
+            }
+            self.next();
+        }
+        if has_invalid_digits || !has_digits {
+            self.errors.push(SyntaxError {
+                msg: msg.to_string(),
+                span: (start, self.current),
+            });
+        }
+    }
+
+    // Consumes the optional sign of an exponent and checks that digits follow it
+    fn match_exponent(&mut self, start: usize) {
+        if matches!(self.peek(), Some('+') | Some('-')) {
+            self.next();
+        }
+        if !matches!(self.peek(), Some('0'..='9')) {
+            self.errors.push(SyntaxError {
+                msg: "Exponent must be followed by digits".to_string(),
+                span: (start, self.current),
+            });
+        }
+    }
+
     fn match_indentation(&mut self) -> Option<Kind> {
         use std::cmp::Ordering;
         let mut spaces_count = 0;
@@ -855,7 +885,13 @@ impl Lexer {
                             de_indents += 1;
                         }
                         Ordering::Equal => break,
-                        Ordering::Less => panic!("Invalid indentation, current indentation is {} which is less than previous {}", spaces_count, top),
+                        Ordering::Less => {
+                            self.errors.push(SyntaxError {
+                                msg: format!("Invalid indentation, current indentation is {} which is less than previous {}", spaces_count, top),
+                                span: (self.current - kind_value.len(), self.current),
+                            });
+                            break;
+                        }
                     }
                 }
                 if de_indents != 1 {
@@ -1238,7 +1274,6 @@ def",
     }
 
     #[test]
-    #[should_panic]
     fn test_unexpected_indentation() {
         let mut lexer = Lexer::new(
             "if True:
@@ -1251,5 +1286,32 @@ def",
                 break;
             }
         }
+        assert_eq!(lexer.errors.len(), 1);
+        assert_eq!(lexer.errors[0].span, (22, 26));
+    }
+
+    #[test]
+    fn test_invalid_numeric_literals() {
+        for (source, msg) in [
+            ("0b102", "Binary literals must be 0 or 1"),
+            ("0o78", "Octal literals must be 0-7"),
+            ("0xfg", "Hexadecimal literals must be 0-9, a-f, A-F"),
+            ("0x", "Hexadecimal literals must be 0-9, a-f, A-F"),
+            ("1.5e", "Exponent must be followed by digits"),
+            ("1e+", "Exponent must be followed by digits"),
+        ] {
+            let mut lexer = Lexer::new(source);
+            let token = lexer.next_token().unwrap();
+            assert_eq!(token.end, source.len());
+            assert_eq!(lexer.errors.len(), 1, "{}", source);
+            assert_eq!(lexer.errors[0].msg, msg);
+            assert_eq!(lexer.errors[0].span, (0, source.len()));
+        }
+
+        for source in ["0b1 ", "0o7)", "0xff\n", "1e10", "1.5e-3", "1_000.5E+2j"] {
+            let mut lexer = Lexer::new(source);
+            lexer.next_token().unwrap();
+            assert!(lexer.errors.is_empty(), "{}", source);
+        }
     }
 }
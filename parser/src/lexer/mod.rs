@@ -1,2 +1,2 @@
-mod diagnostics;
+pub mod diagnostics;
 pub mod lexer;
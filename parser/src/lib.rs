@@ -1,7 +1,10 @@
 mod lexer;
+pub mod line_index;
 mod parser;
 
 pub use crate::lexer::lexer::Lexer;
+pub use crate::line_index::LineIndex;
 pub use crate::parser::ast;
+pub use crate::parser::error;
 pub use crate::parser::parser::Parser;
 pub mod token;
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+/// Zero based line and column of a position in the source.
+/// Depending on the method that created it, the column is counted in UTF-8 bytes
+/// or in UTF-16 code units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+/// Start and end of a span of the source as line and column positions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LineColRange {
+    pub start: LineCol,
+    pub end: LineCol,
+}
+
+// A character that takes more than one byte in UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WideChar {
+    // UTF-8 column of the first byte of the character
+    start: u32,
+    len_utf8: u32,
+}
+
+impl WideChar {
+    fn len_utf16(&self) -> u32 {
+        // Only the characters that take 4 bytes in UTF-8 are outside the basic multilingual plane
+        if self.len_utf8 == 4 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Maps byte offsets of a source file to lines and columns and back.
+///
+/// Lines end with `\n`, `\r\n` or `\r`. Lookups are a binary search over the
+/// start of the lines, UTF-16 columns are only computed for the lines that contain
+/// non-ASCII characters.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineIndex {
+    // Byte offset of the start of each line
+    line_starts: Vec<u32>,
+    // Byte offset of the end of each line, excluding the line terminator
+    line_ends: Vec<u32>,
+    // Multi-byte characters of each line
+    wide_chars: HashMap<u32, Vec<WideChar>>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut line_ends = vec![];
+        let mut wide_chars: HashMap<u32, Vec<WideChar>> = HashMap::new();
+
+        let mut chars = source.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            let line_start = *line_starts.last().unwrap();
+            match c {
+                '\n' | '\r' => {
+                    line_ends.push(offset as u32);
+                    let mut next_line = offset + 1;
+                    if c == '\r' && matches!(chars.peek(), Some((_, '\n'))) {
+                        chars.next();
+                        next_line += 1;
+                    }
+                    line_starts.push(next_line as u32);
+                }
+                c if !c.is_ascii() => {
+                    wide_chars
+                        .entry(line_starts.len() as u32 - 1)
+                        .or_default()
+                        .push(WideChar {
+                            start: offset as u32 - line_start,
+                            len_utf8: c.len_utf8() as u32,
+                        });
+                }
+                _ => {}
+            }
+        }
+        line_ends.push(source.len() as u32);
+
+        Self {
+            line_starts,
+            line_ends,
+            wide_chars,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the byte offset of the start of the zero based line
+    pub fn line_start(&self, line: u32) -> Option<usize> {
+        self.line_starts.get(line as usize).map(|&s| s as usize)
+    }
+
+    /// Returns the line and UTF-8 column of a byte offset.
+    /// Offsets past the end of the source are clamped to the end.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = (offset as u32).min(*self.line_ends.last().unwrap());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        LineCol {
+            line: line as u32,
+            col: offset - self.line_starts[line],
+        }
+    }
+
+    /// Returns the line and UTF-16 column of a byte offset
+    pub fn line_col_utf16(&self, offset: usize) -> LineCol {
+        self.to_utf16(self.line_col(offset))
+    }
+
+    pub fn range(&self, start: usize, end: usize) -> LineColRange {
+        LineColRange {
+            start: self.line_col(start),
+            end: self.line_col(end),
+        }
+    }
+
+    pub fn range_utf16(&self, start: usize, end: usize) -> LineColRange {
+        LineColRange {
+            start: self.line_col_utf16(start),
+            end: self.line_col_utf16(end),
+        }
+    }
+
+    /// Returns the byte offset of a line and UTF-8 column.
+    /// Columns past the end of the line are clamped to the end of the line,
+    /// `None` is returned if the line does not exist.
+    pub fn offset(&self, line_col: LineCol) -> Option<usize> {
+        let start = *self.line_starts.get(line_col.line as usize)?;
+        let end = self.line_ends[line_col.line as usize];
+        Some((start + line_col.col).min(end) as usize)
+    }
+
+    /// Returns the byte offset of a line and UTF-16 column
+    pub fn offset_utf16(&self, line_col: LineCol) -> Option<usize> {
+        self.offset(self.to_utf8(line_col))
+    }
+
+    /// Converts the UTF-8 column of a position to a UTF-16 column
+    pub fn to_utf16(&self, line_col: LineCol) -> LineCol {
+        let mut col = line_col.col;
+        if let Some(chars) = self.wide_chars.get(&line_col.line) {
+            for c in chars {
+                if c.start + c.len_utf8 > line_col.col {
+                    break;
+                }
+                col -= c.len_utf8 - c.len_utf16();
+            }
+        }
+        LineCol {
+            line: line_col.line,
+            col,
+        }
+    }
+
+    /// Converts the UTF-16 column of a position to a UTF-8 column
+    pub fn to_utf8(&self, line_col: LineCol) -> LineCol {
+        let mut col = line_col.col;
+        if let Some(chars) = self.wide_chars.get(&line_col.line) {
+            for c in chars {
+                if c.start >= col {
+                    break;
+                }
+                col += c.len_utf8 - c.len_utf16();
+            }
+        }
+        LineCol {
+            line: line_col.line,
+            col,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lc(line: u32, col: u32) -> LineCol {
+        LineCol { line, col }
+    }
+
+    #[test]
+    fn test_line_col() {
+        let index = LineIndex::new("a = 1\nb = 2\r\n\rc");
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_col(0), lc(0, 0));
+        assert_eq!(index.line_col(5), lc(0, 5));
+        assert_eq!(index.line_col(6), lc(1, 0));
+        assert_eq!(index.line_col(10), lc(1, 4));
+        assert_eq!(index.line_col(13), lc(2, 0));
+        assert_eq!(index.line_col(14), lc(3, 0));
+        assert_eq!(index.line_col(100), lc(3, 1));
+
+        assert_eq!(index.offset(lc(1, 4)), Some(10));
+        assert_eq!(index.offset(lc(1, 100)), Some(11));
+        assert_eq!(index.offset(lc(3, 1)), Some(15));
+        assert_eq!(index.offset(lc(4, 0)), None);
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        // é takes 2 bytes and 1 UTF-16 unit, 😀 takes 4 bytes and 2 UTF-16 units
+        let source = "x = 1\ns = \"é😀\" + y\n";
+        let index = LineIndex::new(source);
+        let y = source.find('y').unwrap();
+        assert_eq!(index.line_col(y), lc(1, 15));
+        assert_eq!(index.line_col_utf16(y), lc(1, 12));
+        assert_eq!(index.offset_utf16(lc(1, 12)), Some(y));
+
+        let emoji = source.find('😀').unwrap();
+        assert_eq!(index.line_col_utf16(emoji), lc(1, 6));
+        assert_eq!(index.offset_utf16(lc(1, 6)), Some(emoji));
+        assert_eq!(index.line_col_utf16(emoji + 4), lc(1, 8));
+        assert_eq!(index.offset_utf16(lc(1, 8)), Some(emoji + 4));
+
+        // ASCII lines are not affected
+        assert_eq!(index.line_col_utf16(4), lc(0, 4));
+        assert_eq!(index.offset_utf16(lc(0, 4)), Some(4));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let source = "def f(ä: int):\n    return \"𝔘\" * ä\n\n# ñ\n";
+        let index = LineIndex::new(source);
+        for (offset, _) in source.char_indices() {
+            let line_col = index.line_col(offset);
+            assert_eq!(index.offset(line_col), Some(offset));
+            let line_col = index.line_col_utf16(offset);
+            assert_eq!(index.offset_utf16(line_col), Some(offset));
+        }
+    }
+}
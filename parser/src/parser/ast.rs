@@ -1,6 +1,6 @@
 use miette::{SourceOffset, SourceSpan};
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)] // #[serde(tag = "type")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)] // #[serde(tag = "type")]
 pub struct Node {
     /// Start offset in source
     pub start: usize,
@@ -67,6 +67,8 @@ pub enum Statement {
     AsyncFunctionDef(AsyncFunctionDef),
     ClassDef(ClassDef),
     Match(Match),
+    /// A statement that could not be parsed
+    Invalid(Invalid),
 }
 
 impl GetNode for Statement {
@@ -99,6 +101,7 @@ impl GetNode for Statement {
             Statement::AsyncFunctionDef(s) => s.node,
             Statement::ClassDef(s) => s.node,
             Statement::Match(s) => s.node,
+            Statement::Invalid(s) => s.node,
         }
     }
 }
@@ -156,6 +159,13 @@ pub struct Pass {
     pub node: Node,
 }
 
+// Placeholder for source code with syntax errors.
+// The node covers the source that was skipped while recovering from the error.
+#[derive(Debug, Clone)]
+pub struct Invalid {
+    pub node: Node,
+}
+
 #[derive(Debug, Clone)]
 pub struct Delete {
     pub node: Node,
@@ -255,6 +265,8 @@ pub enum Expression {
     IfExp(Box<IfExp>),
     JoinedStr(Box<JoinedStr>),
     FormattedValue(Box<FormattedValue>),
+    /// An expression that could not be parsed
+    Invalid(Box<Invalid>),
 }
 
 impl GetNode for Expression {
@@ -287,6 +299,7 @@ impl GetNode for Expression {
             Expression::IfExp(i) => i.node,
             Expression::JoinedStr(j) => j.node,
             Expression::FormattedValue(f) => f.node,
+            Expression::Invalid(i) => i.node,
         }
     }
 }
@@ -1,6 +1,8 @@
-use std::{panic, vec};
+use std::vec;
 
+use crate::lexer::diagnostics::SyntaxError;
 use crate::lexer::lexer::Lexer;
+use crate::line_index::LineIndex;
 use crate::parser::ast::*;
 use crate::parser::string::{extract_string_inside, is_string};
 use crate::token::{Kind, Token, TokenValue};
@@ -31,57 +33,41 @@ pub struct Parser {
     curr_line_string: String,
     curr_line_number: u32,
     path: String,
+    // Maps the byte offsets of the nodes and errors to lines and columns
+    pub line_index: LineIndex,
 }
 
 #[allow(unused)]
 impl Parser {
     pub fn new(source: String, path: String) -> Self {
-        let mut lexer = Lexer::new(&source);
-        let cur_token = lexer.next_token().unwrap();
+        let lexer = Lexer::new(&source);
+        let line_index = LineIndex::new(&source);
         let prev_token_end = 0;
 
-        Self {
+        let mut parser = Self {
             source,
             lexer,
-            cur_token,
+            cur_token: Token {
+                kind: Kind::Eof,
+                value: TokenValue::None,
+                start: 0,
+                end: 0,
+            },
             prev_token_end,
             nested_expression_list: 0,
             errors: vec![],
             curr_line_string: String::new(),
             path,
             curr_line_number: 1,
-        }
+            line_index,
+        };
+        parser.cur_token = parser.next_token();
+        parser
     }
 
     pub fn parse(&mut self) -> Module {
         let node = self.start_node();
-        let mut body = vec![];
-        while self.cur_kind() != Kind::Eof {
-            // TODO: comments can be parsed and used
-            // Also comments can be everywhere
-            if self.eat(Kind::Comment) {
-                continue;
-            }
-            if self.at(Kind::NewLine) {
-                self.bump_any();
-                continue;
-            }
-            let stmt = if is_at_compound_statement(self.cur_token()) {
-                self.parse_compount_statement()
-            } else {
-                self.parse_simple_statement()
-            };
-            if stmt.is_ok() {
-                body.push(stmt.unwrap());
-            } else {
-                self.errors.push(stmt.err().unwrap());
-                self.bump_any();
-            }
-        }
-
-        for err in &self.errors {
-            println!("{:#?}", err);
-        }
+        let body = self.parse_statements(true);
 
         Module {
             node: self.finish_node(node),
@@ -157,7 +143,7 @@ impl Parser {
 
     /// Move to the next token
     fn advance(&mut self) {
-        let token = self.lexer.next_token();
+        let token = self.next_token();
         if self.at(Kind::NewLine) {
             self.curr_line_string.clear();
             self.curr_line_number += 1;
@@ -165,53 +151,84 @@ impl Parser {
             self.curr_line_string
                 .push_str(&self.source[self.prev_token_end..self.cur_token.end]);
         }
-        match token {
-            Err(err) => {
-                self.bump_any();
-            }
-            Ok(token) => {
-                self.prev_token_end = self.cur_token.end;
-                self.cur_token = token;
-                if self.cur_kind() == Kind::Comment {
-                    self.bump(Kind::Comment);
+        self.prev_token_end = self.cur_token.end;
+        self.cur_token = token;
+        if self.cur_kind() == Kind::Comment {
+            self.bump(Kind::Comment);
+        }
+    }
+
+    /// Reads the next token from the lexer and records the lexer errors.
+    /// Tokens that the lexer cannot produce, e.g. unterminated strings, are skipped.
+    fn next_token(&mut self) -> Token {
+        loop {
+            let token = self.lexer.next_token();
+            for err in std::mem::take(&mut self.lexer.errors) {
+                let err = self.syntax_error(err.msg, err.span);
+                self.errors.push(err);
+            }
+            match token {
+                Ok(token) => return token,
+                Err(err) => {
+                    let err = match err.downcast::<SyntaxError>() {
+                        Ok(err) => self.syntax_error(err.msg, err.span),
+                        Err(err) => self.syntax_error(
+                            err.to_string(),
+                            (self.cur_token.end, self.cur_token.end),
+                        ),
+                    };
+                    self.errors.push(err);
                 }
             }
         }
     }
 
-    fn advance_to_next_line_or_semicolon(&mut self) {
-        while !self.eat(Kind::NewLine) && !self.eat(Kind::SemiColon) && !self.at(Kind::Eof) {
-            self.advance();
+    fn syntax_error(&self, msg: String, span: (usize, usize)) -> ParsingError {
+        ParsingError::InvalidSyntax {
+            path: Box::from(self.path.as_str()),
+            msg: Box::from(msg),
+            line: self.get_line_number_of_character_position(span.0),
+            input: self.curr_line_string.clone(),
+            advice: "".to_string(),
+            span,
         }
     }
 
+    /// Checks if the current token ends the statement
+    fn at_statement_end(&self) -> bool {
+        matches!(
+            self.cur_kind(),
+            Kind::NewLine | Kind::Dedent | Kind::Indent | Kind::Eof
+        )
+    }
+
     /// Expect a `Kind` or return error
     pub fn expect(&mut self, kind: Kind) -> Result<(), ParsingError> {
         if !self.at(kind) {
-            let found = self.cur_token.kind;
-            let node = self.start_node();
-            let range = self.finish_node(node);
-            let err = ParsingError::InvalidSyntax {
-                path: Box::from(self.path.as_str()),
-                msg: Box::from(format!("Expected {:?} but found {:?}", kind, found)),
-                line: self.curr_line_number,
-                input: self.curr_line_string.clone(),
-                advice: "maybe you forgot to put this character".to_string(),
-                span: (range.start, range.end),
-            };
-            self.advance_to_next_line_or_semicolon();
-            return Err(err);
+            return Err(self.expected_token_error(kind));
         }
         self.bump_any();
         Ok(())
     }
 
+    fn expected_token_error(&self, kind: Kind) -> ParsingError {
+        let found = self.cur_token.kind;
+        let span = (self.cur_token.start, self.cur_token.end);
+        ParsingError::InvalidSyntax {
+            path: Box::from(self.path.as_str()),
+            msg: Box::from(format!("Expected {:?} but found {:?}", kind, found)),
+            line: self.get_line_number_of_character_position(span.0),
+            input: self.curr_line_string.clone(),
+            advice: "maybe you forgot to put this character".to_string(),
+            span,
+        }
+    }
+
     /// Expect any of `Kinds` or return error
     pub fn expect_any(&mut self, kind: Vec<Kind>) -> Result<(), ParsingError> {
         if !kind.contains(&self.cur_token.kind) {
             let found = self.cur_token.kind;
-            let node = self.start_node();
-            let range = self.finish_node(node);
+            let span = (self.cur_token.start, self.cur_token.end);
             let mut expected = String::new();
             for kind in kind {
                 expected.push_str(&format!("{:?}, ", kind));
@@ -222,12 +239,11 @@ impl Parser {
                     "Expected one of {:?} but found {:?}",
                     expected, found
                 )),
-                line: self.curr_line_number,
+                line: self.get_line_number_of_character_position(span.0),
                 input: self.curr_line_string.clone(),
                 advice: "maybe you forgot to put this character".to_string(),
-                span: (range.start, range.end),
+                span,
             };
-            self.advance_to_next_line_or_semicolon();
             return Err(err);
         }
         self.bump_any();
@@ -235,8 +251,12 @@ impl Parser {
     }
 
     fn unepxted_token(&mut self, node: Node, kind: Kind) -> Result<(), ParsingError> {
-        self.bump_any();
+        // Tokens that end the statement are left for the statement parser to recover from the error
+        if !self.at_statement_end() {
+            self.bump_any();
+        }
         let range = self.finish_node(node);
+        let range = Node::new(range.start, range.end.max(range.start));
         let line_number = self.get_line_number_of_character_position(range.start);
         let err = ParsingError::InvalidSyntax {
             path: Box::from(self.path.as_str()),
@@ -252,8 +272,11 @@ impl Parser {
     // write this like the expect function
     fn unexpected_token_new(&mut self, node: Node, kinds: Vec<Kind>, advice: &str) -> ParsingError {
         let curr_kind = self.cur_kind();
-        self.bump_any();
+        if !self.at_statement_end() {
+            self.bump_any();
+        }
         let range = self.finish_node(node);
+        let range = Node::new(range.start, range.end.max(range.start));
         let line_number = self.curr_line_number;
         let mut expected = String::new();
         for kind in kinds {
@@ -263,8 +286,7 @@ impl Parser {
             path: Box::from(self.path.as_str()),
             msg: Box::from(format!(
                 "Expected one of {:?} but found {:?}",
-                expected,
-                self.cur_kind()
+                expected, curr_kind
             )),
             line: line_number,
             input: self.curr_line_string.clone(),
@@ -274,17 +296,9 @@ impl Parser {
         err
     }
 
+    // Returns the one based line number of a byte offset
     fn get_line_number_of_character_position(&self, pos: usize) -> u32 {
-        let mut line_number = 1;
-        for (i, c) in self.source.chars().enumerate() {
-            if i == pos {
-                break;
-            }
-            if c == '\n' {
-                line_number += 1;
-            }
-        }
-        line_number
+        self.line_index.line_col(pos).line + 1
     }
 
     fn parse_simple_statement(&mut self) -> Result<Statement, ParsingError> {
@@ -1218,16 +1232,15 @@ impl Parser {
     // https://docs.python.org/3/reference/compound_stmts.html#grammar-token-python-grammar-suite
     fn parse_suite(&mut self) -> Result<Vec<Statement>, ParsingError> {
         let stmts = if self.eat(Kind::NewLine) {
-            self.consume_whitespace_and_newline();
-            self.expect(Kind::Indent)?;
-            let mut stmts = vec![];
-            while !self.eat(Kind::Dedent) && !self.at(Kind::Eof) {
-                if self.eat(Kind::Comment) || self.consume_whitespace_and_newline() {
-                    continue;
-                }
-                let stmt = self.parse_statement()?;
-                stmts.extend(stmt);
-            }
+            while self.eat(Kind::Comment) || self.consume_whitespace_and_newline() {}
+            if !self.eat(Kind::Indent) {
+                // The compound statement is kept with an empty body
+                let err = self.expected_token_error(Kind::Indent);
+                self.errors.push(err);
+                return Ok(vec![]);
+            }
+            let stmts = self.parse_statements(false);
+            self.bump(Kind::Dedent);
             Ok(stmts)
         } else {
             let stmt = self.parse_statement_list()?;
@@ -1237,6 +1250,124 @@ impl Parser {
         stmts
     }
 
+    // Parses the statements of a block until its dedent, or the end of file for the top level block.
+    // A statement with syntax errors is replaced with an invalid statement
+    // and parsing continues from the next statement.
+    fn parse_statements(&mut self, top_level: bool) -> Vec<Statement> {
+        let mut stmts = vec![];
+        // Indents that do not start a block, their dedents do not end the block
+        let mut unexpected_indents = 0;
+        loop {
+            match self.cur_kind() {
+                Kind::Eof => break,
+                Kind::Dedent if unexpected_indents > 0 || top_level => {
+                    unexpected_indents = std::cmp::max(unexpected_indents, 1) - 1;
+                    self.bump_any();
+                    continue;
+                }
+                Kind::Dedent => break,
+                Kind::Comment | Kind::NewLine | Kind::WhiteSpace => {
+                    self.bump_any();
+                    continue;
+                }
+                Kind::Indent => {
+                    let span = (self.cur_token.start, self.cur_token.end);
+                    self.errors.push(ParsingError::InvalidSyntax {
+                        path: Box::from(self.path.as_str()),
+                        msg: Box::from("Unexpected indent"),
+                        line: self.get_line_number_of_character_position(span.0),
+                        input: self.curr_line_string.clone(),
+                        advice: "remove the extra indentation".to_string(),
+                        span,
+                    });
+                    unexpected_indents += 1;
+                    self.bump_any();
+                    continue;
+                }
+                _ => {}
+            }
+            let node = self.start_node();
+            match self.parse_statement() {
+                Ok(stmt) => stmts.extend(stmt),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize();
+                    stmts.push(Statement::Invalid(Invalid {
+                        node: self.finish_node(node),
+                    }));
+                }
+            }
+        }
+        stmts
+    }
+
+    // Skips the rest of a statement with syntax errors.
+    // When the statement is followed by an indented block, e.g. a function
+    // with an invalid signature, the block is skipped too.
+    fn synchronize(&mut self) {
+        loop {
+            match self.cur_kind() {
+                Kind::Eof | Kind::Dedent => return,
+                Kind::NewLine => {
+                    self.bump_any();
+                    break;
+                }
+                _ => self.bump_any(),
+            }
+        }
+        while self.eat(Kind::Comment) || self.consume_whitespace_and_newline() {}
+        if !self.at(Kind::Indent) {
+            return;
+        }
+        let mut depth = 0;
+        loop {
+            match self.cur_kind() {
+                Kind::Eof => return,
+                Kind::Indent => depth += 1,
+                Kind::Dedent => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.bump_any();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.bump_any();
+        }
+    }
+
+    // Skips the rest of an expression with syntax errors inside brackets,
+    // up to the next comma or the closing bracket
+    fn skip_to_next_item(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.cur_kind() {
+                Kind::Eof | Kind::NewLine | Kind::Dedent | Kind::Indent => return,
+                Kind::LeftParen | Kind::LeftBrace | Kind::LeftBracket => depth += 1,
+                Kind::RightParen | Kind::RightBrace | Kind::RightBracket => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1;
+                }
+                Kind::Comma if depth == 0 => return,
+                _ => {}
+            }
+            self.bump_any();
+        }
+    }
+
+    // Records the error and returns an invalid expression that covers the skipped tokens
+    fn recover_expression(&mut self, node: Node, err: ParsingError) -> Expression {
+        self.errors.push(err);
+        self.skip_to_next_item();
+        let end = std::cmp::max(self.prev_token_end, node.start);
+        Expression::Invalid(Box::new(Invalid {
+            node: Node::new(node.start, end),
+        }))
+    }
+
     // https://docs.python.org/3/reference/compound_stmts.html#grammar-token-python-grammar-statement
     fn parse_statement(&mut self) -> Result<Vec<Statement>, ParsingError> {
         if is_at_compound_statement(self.cur_token()) {
@@ -1253,6 +1384,10 @@ impl Parser {
         let stmt = self.parse_simple_statement()?;
         stmts.push(stmt);
         while self.eat(Kind::SemiColon) {
+            // A semicolon can end the line
+            if self.at_statement_end() {
+                break;
+            }
             let stmt = self.parse_simple_statement()?;
             stmts.push(stmt);
         }
@@ -1285,7 +1420,11 @@ impl Parser {
         let mut targets = vec![lhs];
         self.bump(Kind::Assign);
         let value = loop {
-            let rhs = self.parse_expression()?;
+            let rhs_node = self.start_node();
+            let rhs = match self.parse_expression() {
+                Ok(rhs) => rhs,
+                Err(err) => self.recover_expression(rhs_node, err),
+            };
             // if there's an assign after the expression we have multiple targets
             // like a = b = 1
             // so we add the rhs to the targets and continue parsing
@@ -1344,10 +1483,13 @@ impl Parser {
     // The value is either expression list or yield expression
     // https://docs.python.org/3/reference/simple_stmts.html#assignment-statements
     fn parse_assignment_value(&mut self) -> Result<Expression, ParsingError> {
-        if self.cur_kind() == Kind::Yield {
-            return self.parse_yield_expression();
-        }
-        self.parse_expression_list()
+        let node = self.start_node();
+        let value = if self.cur_kind() == Kind::Yield {
+            self.parse_yield_expression()
+        } else {
+            self.parse_expression_list()
+        };
+        Ok(value.unwrap_or_else(|err| self.recover_expression(node, err)))
     }
 
     fn parse_aug_assign_op(&mut self) -> Option<AugAssignOp> {
@@ -1490,6 +1632,9 @@ impl Parser {
     fn parse_import_statement(&mut self) -> Result<Statement, ParsingError> {
         let node = self.start_node();
         self.bump(Kind::Import);
+        if !self.at(Kind::Identifier) {
+            return Err(self.expected_token_error(Kind::Identifier));
+        }
         let mut aliases = vec![];
         while self.at(Kind::Identifier) {
             let node = self.start_node();
@@ -1590,6 +1735,10 @@ impl Parser {
             }
             self.bump_any();
         }
+        // Only relative imports can omit the module name e.g. `from . import x`
+        if level == 0 && !self.at(Kind::Identifier) {
+            return Err(self.expected_token_error(Kind::Identifier));
+        }
         let mut module = self.cur_token().value.to_string();
         self.expect(Kind::Identifier);
         while self.eat(Kind::Dot) {
@@ -1877,7 +2026,7 @@ impl Parser {
                 node: self.finish_node(node),
                 value: Box::new(self.parse_target()?),
             })),
-            _ => panic!("invalid target"),
+            _ => return Err(self.unepxted_token(node, self.cur_kind()).err().unwrap()),
         };
         targets.push(target);
         while self.eat(Kind::Comma) {
@@ -2055,7 +2204,11 @@ impl Parser {
             if self.eat(Kind::Comment) || self.consume_whitespace_and_newline() {
                 continue;
             }
-            let expr = self.parse_starred_item()?;
+            let node = self.start_node();
+            let expr = match self.parse_starred_item() {
+                Ok(expr) => expr,
+                Err(err) => self.recover_expression(node, err),
+            };
             if !self.at(Kind::Eof) && !self.at(termination_kind) {
                 self.expect(Kind::Comma)?;
             }
@@ -2086,7 +2239,7 @@ impl Parser {
     fn parse_expression_2(&mut self) -> Result<Expression, ParsingError> {
         let node = self.start_node();
         if self.eat(Kind::Lambda) {
-            let params_list = self.parse_parameters(true).expect("lambda params");
+            let params_list = self.parse_parameters(true)?;
             self.expect(Kind::Colon)?;
             let expr = self.parse_expression_2()?;
 
@@ -2365,7 +2518,11 @@ impl Parser {
                             span: (node_end.start, node_end.end),
                         });
                     }
-                    let arg = self.parse_named_expression()?;
+                    let arg_node = self.start_node();
+                    let arg = match self.parse_named_expression() {
+                        Ok(arg) => arg,
+                        Err(err) => self.recover_expression(arg_node, err),
+                    };
                     positional_args.push(arg);
                 }
                 if !self.eat(Kind::Comma) {
@@ -2374,7 +2531,13 @@ impl Parser {
             }
 
             self.bump(Kind::Comma);
-            self.expect(Kind::RightParen)?;
+            if self.at_statement_end() {
+                // Keep the call when the user has not typed the closing paren yet
+                let err = self.expected_token_error(Kind::RightParen);
+                self.errors.push(err);
+            } else {
+                self.expect(Kind::RightParen)?;
+            }
 
             Ok(Expression::Call(Box::new(Call {
                 node: self.finish_node(node),
@@ -2443,7 +2606,11 @@ impl Parser {
                         span: (node_end.start, node_end.end),
                     });
                 }
-                let arg = self.parse_named_expression()?;
+                let arg_node = self.start_node();
+                let arg = match self.parse_named_expression() {
+                    Ok(arg) => arg,
+                    Err(err) => self.recover_expression(arg_node, err),
+                };
                 positional_args.push(arg);
             }
             if !self.eat(Kind::Comma) {
@@ -2460,8 +2627,17 @@ impl Parser {
     ) -> Result<Expression, ParsingError> {
         let mut expr = Ok(value);
         while self.eat(Kind::Dot) {
-            let attr_val = self.cur_token().value.to_string();
-            self.expect(Kind::Identifier)?;
+            let attr_val = if self.at(Kind::Identifier) {
+                let attr_val = self.cur_token().value.to_string();
+                self.bump(Kind::Identifier);
+                attr_val
+            } else {
+                // Keep the object with an empty attribute name,
+                // e.g. the user is typing `a.` and completion needs `a`
+                let err = self.expected_token_error(Kind::Identifier);
+                self.errors.push(err);
+                String::new()
+            };
             expr = Ok(Expression::Attribute(Box::new(Attribute {
                 node: self.finish_node(node),
                 value: Box::new(expr?),
@@ -2529,7 +2705,9 @@ impl Parser {
                         let token_kind = self.cur_kind();
                         self.bump_any();
                         let next_str = self.map_to_atom(node, &token_kind, token_value)?;
-                        expr = concat_string_exprs(expr, next_str)?;
+                        expr = concat_string_exprs(expr, next_str).map_err(|msg| {
+                            self.syntax_error(msg.to_string(), (node.start, self.prev_token_end))
+                        })?;
                     } else if self.eat(Kind::WhiteSpace) {
                         continue;
                     } else if self.at(Kind::Indent)
@@ -2628,7 +2806,11 @@ impl Parser {
             if self.at(Kind::RightParen) {
                 break;
             }
-            let expr = self.parse_starred_item()?;
+            let item_node = self.start_node();
+            let expr = match self.parse_starred_item() {
+                Ok(expr) => expr,
+                Err(err) => self.recover_expression(item_node, err),
+            };
             elements.push(expr);
             seen_comma = true;
         }
@@ -2718,6 +2900,11 @@ impl Parser {
         kind: &Kind,
         value: TokenValue,
     ) -> Result<Expression, ParsingError> {
+        let extract_string_inside = |val: String| {
+            extract_string_inside(val).map_err(|msg| {
+                self.syntax_error(msg.to_string(), (start.start, self.prev_token_end))
+            })
+        };
         let atom = match kind {
             Kind::Identifier => Expression::Name(Box::new(Name {
                 node: self.finish_node(start),
@@ -2747,21 +2934,17 @@ impl Parser {
                 },
             })),
             Kind::Bytes => {
-                let bytes_val = extract_string_inside(
-                    value
-                        .to_string()
-                        .strip_prefix('b')
-                        .expect("bytes literal must start with b")
-                        .to_string(),
-                )
-                .into_bytes();
+                // b or B appears in the beginning of bytes
+                let bytes_val =
+                    extract_string_inside(value.to_string().chars().skip(1).collect::<String>())?
+                        .into_bytes();
                 Expression::Constant(Box::new(Constant {
                     node: self.finish_node(start),
                     value: ConstantValue::Bytes(bytes_val),
                 }))
             }
             Kind::StringLiteral => {
-                let string_val = extract_string_inside(value.to_string());
+                let string_val = extract_string_inside(value.to_string())?;
                 Expression::Constant(Box::new(Constant {
                     node: self.finish_node(start),
                     value: ConstantValue::Str(string_val),
@@ -2769,7 +2952,7 @@ impl Parser {
             }
             Kind::RawString => {
                 let string_val =
-                    extract_string_inside(value.to_string().chars().skip(1).collect::<String>());
+                    extract_string_inside(value.to_string().chars().skip(1).collect::<String>())?;
                 Expression::Constant(Box::new(Constant {
                     node: self.finish_node(start),
                     value: ConstantValue::Str(string_val),
@@ -2778,7 +2961,7 @@ impl Parser {
             Kind::RawBytes => {
                 // rb or br appear in the beginning of raw bytes
                 let bytes_val =
-                    extract_string_inside(value.to_string().chars().skip(2).collect::<String>())
+                    extract_string_inside(value.to_string().chars().skip(2).collect::<String>())?
                         .into_bytes();
                 Expression::Constant(Box::new(Constant {
                     node: self.finish_node(start),
@@ -3750,4 +3933,61 @@ class a: pass",
             }
         });
     }
+
+    #[test]
+    fn test_error_recovery() {
+        glob!("../../test_data", "inputs/recovery/*.py", |path| {
+            let input = fs::read_to_string(path).unwrap();
+            for test_case in input.split("\n\n") {
+                let mut parser = Parser::new(
+                    test_case.to_string(),
+                    String::from(path.file_name().unwrap().to_str().unwrap()),
+                );
+                let program = parser.parse();
+                assert!(
+                    !parser.errors.is_empty(),
+                    "expected errors in {}",
+                    test_case
+                );
+
+                insta::with_settings!({
+                        description => test_case,
+                        omit_expression => true
+                    }, {
+                        assert_debug_snapshot!((program, parser.errors));
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_broken_input_never_panics() {
+        glob!("../../test_data", "inputs/broken/*.py", |path| {
+            let input = fs::read_to_string(path).unwrap();
+            for test_case in input.split("\n\n") {
+                let mut parser = Parser::new(
+                    test_case.to_string(),
+                    String::from(path.file_name().unwrap().to_str().unwrap()),
+                );
+                let program = parser.parse();
+                assert!(
+                    !parser.errors.is_empty(),
+                    "expected errors in {}",
+                    test_case
+                );
+                assert!(program.node.end <= test_case.len());
+                for err in &parser.errors {
+                    let ParsingError::InvalidSyntax { span, .. } = err else {
+                        panic!("unexpected error {:?}", err);
+                    };
+                    assert!(
+                        span.0 <= span.1 && span.1 <= test_case.len(),
+                        "invalid span {:?} in {}",
+                        span,
+                        test_case
+                    );
+                }
+            }
+        });
+    }
 }
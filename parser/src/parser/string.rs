@@ -5,30 +5,23 @@ use crate::parser::ast::JoinedStr;
 use crate::token::Kind;
 
 use super::ast::Node;
-use super::error::ParsingError;
-pub fn extract_string_inside(val: String) -> String {
-    if let Some(val) = val.strip_prefix("\"\"\"") {
+// Returns the contents of a string literal without its quotes, returns the error
+// message when the literal is not enclosed in quotes, e.g. an unterminated string
+pub fn extract_string_inside(val: String) -> Result<String, &'static str> {
+    let inside = if let Some(val) = val.strip_prefix("\"\"\"") {
         val.strip_suffix("\"\"\"")
-            .expect("String must be enclosed with \"\"\"")
-            .to_string()
     } else if let Some(val) = val.strip_prefix('\"') {
         val.strip_suffix('\"')
-            .expect("String must be enclosed with \"")
-            .to_string()
     } else if let Some(val) = val.strip_prefix("'''") {
         val.strip_suffix("'''")
-            .expect("String must be enclosed with '''")
-            .to_string()
     } else if let Some(val) = val.strip_prefix('\'') {
         val.strip_suffix('\'')
-            .expect("String must be enclosed with '")
-            .to_string()
     } else {
-        panic!(
-            "String must be enclosed in \"\"\", \"', ''' or ' but got {} ",
-            val.starts_with('\'')
-        );
-    }
+        None
+    };
+    inside
+        .map(str::to_string)
+        .ok_or("String must be enclosed in \"\"\", \", ''' or '")
 }
 
 pub fn is_string(kind: &Kind) -> bool {
@@ -42,7 +35,9 @@ pub fn is_string(kind: &Kind) -> bool {
     }
 }
 
-pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expression, ParsingError> {
+// Concatenates adjacent string literals, returns the error message
+// when the literals cannot be concatenated, e.g. bytes and strings
+pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expression, &'static str> {
     use crate::parser::ast::{Constant, ConstantValue};
     match (lhs, rhs) {
         (Expression::Constant(lhs), Expression::Constant(rhs)) => {
@@ -64,27 +59,11 @@ pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expressio
                         value: ConstantValue::Bytes(lhs),
                     }))
                 }
-                (ConstantValue::Bytes(_lhs), _) => {
-                    return Err(ParsingError::InvalidSyntax {
-                        path: "test".into(),
-                        msg: "Cannot concat bytes and string".into(),
-                        line: 0,
-                        input: "test".into(),
-                        advice: "test".into(),
-                        span: (0, 0),
-                    })
-                }
+                (ConstantValue::Bytes(_lhs), _) => return Err("Cannot concat bytes and string"),
                 (_, ConstantValue::Bytes(_rhs)) => {
-                    return Err(ParsingError::InvalidSyntax {
-                        path: "test".into(),
-                        msg: "Can only concat bytes with other bytes".into(),
-                        line: 0,
-                        input: "test".into(),
-                        advice: "test".into(),
-                        span: (0, 0),
-                    });
+                    return Err("Can only concat bytes with other bytes");
                 }
-                _ => panic!("Cannot concat string"),
+                _ => return Err("Cannot concat string"),
             };
             Ok(concatnated_string)
         }
@@ -109,16 +88,9 @@ pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expressio
                     })));
                 }
                 ConstantValue::Bytes(_) => {
-                    return Err(ParsingError::InvalidSyntax {
-                        path: "test".into(),
-                        msg: "Cannot concat string and bytes".into(),
-                        line: 0,
-                        input: "test".into(),
-                        advice: "test".into(),
-                        span: (0, 0),
-                    });
+                    return Err("Cannot concat string and bytes");
                 }
-                _ => panic!("Cannot concat string"),
+                _ => return Err("Cannot concat string"),
             }
             Ok(Expression::JoinedStr(Box::new(JoinedStr {
                 node: Node {
@@ -135,16 +107,9 @@ pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expressio
                     value: ConstantValue::Str(rhs_val),
                 })),
                 ConstantValue::Bytes(_) => {
-                    return Err(ParsingError::InvalidSyntax {
-                        path: "test".into(),
-                        msg: "Cannot concat string and bytes".into(),
-                        line: 0,
-                        input: "test".into(),
-                        advice: "test".into(),
-                        span: (0, 0),
-                    });
+                    return Err("Cannot concat string and bytes");
                 }
-                _ => panic!("Cannot concat string"),
+                _ => return Err("Cannot concat string"),
             };
             let mut values = vec![const_expr];
             values.extend(fstring_rhs.values);
@@ -156,6 +121,6 @@ pub fn concat_string_exprs(lhs: Expression, rhs: Expression) -> Result<Expressio
                 values,
             })))
         }
-        _ => panic!("Cannot concat string"),
+        _ => Err("Cannot concat string"),
     }
 }
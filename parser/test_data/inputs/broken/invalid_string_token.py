@@ -0,0 +1,6 @@
+x = !"
+y = "a"
+
+x = f"abc {a}
+
+x = b"abc
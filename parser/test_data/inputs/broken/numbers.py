@@ -0,0 +1,11 @@
+x = 0b102
+
+x = 0o78 + 1
+
+x = 0xfg
+
+x = 0x
+
+x = 1.5e
+
+x = 1e+ * 2
@@ -0,0 +1,30 @@
+for 1 in x: pass
+
+lambda (: 1
+
+if True:
+        pass
+    pass
+
+def f(:
+    pass
+
+class A(
+    x = 1
+
+import
+
+from import x
+
+x = (1, 2
+
+x = [1,, 2]
+
+x = {1: }
+
+@
+def f(): pass
+
+try:
+    pass
+except
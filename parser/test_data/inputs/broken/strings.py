@@ -0,0 +1,9 @@
+"abc
+
+x = 'abc
+
+x = "a" b"b"
+
+x = f"{a}" b"b"
+
+x = """never closed
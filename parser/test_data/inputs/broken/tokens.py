@@ -0,0 +1,9 @@
+x = $
+
+x = ?
+
+x = `a`
+
+x = a !b
+
+print(a) \ b
@@ -0,0 +1,29 @@
+while :
+    x = 1
+z = 2
+
+if x
+    y = 1
+z = 3
+
+a =
+b = 1
+
+foo(a, , b)
+c = 1
+
+foo(a.
+
+for x in y:
+    a.
+    b = 2
+
+x = 1
+    y = 2
+z = 3
+
+class A:
+def f(): pass
+
+x = [1, )
+y = 2
@@ -33,6 +33,7 @@ pub trait TraversalVisitor {
             Statement::FunctionDef(f) => self.visit_function_def(f),
             Statement::ClassDef(c) => self.visit_class_def(c),
             Statement::Match(m) => self.visit_match(m),
+            Statement::Invalid(_) => {}
             Statement::AsyncForStatement(f) => self.visit_async_for(f),
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
@@ -67,6 +68,7 @@ pub trait TraversalVisitor {
             Expression::IfExp(i) => self.visit_if_exp(i),
             Expression::JoinedStr(j) => self.visit_joined_str(j),
             Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            Expression::Invalid(_) => {}
         }
     }
     fn visit_import(&mut self, _i: &Import) {
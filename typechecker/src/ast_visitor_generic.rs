@@ -34,6 +34,7 @@ pub trait TraversalVisitorImmutGeneric<T> {
             Statement::FunctionDef(f) => self.visit_function_def(f),
             Statement::ClassDef(c) => self.visit_class_def(c),
             Statement::Match(m) => self.visit_match(m),
+            Statement::Invalid(i) => self.visit_invalid(i),
             Statement::AsyncForStatement(f) => self.visit_async_for(f),
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
@@ -68,6 +69,7 @@ pub trait TraversalVisitorImmutGeneric<T> {
             Expression::IfExp(i) => self.visit_if_exp(i),
             Expression::JoinedStr(j) => self.visit_joined_str(j),
             Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            Expression::Invalid(i) => self.visit_invalid(i),
         }
     }
     fn visit_import(&self, _i: &Import) -> T {
@@ -232,6 +234,9 @@ pub trait TraversalVisitorImmutGeneric<T> {
         todo!()
     }
 
+    // Invalid nodes are left by syntax errors in any file, so every visitor has to handle them
+    fn visit_invalid(&self, i: &Invalid) -> T;
+
     fn visit_delete(&self, _d: &Delete) -> T {
         todo!()
     }
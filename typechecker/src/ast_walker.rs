@@ -0,0 +1,383 @@
+use enderpy_python_parser::ast::*;
+
+/// A node of the AST that is passed to the walker
+#[derive(Debug, Clone, Copy)]
+pub enum AstNode<'a> {
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+    Arg(&'a Arg),
+    Keyword(&'a Keyword),
+    Alias(&'a Alias),
+    ExceptHandler(&'a ExceptHandler),
+}
+
+impl<'a> AstNode<'a> {
+    pub fn node(&self) -> Node {
+        match self {
+            AstNode::Statement(s) => s.get_node(),
+            AstNode::Expression(e) => e.get_node(),
+            AstNode::Arg(a) => a.node,
+            AstNode::Keyword(k) => k.node,
+            AstNode::Alias(a) => a.node,
+            AstNode::ExceptHandler(h) => h.node,
+        }
+    }
+}
+
+/// Walks every node of the AST in source order.
+/// Unlike the visitors, walkers only implement the methods for the nodes they need
+/// and the traversal of the children is done by `walk_*` functions.
+pub trait AstWalker<'a> {
+    /// Called before the children of the node are walked.
+    /// Returning false skips the children of the node.
+    fn enter(&mut self, _node: AstNode<'a>) -> bool {
+        true
+    }
+
+    /// Called after the children of the node are walked
+    fn leave(&mut self, _node: AstNode<'a>) {}
+}
+
+pub fn walk_body<'a>(walker: &mut impl AstWalker<'a>, body: &'a [Statement]) {
+    for stmt in body {
+        walk_stmt(walker, stmt);
+    }
+}
+
+pub fn walk_stmt<'a>(walker: &mut impl AstWalker<'a>, stmt: &'a Statement) {
+    let node = AstNode::Statement(stmt);
+    if !walker.enter(node) {
+        return;
+    }
+    match stmt {
+        Statement::AssignStatement(a) => {
+            walk_exprs(walker, &a.targets);
+            walk_expr(walker, &a.value);
+        }
+        Statement::AnnAssignStatement(a) => {
+            walk_expr(walker, &a.target);
+            walk_expr(walker, &a.annotation);
+            walk_opt_expr(walker, a.value.as_ref());
+        }
+        Statement::AugAssignStatement(a) => {
+            walk_expr(walker, &a.target);
+            walk_expr(walker, &a.value);
+        }
+        Statement::ExpressionStatement(e) => walk_expr(walker, e),
+        Statement::Assert(a) => {
+            walk_expr(walker, &a.test);
+            walk_opt_expr(walker, a.msg.as_ref());
+        }
+        Statement::Delete(d) => walk_exprs(walker, &d.targets),
+        Statement::Return(r) => walk_opt_expr(walker, r.value.as_ref()),
+        Statement::Raise(r) => {
+            walk_opt_expr(walker, r.exc.as_ref());
+            walk_opt_expr(walker, r.cause.as_ref());
+        }
+        Statement::Import(i) => walk_aliases(walker, &i.names),
+        Statement::ImportFrom(i) => walk_aliases(walker, &i.names),
+        Statement::IfStatement(i) => {
+            walk_expr(walker, &i.test);
+            walk_body(walker, &i.body);
+            walk_body(walker, &i.orelse);
+        }
+        Statement::WhileStatement(w) => {
+            walk_expr(walker, &w.test);
+            walk_body(walker, &w.body);
+            walk_body(walker, &w.orelse);
+        }
+        Statement::ForStatement(f) => {
+            walk_expr(walker, &f.target);
+            walk_expr(walker, &f.iter);
+            walk_body(walker, &f.body);
+            walk_body(walker, &f.orelse);
+        }
+        Statement::AsyncForStatement(f) => {
+            walk_expr(walker, &f.target);
+            walk_expr(walker, &f.iter);
+            walk_body(walker, &f.body);
+            walk_body(walker, &f.orelse);
+        }
+        Statement::WithStatement(w) => {
+            walk_with_items(walker, &w.items);
+            walk_body(walker, &w.body);
+        }
+        Statement::AsyncWithStatement(w) => {
+            walk_with_items(walker, &w.items);
+            walk_body(walker, &w.body);
+        }
+        Statement::TryStatement(t) => {
+            walk_body(walker, &t.body);
+            walk_handlers(walker, &t.handlers);
+            walk_body(walker, &t.orelse);
+            walk_body(walker, &t.finalbody);
+        }
+        Statement::TryStarStatement(t) => {
+            walk_body(walker, &t.body);
+            walk_handlers(walker, &t.handlers);
+            walk_body(walker, &t.orelse);
+            walk_body(walker, &t.finalbody);
+        }
+        Statement::FunctionDef(f) => {
+            walk_exprs(walker, &f.decorator_list);
+            walk_arguments(walker, &f.args);
+            if let Some(returns) = &f.returns {
+                walk_expr(walker, returns);
+            }
+            walk_body(walker, &f.body);
+        }
+        Statement::AsyncFunctionDef(f) => {
+            walk_exprs(walker, &f.decorator_list);
+            walk_arguments(walker, &f.args);
+            if let Some(returns) = &f.returns {
+                walk_expr(walker, returns);
+            }
+            walk_body(walker, &f.body);
+        }
+        Statement::ClassDef(c) => {
+            walk_exprs(walker, &c.decorator_list);
+            walk_exprs(walker, &c.bases);
+            walk_keywords(walker, &c.keywords);
+            walk_body(walker, &c.body);
+        }
+        Statement::Match(m) => {
+            walk_expr(walker, &m.subject);
+            for case in &m.cases {
+                walk_pattern(walker, &case.pattern);
+                if let Some(guard) = &case.guard {
+                    walk_expr(walker, guard);
+                }
+                walk_body(walker, &case.body);
+            }
+        }
+        Statement::Pass(_)
+        | Statement::Break(_)
+        | Statement::Continue(_)
+        | Statement::Global(_)
+        | Statement::Nonlocal(_)
+        | Statement::Invalid(_) => {}
+    }
+    walker.leave(node);
+}
+
+pub fn walk_expr<'a>(walker: &mut impl AstWalker<'a>, expr: &'a Expression) {
+    let node = AstNode::Expression(expr);
+    if !walker.enter(node) {
+        return;
+    }
+    match expr {
+        Expression::List(l) => walk_exprs(walker, &l.elements),
+        Expression::Tuple(t) => walk_exprs(walker, &t.elements),
+        Expression::Set(s) => walk_exprs(walker, &s.elements),
+        Expression::Dict(d) => {
+            // Keys and values are walked in source order
+            for (key, value) in d.keys.iter().zip(d.values.iter()) {
+                walk_expr(walker, key);
+                walk_expr(walker, value);
+            }
+        }
+        Expression::BoolOp(b) => walk_exprs(walker, &b.values),
+        Expression::UnaryOp(u) => walk_expr(walker, &u.operand),
+        Expression::BinOp(b) => {
+            walk_expr(walker, &b.left);
+            walk_expr(walker, &b.right);
+        }
+        Expression::NamedExpr(n) => {
+            walk_expr(walker, &n.target);
+            walk_expr(walker, &n.value);
+        }
+        Expression::Yield(y) => {
+            if let Some(value) = &y.value {
+                walk_expr(walker, value);
+            }
+        }
+        Expression::YieldFrom(y) => walk_expr(walker, &y.value),
+        Expression::Starred(s) => walk_expr(walker, &s.value),
+        Expression::Generator(g) => {
+            walk_expr(walker, &g.element);
+            walk_comprehensions(walker, &g.generators);
+        }
+        Expression::ListComp(l) => {
+            walk_expr(walker, &l.element);
+            walk_comprehensions(walker, &l.generators);
+        }
+        Expression::SetComp(s) => {
+            walk_expr(walker, &s.element);
+            walk_comprehensions(walker, &s.generators);
+        }
+        Expression::DictComp(d) => {
+            walk_expr(walker, &d.key);
+            walk_expr(walker, &d.value);
+            walk_comprehensions(walker, &d.generators);
+        }
+        Expression::Attribute(a) => walk_expr(walker, &a.value),
+        Expression::Subscript(s) => {
+            walk_expr(walker, &s.value);
+            walk_expr(walker, &s.slice);
+        }
+        Expression::Slice(s) => {
+            for part in [&s.lower, &s.upper, &s.step].into_iter().flatten() {
+                walk_expr(walker, part);
+            }
+        }
+        Expression::Call(c) => {
+            walk_expr(walker, &c.func);
+            walk_exprs(walker, &c.args);
+            walk_keywords(walker, &c.keywords);
+            if let Some(starargs) = &c.starargs {
+                walk_expr(walker, starargs);
+            }
+            if let Some(kwargs) = &c.kwargs {
+                walk_expr(walker, kwargs);
+            }
+        }
+        Expression::Await(a) => walk_expr(walker, &a.value),
+        Expression::Compare(c) => {
+            walk_expr(walker, &c.left);
+            walk_exprs(walker, &c.comparators);
+        }
+        Expression::Lambda(l) => {
+            walk_arguments(walker, &l.args);
+            walk_expr(walker, &l.body);
+        }
+        Expression::IfExp(i) => {
+            walk_expr(walker, &i.body);
+            walk_expr(walker, &i.test);
+            walk_expr(walker, &i.orelse);
+        }
+        Expression::JoinedStr(j) => walk_exprs(walker, &j.values),
+        Expression::FormattedValue(f) => {
+            walk_expr(walker, &f.value);
+            if let Some(format_spec) = &f.format_spec {
+                walk_expr(walker, format_spec);
+            }
+        }
+        Expression::Constant(_) | Expression::Name(_) | Expression::Invalid(_) => {}
+    }
+    walker.leave(node);
+}
+
+fn walk_exprs<'a>(walker: &mut impl AstWalker<'a>, exprs: &'a [Expression]) {
+    for expr in exprs {
+        walk_expr(walker, expr);
+    }
+}
+
+fn walk_opt_expr<'a>(walker: &mut impl AstWalker<'a>, expr: Option<&'a Expression>) {
+    if let Some(expr) = expr {
+        walk_expr(walker, expr);
+    }
+}
+
+fn walk_aliases<'a>(walker: &mut impl AstWalker<'a>, aliases: &'a [Alias]) {
+    for alias in aliases {
+        let node = AstNode::Alias(alias);
+        if walker.enter(node) {
+            walker.leave(node);
+        }
+    }
+}
+
+fn walk_keywords<'a>(walker: &mut impl AstWalker<'a>, keywords: &'a [Keyword]) {
+    for keyword in keywords {
+        let node = AstNode::Keyword(keyword);
+        if walker.enter(node) {
+            walk_expr(walker, &keyword.value);
+            walker.leave(node);
+        }
+    }
+}
+
+fn walk_with_items<'a>(walker: &mut impl AstWalker<'a>, items: &'a [WithItem]) {
+    for item in items {
+        walk_expr(walker, &item.context_expr);
+        if let Some(vars) = &item.optional_vars {
+            walk_expr(walker, vars);
+        }
+    }
+}
+
+fn walk_handlers<'a>(walker: &mut impl AstWalker<'a>, handlers: &'a [ExceptHandler]) {
+    for handler in handlers {
+        let node = AstNode::ExceptHandler(handler);
+        if walker.enter(node) {
+            if let Some(typ) = &handler.typ {
+                walk_expr(walker, typ);
+            }
+            walk_body(walker, &handler.body);
+            walker.leave(node);
+        }
+    }
+}
+
+fn walk_comprehensions<'a>(walker: &mut impl AstWalker<'a>, generators: &'a [Comprehension]) {
+    for generator in generators {
+        walk_expr(walker, &generator.target);
+        walk_expr(walker, &generator.iter);
+        walk_exprs(walker, &generator.ifs);
+    }
+}
+
+// Arguments are walked in source order, the defaults come after their argument
+pub fn walk_arguments<'a>(walker: &mut impl AstWalker<'a>, args: &'a Arguments) {
+    let positional = args.posonlyargs.iter().chain(args.args.iter());
+    let first_default =
+        (args.posonlyargs.len() + args.args.len()).saturating_sub(args.defaults.len());
+    for (i, arg) in positional.enumerate() {
+        walk_arg(walker, arg);
+        if i >= first_default {
+            walk_expr(walker, &args.defaults[i - first_default]);
+        }
+    }
+    if let Some(vararg) = &args.vararg {
+        walk_arg(walker, vararg);
+    }
+    for (i, arg) in args.kwonlyargs.iter().enumerate() {
+        walk_arg(walker, arg);
+        if let Some(Some(default)) = args.kw_defaults.get(i) {
+            walk_expr(walker, default);
+        }
+    }
+    if let Some(kwarg) = &args.kwarg {
+        walk_arg(walker, kwarg);
+    }
+}
+
+fn walk_arg<'a>(walker: &mut impl AstWalker<'a>, arg: &'a Arg) {
+    let node = AstNode::Arg(arg);
+    if walker.enter(node) {
+        if let Some(annotation) = &arg.annotation {
+            walk_expr(walker, annotation);
+        }
+        walker.leave(node);
+    }
+}
+
+fn walk_pattern<'a>(walker: &mut impl AstWalker<'a>, pattern: &'a MatchPattern) {
+    match pattern {
+        MatchPattern::MatchValue(v) => walk_expr(walker, &v.value),
+        MatchPattern::MatchSingleton(e) | MatchPattern::MatchStar(e) => walk_expr(walker, e),
+        MatchPattern::MatchSequence(patterns) | MatchPattern::MatchOr(patterns) => {
+            for pattern in patterns {
+                walk_pattern(walker, pattern);
+            }
+        }
+        MatchPattern::MatchMapping(m) => {
+            for (key, pattern) in m.keys.iter().zip(m.patterns.iter()) {
+                walk_expr(walker, key);
+                walk_pattern(walker, pattern);
+            }
+        }
+        MatchPattern::MatchAs(m) => {
+            if let Some(pattern) = &m.pattern {
+                walk_pattern(walker, pattern);
+            }
+        }
+        MatchPattern::MatchClass(m) => {
+            walk_expr(walker, &m.cls);
+            for pattern in m.patterns.iter().chain(m.kwd_patterns.iter()) {
+                walk_pattern(walker, pattern);
+            }
+        }
+    }
+}
@@ -1,17 +1,27 @@
 use env_logger::Builder;
 use log::info;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
 
-use enderpy_python_parser::Parser;
+use enderpy_python_parser::error::ParsingError;
+use enderpy_python_parser::line_index::LineColRange;
+use enderpy_python_parser::{LineIndex, Parser};
 
+use crate::ide::{is_identifier_char, Location};
 use crate::nodes::EnderpyFile;
 use crate::ruff_python_import_resolver as ruff_python_resolver;
 use crate::ruff_python_import_resolver::config::Config;
+use crate::ruff_python_import_resolver::host::Host;
+pub use crate::ruff_python_import_resolver::host::PythonHost;
 use crate::ruff_python_import_resolver::{execution_environment, resolver};
-use crate::settings::Settings;
+use crate::settings::{Settings, Severity};
 use crate::state::State;
-use crate::symbol_table::SymbolTable;
 use crate::type_check::checker::TypeChecker;
+use crate::type_check::names::check_names;
+use crate::type_check::rules::Rule;
 
 pub struct BuildSource {
     pub path: PathBuf,
@@ -22,36 +32,96 @@ pub struct BuildSource {
     pub followed: bool,
 }
 
+impl BuildSource {
+    pub fn from_path(path: PathBuf, followed: bool) -> std::io::Result<Self> {
+        let source = std::fs::read_to_string(&path)?;
+        let module = BuildManager::get_module_name(&path);
+        Ok(BuildSource {
+            path,
+            module,
+            source,
+            followed,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BuildError {
     pub msg: String,
-    pub line: u32,
+    // Suggestion on how to fix the error
+    pub advice: Option<String>,
+    // Code of the rule that reported the error
+    pub code: &'static str,
+    pub severity: Severity,
+    // File that the error was found in
+    pub path: PathBuf,
+    // Byte offsets of the error in the source
     pub start: u32,
     pub end: u32,
+    // Zero based lines and UTF-8 columns of the error,
+    // use the line index of the file to get UTF-16 columns
+    pub range: LineColRange,
+    // Other places that explain the error, like the previous declaration of a redeclared name
+    pub related: Vec<RelatedInformation>,
+}
+
+/// A step of the build that reports its progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildPhase {
+    /// Resolving the imports and parsing the modules that they find
+    Gathering,
+    /// Populating the symbol tables of the modules
+    Analyzing,
+}
+
+/// A location that is related to an error, with a message that says how
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedInformation {
+    pub msg: String,
+    pub location: Location,
 }
 
 #[derive(Debug)]
 pub struct BuildManager {
-    errors: Vec<BuildError>,
+    // Errors of the modules that are checked and not changed since
+    errors: HashMap<String, Vec<BuildError>>,
     pub modules: HashMap<String, State>,
+    // Keys of the modules by the module names of their files, which declarations refer to
+    module_keys: HashMap<String, String>,
+    // Modules imported by each module, only present for the modules
+    // whose imports are resolved and not changed since
+    dependencies: HashMap<String, HashSet<String>>,
+    // Modules whose symbol table is populated and not changed since
+    analyzed: HashSet<String>,
     options: Settings,
+    // The python environment used to resolve third party imports
+    host: PythonHost,
 }
 #[allow(unused)]
 impl BuildManager {
     pub fn new(sources: Vec<BuildSource>, options: Settings) -> Self {
-        if sources.len() > 1 {
-            panic!("analyzing more than 1 input is not supported");
-        }
+        let host = PythonHost::discover(
+            &options.root,
+            options.import_discovery.python_executable.as_deref(),
+        );
+        Self::with_host(sources, options, host)
+    }
 
+    /// Creates a manager that resolves third party imports in the given python environment
+    /// instead of the one of the project interpreter, e.g. [`PythonHost::default`] to not run python.
+    pub fn with_host(sources: Vec<BuildSource>, options: Settings, host: PythonHost) -> Self {
         let mut modules = HashMap::new();
 
         for build_source in sources {
             let mod_name = Self::get_module_name(&build_source.path);
             let file = Box::new(Self::parse_file(build_source));
-            let symbol_table = SymbolTable::new(crate::symbol_table::SymbolTableType::Module, 0);
 
             modules.insert(mod_name, State::new(file));
         }
+        let module_keys = modules
+            .iter()
+            .map(|(key, state)| (state.file.module_name.clone(), key.clone()))
+            .collect();
 
         let mut builder = Builder::new();
         if options.debug {
@@ -61,144 +131,420 @@ impl BuildManager {
         }
 
         BuildManager {
-            errors: vec![],
+            errors: HashMap::new(),
             modules,
+            module_keys,
+            dependencies: HashMap::new(),
+            analyzed: HashSet::new(),
             options,
+            host,
         }
     }
 
-    pub fn add_source(&mut self, path: &PathBuf) {
-        let source = std::fs::read_to_string(path).unwrap();
-        let module = Self::get_module_name(path);
-        let build_source = BuildSource {
-            path: path.clone(),
-            module: module.clone(),
-            source,
-            followed: false,
-        };
+    /// Reads the file at path and adds it to the modules that are checked,
+    /// the file may be deleted or unreadable by the time it is read
+    pub fn add_source(&mut self, path: &Path) -> std::io::Result<()> {
+        let build_source = BuildSource::from_path(path.to_path_buf(), false)?;
+        self.add_build_source(build_source);
+        Ok(())
+    }
+
+    // Adds a source whose contents are already in memory, e.g. an unsaved file in the editor
+    pub fn add_build_source(&mut self, build_source: BuildSource) {
+        let module = Self::get_module_name(&build_source.path);
         let file = Box::new(Self::parse_file(build_source));
-        self.modules.insert(module, State::new(file));
+        self.invalidate(&module);
+        self.insert_module(module, State::new(file));
+    }
+
+    fn insert_module(&mut self, key: String, state: State) {
+        self.module_keys
+            .insert(state.file.module_name.clone(), key.clone());
+        self.modules.insert(key, state);
     }
 
+    /// Returns the errors of all the checked modules, sorted by path and position
     pub fn get_errors(&self) -> Vec<BuildError> {
-        self.errors.clone()
+        let mut errors = self.errors.values().flatten().cloned().collect::<Vec<_>>();
+        errors.sort_by(|a, b| (&a.path, a.start).cmp(&(&b.path, b.start)));
+        errors
+    }
+
+    /// Returns the errors of the module at path, the module must be checked first
+    pub fn get_file_errors(&self, path: &Path) -> Vec<BuildError> {
+        self.errors
+            .get(&Self::get_module_name(path))
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the paths of the modules that are checked, the ones that were
+    /// only found by following imports are left out
+    pub fn checked_files(&self) -> Vec<PathBuf> {
+        self.modules
+            .values()
+            .filter(|state| !state.file.followed)
+            .map(|state| state.file.path.clone())
+            .collect()
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.options
+    }
+
+    pub fn contains_file(&self, path: &Path) -> bool {
+        self.modules.contains_key(&Self::get_module_name(path))
+    }
+
+    /// Returns true if the imports of all the modules are resolved and their symbol
+    /// tables are populated, so building the project again does nothing
+    pub fn is_built(&self) -> bool {
+        self.modules
+            .keys()
+            .all(|module| self.dependencies.contains_key(module) && self.analyzed.contains(module))
+    }
+
+    /// Forgets the results of a module that changed and of all the modules that
+    /// depend on it directly or through other modules.
+    /// The next build only analyzes and checks the invalidated modules again.
+    pub fn invalidate(&mut self, module: &str) {
+        let mut pending = vec![module.to_string()];
+        let mut invalidated = HashSet::new();
+        while let Some(module) = pending.pop() {
+            if !invalidated.insert(module.clone()) {
+                continue;
+            }
+            self.errors.remove(&module);
+            self.analyzed.remove(&module);
+            pending.extend(
+                self.dependencies
+                    .iter()
+                    .filter(|(_, imports)| imports.contains(&module))
+                    .map(|(dependent, _)| dependent.clone()),
+            );
+        }
+        // The imports of the changed module are resolved again, they may have changed
+        self.dependencies.remove(module);
+    }
+
+    /// Reads the module at path again after it changed on disk.
+    /// Returns false if the module is not in the project.
+    pub fn reload_file(&mut self, path: &Path) -> bool {
+        let Some(followed) = self.get_state(path).map(|state| state.file.followed) else {
+            return false;
+        };
+        match BuildSource::from_path(path.to_path_buf(), followed) {
+            Ok(build_source) => self.add_build_source(build_source),
+            Err(e) => {
+                log::warn!("cannot read file: {}", e);
+                return self.remove_file(path);
+            }
+        }
+        true
+    }
+
+    /// Removes the module at path after it was deleted, the modules that import it are invalidated.
+    /// Returns false if the module is not in the project.
+    pub fn remove_file(&mut self, path: &Path) -> bool {
+        let module = Self::get_module_name(path);
+        if !self.modules.contains_key(&module) {
+            return false;
+        }
+        self.invalidate(&module);
+        if let Some(state) = self.modules.remove(&module) {
+            self.module_keys.remove(&state.file.module_name);
+        }
+        true
+    }
+
+    /// Stops checking the module at path, like a file that is closed in the editor.
+    /// The module is kept as a followed module if other modules import it, otherwise it is removed.
+    pub fn release_file(&mut self, path: &Path) {
+        let module = Self::get_module_name(path);
+        let is_imported = self
+            .dependencies
+            .iter()
+            .any(|(dependent, imports)| *dependent != module && imports.contains(&module));
+        if !is_imported {
+            self.remove_file(path);
+            return;
+        }
+        match BuildSource::from_path(path.to_path_buf(), true) {
+            Ok(build_source) => self.add_build_source(build_source),
+            Err(e) => {
+                log::warn!("cannot read file: {}", e);
+                self.remove_file(path);
+            }
+        }
+    }
+
+    /// Resolves the imports of the modules again after files were created or deleted,
+    /// like a new stub that takes the place of its module, and invalidates the modules
+    /// whose imports resolve to other files now.
+    pub fn refresh_imports(&mut self) {
+        let changed = self
+            .dependencies
+            .iter()
+            .filter(|(module, imports)| match self.modules.get(*module) {
+                Some(state) => {
+                    let resolved = self
+                        .resolve_imports(state)
+                        .iter()
+                        .map(|path| Self::get_module_name(path))
+                        .collect::<HashSet<_>>();
+                    resolved != **imports
+                }
+                None => true,
+            })
+            .map(|(module, _)| module.clone())
+            .collect::<Vec<_>>();
+        for module in changed {
+            self.invalidate(&module);
+        }
+    }
+
+    /// Returns the line index of the module at path, if it was parsed
+    pub fn line_index(&self, path: &Path) -> Option<&LineIndex> {
+        self.get_state(path).map(|state| &state.file.line_index)
+    }
+
+    pub(crate) fn get_state(&self, path: &Path) -> Option<&State> {
+        self.modules.get(&Self::get_module_name(path))
+    }
+
+    // Declarations refer to their module by the module name of the file
+    pub(crate) fn get_state_by_module_name(&self, module_name: &str) -> Option<&State> {
+        self.modules.get(self.module_keys.get(module_name)?)
     }
 
     pub fn parse_file(build_source: BuildSource) -> EnderpyFile {
         let file_path = build_source.path.to_str().unwrap_or("");
         let mut parser = Parser::new(build_source.source.clone(), file_path.into());
         let tree = parser.parse();
-        EnderpyFile::from(
+        let line_index = std::mem::take(&mut parser.line_index);
+        let syntax_errors = parser
+            .errors
+            .into_iter()
+            .map(|err| Self::syntax_error(&build_source.path, &line_index, err))
+            .collect();
+        let mut file = EnderpyFile::from(
             tree,
             build_source.module,
             build_source.source.clone(),
             build_source.path,
-        )
+            build_source.followed,
+            line_index,
+        );
+        file.syntax_errors = syntax_errors;
+        file
     }
 
-    pub fn get_module_name(path: &PathBuf) -> String {
+    // Syntax errors are always reported as errors, their severity cannot be configured
+    fn syntax_error(path: &Path, line_index: &LineIndex, err: ParsingError) -> BuildError {
+        match err {
+            ParsingError::InvalidSyntax {
+                msg, advice, span, ..
+            } => BuildError {
+                msg: msg.to_string(),
+                advice: Some(advice).filter(|advice| !advice.is_empty()),
+                code: Rule::SyntaxError.code(),
+                severity: Severity::Error,
+                path: path.to_path_buf(),
+                start: span.0 as u32,
+                end: span.1 as u32,
+                range: line_index.range(span.0, span.1),
+                related: vec![],
+            },
+            ParsingError::IoError(e) => BuildError {
+                msg: e.to_string(),
+                advice: None,
+                code: Rule::SyntaxError.code(),
+                severity: Severity::Error,
+                path: path.to_path_buf(),
+                start: 0,
+                end: 0,
+                range: LineColRange::default(),
+                related: vec![],
+            },
+        }
+    }
+
+    /// Returns the name that imports the project module, e.g. `pkg.mod` for `pkg/mod.py`
+    pub(crate) fn import_name(&self, path: &Path) -> Option<String> {
+        let relative = path
+            .strip_prefix(&self.options.root)
+            .ok()?
+            .with_extension("");
+        let mut parts = relative
+            .iter()
+            .map(|part| part.to_str())
+            .collect::<Option<Vec<_>>>()?;
+        if parts.last() == Some(&"__init__") {
+            parts.pop();
+        }
+        let is_identifier = |part: &&str| {
+            part.chars().all(is_identifier_char) && !part.starts_with(|c: char| c.is_ascii_digit())
+        };
+        if parts.is_empty() || !parts.iter().all(is_identifier) {
+            return None;
+        }
+        Some(parts.join("."))
+    }
+
+    pub fn get_module_name(path: &Path) -> String {
         path.to_str().unwrap_or_default().replace(['/', '\\'], ".")
     }
 
     // Entry point to analyze the program
+    // Only the modules that are new or invalidated since the last build are analyzed
     pub fn build(&mut self) {
-        let files = self.modules.values().collect();
-        let new_files = self.gather_files(files);
-        for file in new_files {
-            self.modules.insert(file.file.module_name.clone(), file);
-        }
-        for module in self.modules.values() {
-            info!("file: {:#?}", module.file.module_name);
-        }
+        let _ = self.build_with_progress(|_, _, _| ControlFlow::Continue(()));
+    }
 
-        self.pre_analysis();
+    /// Builds the project and reports the number of modules that each phase is done with
+    /// and the number of modules that it knows of, which grows while imports are followed.
+    /// The build stops when `progress` breaks, the next build continues from there.
+    pub fn build_with_progress(
+        &mut self,
+        mut progress: impl FnMut(BuildPhase, usize, usize) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        self.gather_files(&mut progress)?;
+        self.pre_analysis(&mut progress)
     }
 
     // Performs pre-analysis on the source files
     // Fills up the symbol table for each module
-    fn pre_analysis(&mut self) {
-        for state in self.modules.iter_mut() {
-            state.1.populate_symbol_table();
+    fn pre_analysis(
+        &mut self,
+        progress: &mut impl FnMut(BuildPhase, usize, usize) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let total = self
+            .modules
+            .keys()
+            .filter(|name| !self.analyzed.contains(*name))
+            .count();
+        let mut done = 0;
+        for (name, state) in self.modules.iter_mut() {
+            if self.analyzed.insert(name.clone()) {
+                state.populate_symbol_table();
+                done += 1;
+                progress(BuildPhase::Analyzing, done, total)?;
+            }
         }
+        ControlFlow::Continue(())
     }
 
     // Performs type checking passes over the code
+    // Modules that were only found by following imports are not checked
     pub fn type_check(&mut self) {
         self.build();
-        for state in self.modules.iter_mut() {
-            let mut checker = TypeChecker::new(state.1, &self.options);
-            for stmt in &state.1.file.body {
-                checker.type_check(stmt);
-            }
-            for error in checker.errors {
-                let line = get_line_number_of_character_position(&state.1.file.source, error.start);
-                self.errors.push(BuildError {
-                    msg: error.msg,
-                    line: line as u32,
-                    start: error.start as u32,
-                    end: error.end as u32,
-                });
-            }
+        for path in self.unchecked_files() {
+            self.type_check_file(&path);
         }
     }
 
-    fn gather_files(&self, current_files: Vec<&State>) -> Vec<State> {
-        let mut new_imports = vec![];
-        let mut discovered_files = vec![];
-        for state in current_files {
-            let resolved_imports = self.resolve_imports(state);
-            // check if the resolved_imports are not in the current files and add them to the new imports
-            for (_, state) in resolved_imports {
-                if !self.modules.contains_key(&state.file.module_name) {
-                    new_imports.push(state);
-                }
+    /// Returns the paths of the modules that are checked and whose errors are not known,
+    /// the project must be built first
+    pub fn unchecked_files(&self) -> Vec<PathBuf> {
+        self.modules
+            .iter()
+            .filter(|(name, state)| !state.file.followed && !self.errors.contains_key(*name))
+            .map(|(_, state)| state.file.path.clone())
+            .collect()
+    }
+
+    /// Checks the module at path if its errors are not known, the project must be built first.
+    /// The language server checks the modules one at a time to stop when the sources change.
+    pub fn type_check_file(&mut self, path: &Path) {
+        let name = Self::get_module_name(path);
+        let Some(state) = self.modules.get(&name) else {
+            return;
+        };
+        // Modules that changed after the build are checked after the next build
+        if state.file.followed || self.errors.contains_key(&name) || !self.analyzed.contains(&name)
+        {
+            return;
+        }
+        let mut errors = state.file.syntax_errors.clone();
+        let mut checker = TypeChecker::new(state, &self.options);
+        for stmt in &state.file.body {
+            checker.type_check(stmt);
+        }
+        let mut check_errors = checker.errors;
+        check_errors.extend(check_names(state));
+        check_errors.sort_by_key(|error| error.start);
+        for error in check_errors {
+            let code = error.rule.code();
+            let severity = self.options.rule_severity(error.rule);
+            if severity == Severity::Off || is_ignored(&state.file.source, error.start, code) {
+                continue;
             }
+            errors.push(BuildError {
+                msg: error.msg,
+                advice: None,
+                code,
+                severity,
+                path: state.file.path.clone(),
+                start: error.start as u32,
+                end: error.end as u32,
+                range: state.file.line_index.range(error.start, error.end),
+                related: error.related,
+            });
         }
+        self.errors.insert(name, errors);
+    }
 
-        discovered_files.extend(new_imports.clone());
-
-        while !new_imports.is_empty() {
-            let mut next_imports = vec![];
-            for state in new_imports {
-                let resolved_imports = self.resolve_imports(&state);
-                // check if the resolved_imports are not in the current files and add them to the new imports
-                for (_, state) in resolved_imports {
-                    if !self.modules.contains_key(&state.file.module_name) {
-                        // check no discovered file with the same name exists
-                        if !discovered_files
-                            .iter()
-                            .any(|x| x.file.module_name == state.file.module_name)
-                        {
-                            next_imports.push(state);
-                        }
-                    }
+    // Follows the imports of the modules whose imports are not resolved yet
+    // and adds the newly discovered modules.
+    // Each module is parsed only once, even if many modules import it.
+    fn gather_files(
+        &mut self,
+        progress: &mut impl FnMut(BuildPhase, usize, usize) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let mut done = 0;
+        let mut pending: Vec<String> = self
+            .modules
+            .keys()
+            .filter(|module| !self.dependencies.contains_key(*module))
+            .cloned()
+            .collect();
+
+        while let Some(module) = pending.pop() {
+            info!("file: {:#?}", module);
+            let paths = match self.modules.get(&module) {
+                Some(state) => self.resolve_imports(state),
+                None => continue,
+            };
+            let mut imports = HashSet::new();
+            for path in paths {
+                let name = Self::get_module_name(&path);
+                imports.insert(name.clone());
+                if self.modules.contains_key(&name) {
+                    continue;
                 }
+                let build_source = match BuildSource::from_path(path, true) {
+                    Ok(build_source) => build_source,
+                    Err(e) => {
+                        log::warn!("cannot read file: {}", e);
+                        continue;
+                    }
+                };
+                let state = State::new(Box::new(Self::parse_file(build_source)));
+                self.insert_module(name.clone(), state);
+                pending.push(name);
             }
-            discovered_files.extend(next_imports.clone());
-            new_imports = next_imports;
+            self.dependencies.insert(module, imports);
+            done += 1;
+            progress(BuildPhase::Gathering, done, done + pending.len())?;
         }
-        discovered_files
+        ControlFlow::Continue(())
     }
 
     // Resolves imports in a file and return the resolved paths
-    fn resolve_imports(&self, state: &State) -> HashMap<String, State> {
-        let execution_environment = &execution_environment::ExecutionEnvironment {
-            root: self.options.root.clone(),
-            python_version: ruff_python_resolver::python_version::PythonVersion::Py311,
-            python_platform: ruff_python_resolver::python_platform::PythonPlatform::Linux,
-            // Adding a blank path to the extra paths is a hack to make the resolver work
-            extra_paths: vec![PathBuf::from("")],
-        };
-        log::debug!("import options: {:?}", execution_environment);
-
-        let import_config = &Config {
-            typeshed_path: None,
-            stub_path: None,
-            venv_path: Some(self.options.root.clone()),
-            venv: None,
-        };
-        let host = &ruff_python_resolver::host::StaticHost::new(vec![]);
-        let mut resolved_paths = HashMap::new();
+    fn resolve_imports(&self, state: &State) -> Vec<PathBuf> {
+        let mut resolved_paths = vec![];
         for import in state.file.imports.iter() {
             let import_desc = match import {
                 crate::nodes::ImportKinds::Import(i) => {
@@ -223,74 +569,211 @@ impl BuildManager {
                     }
                 }
             };
-            let mut resolved = resolver::resolve_import(
-                state.file.path.as_path(),
-                execution_environment,
-                &import_desc,
-                import_config,
-                host,
-            );
+            let resolved = self.resolve_import(&state.file.path, &import_desc);
             if !resolved.is_import_found {
                 let error = format!("cannot import name '{}'", import_desc.name());
                 log::warn!("{}", error);
+                continue;
             }
-            if resolved.is_import_found {
-                for resolved_path in resolved.resolved_paths.iter() {
-                    let source = match std::fs::read_to_string(resolved_path) {
-                        Ok(source) => source,
-                        Err(e) => {
-                            log::warn!("cannot read file: {}", e);
-                            continue;
-                        }
-                    };
-                    let module = Self::get_module_name(resolved_path);
-                    let build_source = BuildSource {
-                        path: resolved_path.clone(),
-                        module: module.clone(),
-                        source,
-                        followed: true,
-                    };
-                    let file = Box::new(Self::parse_file(build_source));
-                    resolved_paths.insert(module, State::new(file));
-                }
-
-                for (name, implicit_import) in resolved.implicit_imports.iter() {
-                    let source = std::fs::read_to_string(implicit_import.path.clone()).unwrap();
-                    let module = Self::get_module_name(&implicit_import.path);
-                    let build_source = BuildSource {
-                        path: implicit_import.path.clone(),
-                        module: module.clone(),
-                        source,
-                        followed: true,
-                    };
-                    let file = Box::new(Self::parse_file(build_source));
-                    resolved_paths.insert(module, State::new(file));
-                }
-            }
+            // Namespace packages resolve to empty paths
+            resolved_paths.extend(
+                resolved
+                    .resolved_paths
+                    .iter()
+                    .filter(|path| !path.as_os_str().is_empty())
+                    .cloned(),
+            );
+            resolved_paths.extend(
+                resolved
+                    .implicit_imports
+                    .iter()
+                    .map(|(_, implicit_import)| implicit_import.path.clone()),
+            );
         }
 
         resolved_paths
     }
+
+    /// Returns the path of the module imported from the file at path.
+    /// `level` is the number of leading dots of a relative import.
+    pub(crate) fn resolve_module(
+        &self,
+        path: &Path,
+        module: &str,
+        level: usize,
+    ) -> Option<PathBuf> {
+        let import_desc = ruff_python_resolver::module_descriptor::ImportModuleDescriptor {
+            leading_dots: level,
+            name_parts: module
+                .split('.')
+                .filter(|part| !part.is_empty())
+                .map(std::string::ToString::to_string)
+                .collect(),
+            imported_symbols: vec![],
+        };
+        let resolved = self.resolve_import(path, &import_desc);
+        if !resolved.is_import_found {
+            return None;
+        }
+        // The last path is the module itself, the others are its parent packages
+        resolved
+            .resolved_paths
+            .last()
+            .filter(|path| !path.as_os_str().is_empty())
+            .cloned()
+    }
+
+    fn resolve_import(
+        &self,
+        path: &Path,
+        import_desc: &ruff_python_resolver::module_descriptor::ImportModuleDescriptor,
+    ) -> ruff_python_resolver::import_result::ImportResult {
+        let execution_environment = &self.execution_environment();
+        log::debug!("import options: {:?}", execution_environment);
+        resolver::resolve_import(
+            path,
+            execution_environment,
+            import_desc,
+            &self.import_config(),
+            &self.host,
+        )
+    }
+
+    /// Returns the directories that absolute imports are searched in, from the highest priority
+    pub(crate) fn import_search_roots(&self) -> Vec<PathBuf> {
+        let import_config = self.import_config();
+        let mut roots = vec![];
+        roots.extend(import_config.stub_path.clone());
+        roots.push(self.options.root.clone());
+        roots.extend(self.options.extra_paths.iter().cloned());
+        roots.extend(ruff_python_resolver::search::python_search_paths(
+            &import_config,
+            &self.host,
+        ));
+        roots
+    }
+
+    /// Returns the directories outside of the project that imports are resolved from,
+    /// like the site packages of the environment
+    pub fn external_search_roots(&self) -> Vec<PathBuf> {
+        self.import_search_roots()
+            .into_iter()
+            .filter(|path| !path.as_os_str().is_empty() && !path.starts_with(&self.options.root))
+            .collect()
+    }
+
+    /// Returns true if the path is in an installed package or the standard library
+    /// rather than in the project.
+    pub(crate) fn is_third_party(&self, path: &Path) -> bool {
+        // Search paths that contain the project, like the current directory, are skipped
+        // but a virtual environment inside the project is not
+        ruff_python_resolver::search::python_search_paths(&self.import_config(), &self.host)
+            .iter()
+            .filter(|search_path| !self.options.root.starts_with(search_path))
+            .any(|search_path| path.starts_with(search_path))
+    }
+
+    /// Returns true if the path is a module of the project that the user can edit
+    pub(crate) fn is_project_module(&self, path: &Path) -> bool {
+        path.starts_with(&self.options.root) && !self.is_third_party(path)
+    }
+
+    fn execution_environment(&self) -> execution_environment::ExecutionEnvironment {
+        // Adding a blank path to the extra paths is a hack to make the resolver work
+        let mut extra_paths = vec![PathBuf::from("")];
+        extra_paths.extend(self.options.extra_paths.iter().cloned());
+        execution_environment::ExecutionEnvironment {
+            root: self.options.root.clone(),
+            python_version: self
+                .options
+                .python_version
+                .unwrap_or_else(|| self.host.python_version()),
+            python_platform: self
+                .options
+                .python_platform
+                .unwrap_or_else(|| self.host.python_platform()),
+            extra_paths,
+        }
+    }
+
+    fn import_config(&self) -> Config {
+        Config {
+            typeshed_path: None,
+            stub_path: self.options.stub_path.clone(),
+            venv_path: Some(
+                self.options
+                    .venv_path
+                    .clone()
+                    .unwrap_or_else(|| self.options.root.clone()),
+            ),
+            venv: self.options.venv.clone(),
+        }
+    }
+}
+
+/// A `# type: ignore` comment of a line, the codes are given in brackets like
+/// `# type: ignore[unused-import]` and a comment without codes ignores all the errors
+pub(crate) struct TypeIgnore<'a> {
+    pub codes: Option<Vec<&'a str>>,
+    // Offset in the line after the last code, or after `ignore` without codes
+    pub end: usize,
 }
 
-fn get_line_number_of_character_position(source: &str, pos: usize) -> usize {
-    let mut line_number = 1;
-    for (i, c) in source.chars().enumerate() {
-        if i == pos {
-            break;
+pub(crate) fn type_ignore_comment(line: &str) -> Option<TypeIgnore<'_>> {
+    for (index, _) in line.match_indices('#') {
+        let Some(rest) = line[index + 1..].trim_start().strip_prefix("type:") else {
+            continue;
+        };
+        let Some(rest) = rest.trim_start().strip_prefix("ignore") else {
+            continue;
+        };
+        if let Some(codes) = rest.strip_prefix('[') {
+            let Some(close) = codes.find(']') else {
+                continue;
+            };
+            return Some(TypeIgnore {
+                codes: Some(codes[..close].split(',').map(str::trim).collect()),
+                end: line.len() - codes.len() + close,
+            });
         }
-        if c == '\n' {
-            line_number += 1;
+        // Like `# type: ignored`
+        if rest.starts_with(is_identifier_char) {
+            continue;
         }
+        return Some(TypeIgnore {
+            codes: None,
+            end: line.len() - rest.len(),
+        });
     }
-    line_number
+    None
+}
+
+/// Returns the line that contains the byte offset, without its line break
+pub(crate) fn line_at(source: &str, offset: usize) -> (usize, &str) {
+    let start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |index| offset + index);
+    (start, source[start..end].trim_end_matches('\r'))
+}
+
+// Errors are ignored by a `# type: ignore` comment on the line where they start
+fn is_ignored(source: &str, offset: usize, code: &str) -> bool {
+    let (_, line) = line_at(source, offset);
+    type_ignore_comment(line).is_some_and(|comment| {
+        comment
+            .codes
+            .as_ref()
+            .map_or(true, |codes| codes.contains(&code))
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use enderpy_python_parser::line_index::LineCol;
     fn snapshot_symbol_table(source: &str) -> String {
-        let mut manager = BuildManager::new(
+        let mut manager = BuildManager::with_host(
             vec![BuildSource {
                 path: PathBuf::from("test.py"),
                 module: String::from("test"),
@@ -298,6 +781,7 @@ mod tests {
                 followed: false,
             }],
             Settings::test_settings(),
+            PythonHost::default(),
         );
         manager.build();
 
@@ -307,7 +791,7 @@ mod tests {
     }
 
     fn snapshot_type_check(source: &str) -> String {
-        let mut manager = BuildManager::new(
+        let mut manager = BuildManager::with_host(
             vec![BuildSource {
                 path: PathBuf::from("test.py"),
                 module: String::from("test"),
@@ -315,17 +799,289 @@ mod tests {
                 followed: false,
             }],
             Settings::test_settings(),
+            PythonHost::default(),
         );
         manager.type_check();
 
         let errors = manager.get_errors();
         errors
             .iter()
-            .map(|x| format!("{}:{}:{}: {}", x.line, x.start, x.end, x.msg))
+            .map(|x| {
+                format!(
+                    "{}:{}:{}: {}",
+                    x.range.start.line + 1,
+                    x.start,
+                    x.end,
+                    x.msg
+                )
+            })
             .collect::<Vec<String>>()
             .join("\n")
     }
 
+    #[test]
+    fn type_check_multiple_sources() {
+        let sources = ["a.py", "b.py"].map(|path| BuildSource {
+            path: PathBuf::from(path),
+            module: BuildManager::get_module_name(&PathBuf::from(path)),
+            source: "x = undefined()\n".to_string(),
+            followed: false,
+        });
+        let mut manager = BuildManager::with_host(
+            sources.into(),
+            Settings::test_settings(),
+            PythonHost::default(),
+        );
+        manager.type_check();
+
+        let error_paths = manager
+            .get_errors()
+            .into_iter()
+            .map(|e| e.path)
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(
+            error_paths,
+            vec![PathBuf::from("a.py"), PathBuf::from("b.py")]
+        );
+    }
+
+    #[test]
+    fn invalidate_changed_module_and_dependents() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("a.py"), "from b import x\ny = undefined(x)\n").unwrap();
+        std::fs::write(root.join("b.py"), "x = 1\n").unwrap();
+        std::fs::write(root.join("c.py"), "z = 1\n").unwrap();
+        let settings = Settings {
+            root: root.clone(),
+            ..Settings::test_settings()
+        };
+        let sources = ["a.py", "c.py"]
+            .map(|file| BuildSource::from_path(root.join(file), false).unwrap())
+            .into();
+        let mut manager = BuildManager::with_host(sources, settings, PythonHost::default());
+        manager.type_check();
+
+        let a = BuildManager::get_module_name(&root.join("a.py"));
+        let b = BuildManager::get_module_name(&root.join("b.py"));
+        let c = BuildManager::get_module_name(&root.join("c.py"));
+        assert!(manager.modules[&b].file.followed);
+        assert!(manager.dependencies[&a].contains(&b));
+        assert_eq!(manager.get_file_errors(&root.join("a.py")).len(), 1);
+
+        // Changing b invalidates a, which imports it, but not c
+        manager.add_build_source(BuildSource {
+            path: root.join("b.py"),
+            module: b.clone(),
+            source: "x = 2\n".to_string(),
+            followed: true,
+        });
+        assert!(!manager.errors.contains_key(&a));
+        assert!(!manager.analyzed.contains(&a));
+        assert!(!manager.analyzed.contains(&b));
+        assert!(manager.analyzed.contains(&c));
+        assert!(manager.errors.contains_key(&c));
+
+        manager.type_check();
+        assert_eq!(manager.get_file_errors(&root.join("a.py")).len(), 1);
+        assert_eq!(manager.get_errors().len(), 1);
+
+        // Fixing a only invalidates a
+        manager.add_build_source(BuildSource {
+            path: root.join("a.py"),
+            module: a.clone(),
+            source: "from b import x\ny = x\n".to_string(),
+            followed: false,
+        });
+        assert!(manager.analyzed.contains(&b));
+        manager.type_check();
+        assert!(manager.get_errors().is_empty());
+        assert!(manager.dependencies[&a].contains(&b));
+    }
+
+    #[test]
+    fn build_with_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("a.py"), "import b\nimport c\n").unwrap();
+        std::fs::write(root.join("b.py"), "import c\n").unwrap();
+        std::fs::write(root.join("c.py"), "x = 1\n").unwrap();
+        let settings = Settings {
+            root: root.clone(),
+            ..Settings::test_settings()
+        };
+        let sources = vec![BuildSource::from_path(root.join("a.py"), false).unwrap()];
+        let mut manager = BuildManager::with_host(sources, settings, PythonHost::default());
+
+        // A stopped build leaves the rest of the modules to the next build
+        let mut reports = vec![];
+        let stopped = manager.build_with_progress(|phase, done, total| {
+            reports.push((phase, done, total));
+            ControlFlow::Break(())
+        });
+        assert!(stopped.is_break());
+        assert_eq!(reports, vec![(BuildPhase::Gathering, 1, 3)]);
+        assert!(manager.analyzed.is_empty());
+        assert!(!manager.is_built());
+
+        let mut reports = vec![];
+        let built = manager.build_with_progress(|phase, done, total| {
+            reports.push((phase, done, total));
+            ControlFlow::Continue(())
+        });
+        assert!(built.is_continue());
+        assert!(manager.is_built());
+        assert_eq!(
+            reports,
+            vec![
+                (BuildPhase::Gathering, 1, 2),
+                (BuildPhase::Gathering, 2, 2),
+                (BuildPhase::Analyzing, 1, 3),
+                (BuildPhase::Analyzing, 2, 3),
+                (BuildPhase::Analyzing, 3, 3),
+            ]
+        );
+        assert_eq!(manager.analyzed.len(), 3);
+    }
+
+    #[test]
+    fn files_changed_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("a.py"), "from b import x\nfrom c import z\n").unwrap();
+        std::fs::write(root.join("b.py"), "x = 1\n").unwrap();
+        let settings = Settings {
+            root: root.clone(),
+            ..Settings::test_settings()
+        };
+        let sources = vec![BuildSource::from_path(root.join("a.py"), false).unwrap()];
+        let mut manager = BuildManager::with_host(sources, settings, PythonHost::default());
+        manager.type_check();
+        let a = BuildManager::get_module_name(&root.join("a.py"));
+        let b = BuildManager::get_module_name(&root.join("b.py"));
+        let c = BuildManager::get_module_name(&root.join("c.py"));
+        assert!(!manager.modules.contains_key(&c));
+
+        // A changed module keeps being a followed module
+        std::fs::write(root.join("b.py"), "x = 2\n").unwrap();
+        assert!(manager.reload_file(&root.join("b.py")));
+        assert!(manager.modules[&b].file.followed);
+        assert!(!manager.analyzed.contains(&a));
+        assert!(!manager.reload_file(&root.join("c.py")));
+        manager.type_check();
+
+        // A created module is found by the imports that did not resolve before
+        std::fs::write(root.join("c.py"), "z = 1\n").unwrap();
+        manager.refresh_imports();
+        assert!(!manager.dependencies.contains_key(&a));
+        manager.type_check();
+        assert!(manager.modules.contains_key(&c));
+        assert!(manager.dependencies[&a].contains(&c));
+
+        // A deleted module is removed and its dependents are checked again
+        std::fs::remove_file(root.join("b.py")).unwrap();
+        assert!(manager.remove_file(&root.join("b.py")));
+        assert!(!manager.modules.contains_key(&b));
+        assert!(!manager.errors.contains_key(&a));
+        manager.refresh_imports();
+        manager.type_check();
+        assert!(!manager.dependencies[&a].contains(&b));
+        assert!(manager.add_source(&root.join("b.py")).is_err());
+    }
+
+    #[test]
+    fn release_checked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("a.py"), "from b import x\n").unwrap();
+        std::fs::write(root.join("b.py"), "x = 1\n").unwrap();
+        std::fs::write(root.join("c.py"), "z = 1\n").unwrap();
+        let settings = Settings {
+            root: root.clone(),
+            ..Settings::test_settings()
+        };
+        let sources = ["a.py", "b.py", "c.py"]
+            .map(|file| BuildSource::from_path(root.join(file), false).unwrap())
+            .into();
+        let mut manager = BuildManager::with_host(sources, settings, PythonHost::default());
+        manager.type_check();
+
+        // An imported module is not checked anymore but stays for its importers
+        manager.release_file(&root.join("b.py"));
+        assert!(manager.get_state(&root.join("b.py")).unwrap().file.followed);
+        // A module that nothing imports is removed
+        manager.release_file(&root.join("c.py"));
+        assert!(!manager.contains_file(&root.join("c.py")));
+        manager.type_check();
+        assert_eq!(manager.checked_files(), vec![root.join("a.py")]);
+    }
+
+    #[test]
+    fn type_check_error_ranges() {
+        let path = PathBuf::from("test.py");
+        let source = "s = 'é😀'\nx = [s, undefined()]\n";
+        let mut manager = BuildManager::with_host(
+            vec![BuildSource {
+                path: path.clone(),
+                module: String::from("test"),
+                source: source.to_string(),
+                followed: false,
+            }],
+            Settings::test_settings(),
+            PythonHost::default(),
+        );
+        manager.type_check();
+
+        let errors = manager.get_errors();
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!(
+            &source[error.start as usize..error.end as usize],
+            "undefined"
+        );
+        assert_eq!(error.msg, "'undefined' is not defined");
+        assert_eq!(error.range.start, LineCol { line: 1, col: 8 });
+        assert_eq!(error.range.end, LineCol { line: 1, col: 17 });
+
+        let line_index = manager.line_index(&path).unwrap();
+        assert_eq!(
+            line_index.to_utf16(LineCol { line: 0, col: 12 }),
+            LineCol { line: 0, col: 9 }
+        );
+    }
+
+    #[test]
+    fn redeclaration_related_information() {
+        let path = PathBuf::from("test.py");
+        let source = "class A:\n    pass\n\ndef A():\n    pass\n\nx: int = 1\nx: int = 2\n";
+        let mut manager = BuildManager::with_host(
+            vec![BuildSource {
+                path: path.clone(),
+                module: String::from("test"),
+                source: source.to_string(),
+                followed: false,
+            }],
+            Settings::test_settings(),
+            PythonHost::default(),
+        );
+        manager.type_check();
+
+        let errors = manager.get_errors();
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!(error.code, "incompatible-redeclaration");
+        assert_eq!(
+            error.msg,
+            "'A' is redeclared as a function, it was declared as a class"
+        );
+        assert_eq!(error.range.start, LineCol { line: 3, col: 4 });
+        assert_eq!(error.related.len(), 1);
+        let location = &error.related[0].location;
+        assert_eq!(location.path, path);
+        assert_eq!(&source[location.start..location.end], "A");
+        assert_eq!(location.start, 6);
+    }
+
     macro_rules! snap {
         ($name:tt, $path:tt) => {
             #[test]
@@ -370,6 +1126,11 @@ mod tests {
 
     snap!(test_class_def, "../testdata/inputs/class_definition.py");
 
+    snap!(
+        test_multiple_assignment,
+        "../testdata/inputs/multiple_assignment.py"
+    );
+
     snap_type!(test_type_check_var, "../testdata/inputs/type_check_var.py");
     snap_type!(
         test_type_check_call,
@@ -389,4 +1150,24 @@ mod tests {
         test_undefined_names,
         "../testdata/inputs/test_undefined_name.py"
     );
+
+    snap_type!(
+        test_type_check_names,
+        "../testdata/inputs/type_check_names.py"
+    );
+
+    snap_type!(
+        test_type_check_star_import,
+        "../testdata/inputs/type_check_star_import.py"
+    );
+
+    snap_type!(
+        test_type_check_none_assignment,
+        "../testdata/inputs/type_check_none_assignment.py"
+    );
+
+    snap_type!(
+        test_type_check_syntax_error,
+        "../testdata/inputs/type_check_syntax_error.py"
+    );
 }
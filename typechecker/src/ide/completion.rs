@@ -0,0 +1,564 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::build::{BuildManager, BuildSource};
+use crate::state::State;
+use crate::symbol_table::Declaration;
+use crate::type_check::builtins;
+
+use super::namespace::Namespace;
+use super::{
+    find_class_def, format_class_signature, format_function_signature, format_parameter,
+    is_identifier_char, keyword_argument_name, open_call, split_arguments, ImportTarget,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Variable,
+    Parameter,
+    Function,
+    Method,
+    Class,
+    Module,
+    // `name=` inside the parentheses of a call
+    KeywordArgument,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    // Signature of functions and classes
+    pub detail: Option<String>,
+}
+
+// What is completed, found from the text of the line before the cursor
+#[derive(Debug, PartialEq, Eq)]
+enum CompletionContext<'a> {
+    // Modules after `import` or `from`, the parents are the packages before the last dot
+    Module { level: usize, parents: Vec<&'a str> },
+    // Names after `from module import`
+    ImportedName { level: usize, module: &'a str },
+    // Members of the value before the dot
+    Member(Vec<&'a str>),
+    // Names of the enclosing scopes
+    Name,
+    // Nothing is completed, like after the dot of a number
+    None,
+}
+
+impl BuildManager {
+    /// Returns the completions at the byte offset.
+    /// The identifier before the offset is not used to filter the items, the clients do that.
+    pub fn completion(&self, path: &Path, offset: usize) -> Vec<CompletionItem> {
+        let Some(state) = self.get_state(path) else {
+            return vec![];
+        };
+        let source = &state.file.source;
+        let Some(before) = source.get(..offset) else {
+            return vec![];
+        };
+        let word_start = before.trim_end_matches(is_identifier_char).len();
+        let line_start = before[..word_start]
+            .rfind(['\n', '\r'])
+            .map_or(0, |index| index + 1);
+
+        let position = scope_position(source, line_start);
+
+        let mut items = match completion_context(&before[line_start..word_start]) {
+            CompletionContext::Module { level, parents } => self
+                .module_names(path, level, &parents)
+                .into_iter()
+                .map(module_item)
+                .collect(),
+            CompletionContext::ImportedName { level, module } => {
+                self.imported_name_completions(path, level, module)
+            }
+            CompletionContext::Member(receiver) => self
+                .resolve_dotted_name(state, &receiver, position)
+                .and_then(|target| self.namespace_of(target))
+                .map(|namespace| self.member_completions(&namespace))
+                .unwrap_or_default(),
+            CompletionContext::Name => {
+                let mut items = self.keyword_argument_completions(state, &before[..word_start]);
+                items.extend(self.scope_completions(state, position));
+                items
+            }
+            CompletionContext::None => vec![],
+        };
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item.label.clone()));
+        items
+    }
+
+    // Names of the scopes that enclose the position and the builtins
+    fn scope_completions(&self, state: &State, position: usize) -> Vec<CompletionItem> {
+        let mut items = vec![];
+        for scope in state.symbol_table().scopes_at_position(position) {
+            let mut symbols = scope.symbols().collect::<Vec<_>>();
+            symbols.sort_by(|a, b| a.name.cmp(&b.name));
+            for symbol in symbols {
+                if let Some(declaration) = symbol.last_declaration() {
+                    items.push(self.completion_item(&symbol.name, declaration));
+                }
+            }
+        }
+        items.extend(builtins::BUILTINS.iter().map(|name| CompletionItem {
+            label: name.to_string(),
+            kind: CompletionKind::Function,
+            detail: None,
+        }));
+        items
+    }
+
+    fn member_completions(&self, namespace: &Namespace) -> Vec<CompletionItem> {
+        let mut items = self
+            .members(namespace)
+            .iter()
+            .map(|(name, declaration)| self.completion_item(name, declaration))
+            .collect::<Vec<_>>();
+        // The submodules of a package are its members too
+        if let Namespace::Module(path) = namespace {
+            if let Some(dir) = package_dir(path) {
+                items.extend(list_modules(&dir).into_iter().map(module_item));
+            }
+        }
+        items
+    }
+
+    // Names that can be imported from the module, the module may not be imported yet
+    fn imported_name_completions(
+        &self,
+        path: &Path,
+        level: usize,
+        module: &str,
+    ) -> Vec<CompletionItem> {
+        // `from . import` imports the modules of the package
+        if module.is_empty() {
+            return self
+                .module_names(path, level, &[])
+                .into_iter()
+                .map(module_item)
+                .collect();
+        }
+        let Some(module_path) = self.resolve_module(path, module, level) else {
+            return vec![];
+        };
+        let namespace = Namespace::Module(module_path.clone());
+        if self.get_state(&module_path).is_some() {
+            return self.member_completions(&namespace);
+        }
+        // The module is parsed only to find its names, it is not added to the project
+        let Ok(build_source) = BuildSource::from_path(module_path.clone(), true) else {
+            return vec![];
+        };
+        let mut state = State::new(Box::new(Self::parse_file(build_source)));
+        state.populate_symbol_table();
+        let mut symbols = state
+            .symbol_table()
+            .global_scope()
+            .symbols()
+            .collect::<Vec<_>>();
+        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut items = symbols
+            .into_iter()
+            .filter_map(|symbol| {
+                let declaration = symbol.last_declaration()?;
+                Some(CompletionItem {
+                    label: symbol.name.clone(),
+                    kind: declaration_kind(declaration),
+                    detail: None,
+                })
+            })
+            .collect::<Vec<_>>();
+        if let Some(dir) = package_dir(&module_path) {
+            items.extend(list_modules(&dir).into_iter().map(module_item));
+        }
+        items
+    }
+
+    // Keyword arguments of the function that is called at the end of the text
+    fn keyword_argument_completions(&self, state: &State, text: &str) -> Vec<CompletionItem> {
+        let Some((callee, arguments)) = call_at_end(text) else {
+            return vec![];
+        };
+        let Some((function, bound)) = self.called_function(state, &callee, text.len()) else {
+            return vec![];
+        };
+        let Some(source) = self
+            .get_state_by_module_name(&function.declaration_path.module_name)
+            .map(|state| &state.file.source)
+        else {
+            return vec![];
+        };
+        let passed = passed_keywords(arguments);
+        let args = &function.function_node.args;
+        let skip = usize::from(bound && args.posonlyargs.is_empty());
+        let first_default = args.args.len().saturating_sub(args.defaults.len());
+        let positional = args.args.iter().enumerate().skip(skip).map(|(i, arg)| {
+            let default = i
+                .checked_sub(first_default)
+                .and_then(|index| args.defaults.get(index));
+            (arg, default)
+        });
+        let keyword_only = args
+            .kwonlyargs
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| (arg, args.kw_defaults.get(i).and_then(|d| d.as_ref())));
+        positional
+            .chain(keyword_only)
+            .filter(|(arg, _)| !passed.contains(arg.arg.as_str()))
+            .map(|(arg, default)| CompletionItem {
+                label: format!("{}=", arg.arg),
+                kind: CompletionKind::KeywordArgument,
+                detail: Some(format_parameter(arg, default, source)),
+            })
+            .collect()
+    }
+
+    fn completion_item(&self, name: &str, declaration: &Declaration) -> CompletionItem {
+        let source = self
+            .get_state_by_module_name(&declaration.declaration_path().module_name)
+            .map(|state| (state, &state.file.source));
+        let (kind, detail) = match declaration {
+            Declaration::Function(f) => (
+                declaration_kind(declaration),
+                source.map(|(_, source)| format_function_signature(&f.function_node, source)),
+            ),
+            Declaration::Class(c) => (
+                CompletionKind::Class,
+                source.and_then(|(state, source)| {
+                    let class = find_class_def(&state.file.body, c.declaration_path.node)?;
+                    Some(format_class_signature(class, source))
+                }),
+            ),
+            Declaration::Alias(_) => match self.follow_imports(name, declaration.clone()) {
+                Some(ImportTarget::Declaration(imported_name, imported)) => {
+                    let item = self.completion_item(&imported_name, &imported);
+                    (item.kind, item.detail)
+                }
+                Some(ImportTarget::Module(_)) => (CompletionKind::Module, None),
+                None => (declaration_kind(declaration), None),
+            },
+            _ => (declaration_kind(declaration), None),
+        };
+        CompletionItem {
+            label: name.to_string(),
+            kind,
+            detail,
+        }
+    }
+
+    // Names of the modules that can be imported from the packages.
+    // Relative imports are searched from the package of the file,
+    // absolute imports from the search roots of the import resolver.
+    fn module_names(&self, path: &Path, level: usize, parents: &[&str]) -> Vec<String> {
+        let roots = if level > 0 {
+            let mut dir = path.parent();
+            for _ in 1..level {
+                dir = dir.and_then(Path::parent);
+            }
+            dir.map(Path::to_path_buf).into_iter().collect()
+        } else {
+            self.import_search_roots()
+        };
+        let mut names = vec![];
+        for root in roots {
+            let dir = parents.iter().fold(root, |dir, parent| dir.join(parent));
+            names.extend(list_modules(&dir));
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+// Returns a position in the scope of the line that starts at the offset.
+// The nodes of a function end at its last statement, so the lines that are being written
+// at the end of a function are outside of it. The scope is found from the indentation instead:
+// a line that is indented more than the previous line is in the block of the previous line,
+// otherwise it is in the same scope as the previous line with the same indentation.
+fn scope_position(source: &str, line_start: usize) -> usize {
+    let indentation = |line: &str| line.len() - line.trim_start().len();
+    let line_end = source[line_start..]
+        .find(['\n', '\r'])
+        .map_or(source.len(), |index| line_start + index);
+    let current = indentation(&source[line_start..line_end]);
+
+    let mut end = line_start;
+    while end > 0 {
+        let start = source[..end]
+            .trim_end_matches(['\n', '\r'])
+            .rfind(['\n', '\r'])
+            .map_or(0, |index| index + 1);
+        let line = source[start..end].trim_end();
+        end = start;
+        if line.trim_start().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let previous = indentation(line);
+        if previous < current {
+            // Inside the block, like the `:` of `def f():`
+            return start + line.len() - 1;
+        }
+        if previous == current {
+            // Before the statement, still inside the enclosing scope
+            return (start + previous).saturating_sub(1);
+        }
+    }
+    line_start
+}
+
+fn declaration_kind(declaration: &Declaration) -> CompletionKind {
+    match declaration {
+        Declaration::Variable(_) => CompletionKind::Variable,
+        Declaration::Parameter(_) => CompletionKind::Parameter,
+        Declaration::Function(f) if f.is_method => CompletionKind::Method,
+        Declaration::Function(_) => CompletionKind::Function,
+        Declaration::Class(_) => CompletionKind::Class,
+        Declaration::Alias(a) if a.name.is_none() => CompletionKind::Module,
+        Declaration::Alias(_) => CompletionKind::Variable,
+    }
+}
+
+fn module_item(name: String) -> CompletionItem {
+    CompletionItem {
+        label: name,
+        kind: CompletionKind::Module,
+        detail: None,
+    }
+}
+
+fn completion_context(line: &str) -> CompletionContext<'_> {
+    let trimmed = line.trim_start();
+    if let Some(rest) = trimmed.strip_prefix("import ") {
+        // `import a, b` completes the module after the last comma
+        let module = rest.rsplit(',').next().unwrap_or_default().trim_start();
+        return module_context(module);
+    }
+    if let Some(rest) = trimmed.strip_prefix("from ") {
+        return match rest.split_once(" import ") {
+            Some((module, _)) => {
+                let module = module.trim();
+                let relative = module.trim_start_matches('.');
+                CompletionContext::ImportedName {
+                    level: module.len() - relative.len(),
+                    module: relative,
+                }
+            }
+            None => module_context(rest),
+        };
+    }
+    if let Some(receiver) = line.strip_suffix('.') {
+        let start = receiver
+            .trim_end_matches(|c| is_identifier_char(c) || c == '.')
+            .len();
+        let parts = receiver[start..].split('.').collect::<Vec<_>>();
+        let is_name = |part: &&str| part.starts_with(|c: char| c.is_alphabetic() || c == '_');
+        if parts.iter().all(is_name) {
+            return CompletionContext::Member(parts);
+        }
+        return CompletionContext::None;
+    }
+    CompletionContext::Name
+}
+
+fn module_context(module: &str) -> CompletionContext<'_> {
+    // Nothing is completed after `import a as`
+    if module.contains(char::is_whitespace) {
+        return CompletionContext::None;
+    }
+    let relative = module.trim_start_matches('.');
+    CompletionContext::Module {
+        level: module.len() - relative.len(),
+        parents: relative
+            .split('.')
+            .filter(|part| !part.is_empty())
+            .collect(),
+    }
+}
+
+// Returns the callee and the arguments text when the text ends inside the parentheses of a call,
+// at the start of an argument
+fn call_at_end(text: &str) -> Option<(Vec<&str>, &str)> {
+    let trimmed = text.trim_end();
+    if !trimmed.ends_with(['(', ',']) {
+        return None;
+    }
+    open_call(trimmed)
+}
+
+// Keywords that are already passed in the arguments text, like `a` in `a=1, `
+fn passed_keywords(arguments: &str) -> HashSet<&str> {
+    split_arguments(arguments)
+        .into_iter()
+        .filter_map(keyword_argument_name)
+        .collect()
+}
+
+// Returns the directory of a package from the path of its `__init__` file
+fn package_dir(path: &Path) -> Option<PathBuf> {
+    path.file_stem()
+        .is_some_and(|stem| stem == "__init__")
+        .then(|| path.parent().map(Path::to_path_buf))
+        .flatten()
+}
+
+// Names of the modules and packages in the directory
+fn list_modules(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let is_identifier = |name: &str| {
+        name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(is_identifier_char)
+    };
+    let mut names = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = if path.is_dir() {
+            // Like `__pycache__`
+            match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) if !name.starts_with("__") => name,
+                _ => continue,
+            }
+        } else {
+            match (path.file_stem(), path.extension()) {
+                (Some(stem), Some(extension)) if extension == "py" || extension == "pyi" => {
+                    match stem.to_str() {
+                        Some(stem) if stem != "__init__" => stem,
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            }
+        };
+        if is_identifier(name) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ide::tests::build_project;
+
+    // Returns the labels of the completions at the end of main.py
+    fn complete(main: &str, files: &[(&str, &str)]) -> Vec<String> {
+        let mut files = files.to_vec();
+        files.push(("main.py", main));
+        let (manager, dir) = build_project(&files);
+        manager
+            .completion(&dir.path().join("main.py"), main.len())
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_completion_context() {
+        use CompletionContext::*;
+        assert_eq!(
+            completion_context("import os, "),
+            Module {
+                level: 0,
+                parents: vec![]
+            }
+        );
+        assert_eq!(
+            completion_context("from ..pkg."),
+            Module {
+                level: 2,
+                parents: vec!["pkg"]
+            }
+        );
+        assert_eq!(
+            completion_context("from .pkg import a, "),
+            ImportedName {
+                level: 1,
+                module: "pkg"
+            }
+        );
+        assert_eq!(completion_context("    x = a.b."), Member(vec!["a", "b"]));
+        assert_eq!(completion_context("x = 1."), None);
+        assert_eq!(completion_context("import a as "), None);
+        assert_eq!(completion_context("    return "), Name);
+
+        assert_eq!(call_at_end("f(a, g(1), "), Some((vec!["f"], "a, g(1),")));
+        assert_eq!(
+            call_at_end("x = obj.method("),
+            Some((vec!["obj", "method"], ""))
+        );
+        assert_eq!(call_at_end("f([1, "), Option::None);
+        assert_eq!(call_at_end("f(a + "), Option::None);
+    }
+
+    #[test]
+    fn complete_scope_names() {
+        let labels = complete("x = 1\n\ndef f(param):\n    y = 2\n    ", &[]);
+        for name in ["param", "y", "x", "f", "print"] {
+            assert!(
+                labels.contains(&name.to_string()),
+                "{} in {:?}",
+                name,
+                labels
+            );
+        }
+    }
+
+    #[test]
+    fn complete_members() {
+        let main = r#"import pkg
+from pkg.mod import Base
+
+class A(Base):
+    attr = 1
+
+    def method(self):
+        self."#;
+        let files = [
+            ("pkg/__init__.py", "value = 1\n"),
+            (
+                "pkg/mod.py",
+                "class Base:\n    def base_method(self):\n        pass\n",
+            ),
+        ];
+        let labels = complete(main, &files);
+        assert_eq!(labels, vec!["attr", "base_method", "method"]);
+
+        let labels = complete("import pkg\npkg.", &files);
+        assert_eq!(labels, vec!["value", "mod"]);
+    }
+
+    #[test]
+    fn complete_modules() {
+        let files = [
+            ("pkg/__init__.py", ""),
+            ("pkg/mod.py", "def helper():\n    pass\n"),
+            ("pkg/__pycache__/mod.pyc", ""),
+        ];
+        assert!(complete("import ", &files).contains(&"pkg".to_string()));
+        assert_eq!(complete("import pkg.", &files), vec!["mod"]);
+        assert_eq!(complete("from pkg import ", &files), vec!["mod"]);
+        assert_eq!(complete("from pkg.mod import ", &files), vec!["helper"]);
+    }
+
+    #[test]
+    fn complete_keyword_arguments() {
+        let source = r#"class A:
+    def __init__(self, a, *, b=1):
+        pass
+
+"#;
+        let labels = complete(&format!("{}A(", source), &[]);
+        assert_eq!(labels[..2], ["a=", "b="]);
+        let labels = complete(&format!("{}A(a=1, ", source), &[]);
+        assert_eq!(labels[0], "b=");
+        assert!(!labels.contains(&"a=".to_string()));
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use crate::build::BuildManager;
+
+use super::{identifier_at_offset, ImportTarget, Location};
+
+impl BuildManager {
+    /// Returns the location of the declaration that the identifier at the byte offset refers to.
+    /// Names bound by imports are followed to their declaration in the imported module.
+    pub fn definition(&self, path: &Path, offset: usize) -> Option<Location> {
+        let state = self.get_state(path)?;
+        let identifier = identifier_at_offset(&state.file.body, &state.file.source, offset)?;
+        let declaration = self.resolve_identifier(state, &identifier)?;
+        match self.follow_imports(identifier.name(), declaration)? {
+            ImportTarget::Declaration(name, declaration) => {
+                self.declaration_location(&name, &declaration)
+            }
+            ImportTarget::Module(path) => Some(Location {
+                path,
+                start: 0,
+                end: 0,
+            }),
+        }
+    }
+
+    /// Returns the location where the identifier at the byte offset is declared in its module.
+    /// Unlike `definition`, imports are not followed.
+    pub fn declaration(&self, path: &Path, offset: usize) -> Option<Location> {
+        let state = self.get_state(path)?;
+        let identifier = identifier_at_offset(&state.file.body, &state.file.source, offset)?;
+        let declaration = self.resolve_identifier(state, &identifier)?;
+        self.declaration_location(identifier.name(), &declaration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::ide::tests::build_project;
+    use crate::ide::Location;
+
+    // Returns the file name and the text at the location
+    fn location_text(location: Location) -> (String, String) {
+        let source = fs::read_to_string(&location.path).unwrap();
+        (
+            location
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            source[location.start..location.end].to_string(),
+        )
+    }
+
+    fn offset_of(root: &Path, file: &str, text: &str) -> (PathBuf, usize) {
+        let path = root.join(file);
+        let source = fs::read_to_string(&path).unwrap();
+        (path, source.find(text).unwrap())
+    }
+
+    #[test]
+    fn definition_in_module() {
+        let source =
+            "x: int = 1\n\ndef f(param):\n    return param + x\n\nclass A:\n    pass\n\nf(A)\n";
+        let (manager, dir) = build_project(&[("a.py", source)]);
+        let path = dir.path().join("a.py");
+        let definition = |text: &str| {
+            manager
+                .definition(&path, source.find(text).unwrap())
+                .map(|location| location.start)
+        };
+
+        assert_eq!(definition("x\n"), Some(0));
+        assert_eq!(definition("param +"), source.find("param"));
+        assert_eq!(definition("f(A)"), source.find("f("));
+        assert_eq!(definition("A)"), source.find("A:"));
+    }
+
+    #[test]
+    fn definition_follows_imports() {
+        let (manager, dir) = build_project(&[
+            (
+                "main.py",
+                "from lib import helper as h\nimport pkg.mod\nfrom pkg import mod\n\nh()\n",
+            ),
+            ("lib.py", "from pkg.mod import helper\n"),
+            ("pkg/__init__.py", ""),
+            ("pkg/mod.py", "def helper():\n    pass\n"),
+        ]);
+
+        // `h` is declared by the import in main and defined in pkg/mod
+        let (path, offset) = offset_of(dir.path(), "main.py", "h()");
+        assert_eq!(
+            location_text(manager.declaration(&path, offset).unwrap()),
+            ("main.py".to_string(), "h".to_string())
+        );
+        assert_eq!(
+            location_text(manager.definition(&path, offset).unwrap()),
+            ("mod.py".to_string(), "helper".to_string())
+        );
+
+        // Imported modules are defined by their file
+        let (path, offset) = offset_of(dir.path(), "main.py", "pkg.mod");
+        let location = manager.definition(&path, offset).unwrap();
+        assert_eq!(location.path, dir.path().join("pkg/__init__.py"));
+        let (path, offset) = offset_of(dir.path(), "main.py", "mod\n\n");
+        let location = manager.definition(&path, offset).unwrap();
+        assert_eq!(location.path, dir.path().join("pkg/mod.py"));
+    }
+}
@@ -0,0 +1,520 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{Alias, ConstantValue, Expression, GetNode, Node, Statement};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::{line_at, type_ignore_comment, BuildError, BuildManager, BuildSource};
+use crate::project::find_source_files;
+use crate::state::State;
+use crate::symbol_table::Declaration;
+use crate::type_check::rules::Rule;
+
+use super::{node_text, TextEdit};
+
+/// A change that fixes an error, the edits are in the file of the error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    // Describes the change to pick it among the fixes, like `Add 'from a import b'`
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+}
+
+impl BuildManager {
+    /// Returns the fixes of an error of the project.
+    /// All the errors can be ignored with a comment, except syntax errors.
+    /// Fixes look at the other modules, like the ones that can be imported,
+    /// so they are found when they are asked for and not kept with the errors.
+    pub fn fixes(&self, error: &BuildError) -> Vec<Fix> {
+        let Some(state) = self.get_state(&error.path) else {
+            return vec![];
+        };
+        let source = &state.file.source;
+        let range = Node::new(error.start as usize, error.end as usize);
+        let mut fixes = vec![];
+        if error.code == Rule::UndefinedName.code() {
+            fixes.extend(self.import_fixes(state, node_text(source, range)));
+        } else if error.code == Rule::UnusedImport.code() {
+            fixes.extend(remove_import_fix(state, range.start));
+        } else if error.code == Rule::IncompatibleAssignment.code() {
+            fixes.extend(optional_fix(state, range.start));
+        }
+        if error.code != Rule::SyntaxError.code() {
+            fixes.extend(ignore_fix(source, range.start, error.code));
+        }
+        fixes
+    }
+
+    // Imports the name from the project modules that declare it, or the module with the name.
+    // The candidates are the source files of the project, the ones that are not loaded
+    // are read from disk and analyzed if they mention the name.
+    fn import_fixes(&self, state: &State, name: &str) -> Vec<Fix> {
+        let mut paths = find_source_files(&[], self.settings()).unwrap_or_else(|e| {
+            log::warn!("cannot find source files: {}", e);
+            vec![]
+        });
+        paths.extend(self.modules.values().map(|other| other.file.path.clone()));
+        paths.sort();
+        paths.dedup();
+        let mut modules = paths
+            .into_iter()
+            .filter(|path| *path != state.file.path && self.is_project_module(path))
+            .filter_map(|path| Some((self.import_name(&path)?, path)))
+            .collect::<Vec<_>>();
+        modules.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut fixes = vec![];
+        for (module, path) in modules {
+            let declares_name = match self.get_state(&path) {
+                Some(other) => declares_name(other, name),
+                None => read_module(&path, name).is_some_and(|other| declares_name(&other, name)),
+            };
+            let statement = if declares_name {
+                format!("from {} import {}", module, name)
+            } else {
+                match module.rsplit_once('.') {
+                    Some((package, submodule)) if submodule == name => {
+                        format!("from {} import {}", package, name)
+                    }
+                    None if module == name => format!("import {}", name),
+                    _ => continue,
+                }
+            };
+            fixes.push(Fix {
+                title: format!("Add '{}'", statement),
+                edits: vec![insert_import(state, &statement)],
+            });
+        }
+        fixes
+    }
+}
+
+fn declares_name(state: &State, name: &str) -> bool {
+    state
+        .symbol_table()
+        .global_scope()
+        .lookup(name)
+        .is_some_and(|symbol| {
+            symbol.declarations.iter().any(|declaration| {
+                matches!(
+                    declaration,
+                    Declaration::Class(_) | Declaration::Function(_) | Declaration::Variable(_)
+                )
+            })
+        })
+}
+
+// Reads and analyzes a module that is not in the project, unless it does not mention the name
+fn read_module(path: &Path, name: &str) -> Option<State> {
+    let build_source = BuildSource::from_path(path.to_path_buf(), true).ok()?;
+    if !build_source.source.contains(name) {
+        return None;
+    }
+    let mut state = State::new(Box::new(BuildManager::parse_file(build_source)));
+    state.populate_symbol_table();
+    Some(state)
+}
+
+// Adds the import statement after the imports at the top of the module
+fn insert_import(state: &State, statement: &str) -> TextEdit {
+    let source = &state.file.source;
+    let mut after = None;
+    for (i, stmt) in state.file.body.iter().enumerate() {
+        match stmt {
+            Statement::Import(_) | Statement::ImportFrom(_) => after = Some(stmt.get_node().end),
+            // The docstring of the module stays first
+            Statement::ExpressionStatement(Expression::Constant(c))
+                if i == 0 && matches!(c.value, ConstantValue::Str(_)) =>
+            {
+                after = Some(stmt.get_node().end)
+            }
+            _ => break,
+        }
+    }
+    let offset = match after {
+        Some(end) => source[end..]
+            .find('\n')
+            .map_or(source.len(), |index| end + index + 1),
+        None => 0,
+    };
+    let new_text = if offset == source.len() && !source.is_empty() && !source.ends_with('\n') {
+        format!("\n{}\n", statement)
+    } else {
+        format!("{}\n", statement)
+    };
+    TextEdit {
+        start: offset,
+        end: offset,
+        new_text,
+    }
+}
+
+// Removes the alias that starts at the offset from its import statement,
+// or the whole statement when it imports only the alias
+fn remove_import_fix(state: &State, offset: usize) -> Option<Fix> {
+    let source = &state.file.source;
+    let found = find_import(&state.file.body, offset, false)?;
+    let alias = &found.names[found.index];
+    let title = format!(
+        "Remove the unused import '{}'",
+        node_text(source, alias.node)
+    );
+    let edit = match (found.index.checked_sub(1), found.names.get(found.index + 1)) {
+        // The separator after the alias is removed with it
+        (_, Some(next)) => TextEdit {
+            start: alias.node.start,
+            end: next.node.start,
+            new_text: String::new(),
+        },
+        (Some(previous), None) => TextEdit {
+            start: found.names[previous].node.end,
+            end: alias.node.end,
+            new_text: String::new(),
+        },
+        (None, None) => {
+            let node = found.statement.get_node();
+            let (line_start, line) = line_at(source, node.start);
+            let line_end = line_start + line.len();
+            let before = &source[line_start..node.start];
+            let after = &source[node.end.min(line_end)..line_end];
+            // Statements that share their line with other statements are not removed
+            if !before.trim().is_empty()
+                || !(after.trim().is_empty() || after.trim_start().starts_with('#'))
+            {
+                return None;
+            }
+            if found.is_only_statement {
+                // A block cannot be empty
+                TextEdit {
+                    start: node.start,
+                    end: node.end,
+                    new_text: "pass".to_string(),
+                }
+            } else {
+                let end = source[line_end..]
+                    .find('\n')
+                    .map_or(source.len(), |index| line_end + index + 1);
+                TextEdit {
+                    start: line_start,
+                    end,
+                    new_text: String::new(),
+                }
+            }
+        }
+    };
+    Some(Fix {
+        title,
+        edits: vec![edit],
+    })
+}
+
+struct ImportAt<'a> {
+    statement: &'a Statement,
+    names: &'a [Alias],
+    // Index of the alias in the names of the statement
+    index: usize,
+    // The statement is the only statement of a block, like the body of an `if`
+    is_only_statement: bool,
+}
+
+fn find_import(body: &[Statement], offset: usize, nested: bool) -> Option<ImportAt<'_>> {
+    for statement in body {
+        let node = statement.get_node();
+        if offset < node.start || node.end < offset {
+            continue;
+        }
+        let names = match statement {
+            Statement::Import(i) => &i.names,
+            Statement::ImportFrom(i) => &i.names,
+            _ => {
+                return child_bodies(statement)
+                    .into_iter()
+                    .find_map(|child| find_import(child, offset, true));
+            }
+        };
+        return Some(ImportAt {
+            statement,
+            names,
+            index: names.iter().position(|alias| alias.node.start == offset)?,
+            is_only_statement: nested && body.len() == 1,
+        });
+    }
+    None
+}
+
+// The blocks of statements inside a compound statement
+fn child_bodies(statement: &Statement) -> Vec<&[Statement]> {
+    match statement {
+        Statement::IfStatement(i) => vec![&i.body, &i.orelse],
+        Statement::WhileStatement(w) => vec![&w.body, &w.orelse],
+        Statement::ForStatement(f) => vec![&f.body, &f.orelse],
+        Statement::AsyncForStatement(f) => vec![&f.body, &f.orelse],
+        Statement::WithStatement(w) => vec![&w.body],
+        Statement::AsyncWithStatement(w) => vec![&w.body],
+        Statement::TryStatement(t) => std::iter::once(&t.body)
+            .chain(t.handlers.iter().map(|handler| &handler.body))
+            .chain([&t.orelse, &t.finalbody])
+            .map(Vec::as_slice)
+            .collect(),
+        Statement::TryStarStatement(t) => std::iter::once(&t.body)
+            .chain(t.handlers.iter().map(|handler| &handler.body))
+            .chain([&t.orelse, &t.finalbody])
+            .map(Vec::as_slice)
+            .collect(),
+        Statement::FunctionDef(f) => vec![&f.body],
+        Statement::AsyncFunctionDef(f) => vec![&f.body],
+        Statement::ClassDef(c) => vec![&c.body],
+        Statement::Match(m) => m.cases.iter().map(|case| case.body.as_slice()).collect(),
+        _ => vec![],
+    }
+}
+
+// Wraps the annotation of the `None` at the offset in `Optional`, like `x: Optional[int] = None`
+fn optional_fix(state: &State, offset: usize) -> Option<Fix> {
+    struct AnnotationFinder<'a> {
+        source: &'a str,
+        offset: usize,
+        found: Option<Node>,
+    }
+    impl<'a> AstWalker<'a> for AnnotationFinder<'_> {
+        fn enter(&mut self, node: AstNode<'a>) -> bool {
+            let annotation = match node {
+                AstNode::Statement(Statement::AnnAssignStatement(a)) => Some(&a.annotation),
+                AstNode::Arg(a) => a.annotation.as_ref(),
+                _ => None,
+            };
+            // The annotation is followed by `=` and the value
+            if let Some(annotation) = annotation {
+                let annotation = annotation.get_node();
+                let between = self
+                    .source
+                    .get(annotation.end..self.offset)
+                    .unwrap_or_default();
+                if between.trim() == "=" {
+                    self.found = Some(annotation);
+                }
+            }
+            self.found.is_none()
+        }
+    }
+    let source = &state.file.source;
+    let mut finder = AnnotationFinder {
+        source,
+        offset,
+        found: None,
+    };
+    walk_body(&mut finder, &state.file.body);
+    let annotation = finder.found?;
+
+    let global_scope = state.symbol_table().global_scope();
+    let imports_typing = global_scope.lookup("typing").is_some_and(|symbol| {
+        symbol.declarations.iter().any(|declaration| {
+            matches!(declaration, Declaration::Alias(a) if a.module == "typing" && a.name.is_none())
+        })
+    });
+    let mut edits = vec![];
+    let optional = if global_scope.lookup("Optional").is_some() {
+        "Optional"
+    } else if imports_typing {
+        "typing.Optional"
+    } else {
+        edits.push(insert_import(state, "from typing import Optional"));
+        "Optional"
+    };
+    let new_text = format!("{}[{}]", optional, node_text(source, annotation));
+    let title = format!("Change the annotation to '{}'", new_text);
+    edits.push(TextEdit {
+        start: annotation.start,
+        end: annotation.end,
+        new_text,
+    });
+    Some(Fix { title, edits })
+}
+
+// Adds the code to the `# type: ignore` comment of the line or adds the comment
+fn ignore_fix(source: &str, offset: usize, code: &str) -> Option<Fix> {
+    let (line_start, line) = line_at(source, offset);
+    let edit = match type_ignore_comment(line) {
+        Some(comment) => {
+            let codes = comment.codes?;
+            let separator = if codes.iter().all(|code| code.is_empty()) {
+                ""
+            } else {
+                ", "
+            };
+            let start = line_start + comment.end;
+            TextEdit {
+                start,
+                end: start,
+                new_text: format!("{}{}", separator, code),
+            }
+        }
+        None => {
+            let start = line_start + line.trim_end().len();
+            TextEdit {
+                start,
+                end: start,
+                new_text: format!("  # type: ignore[{}]", code),
+            }
+        }
+    };
+    Some(Fix {
+        title: format!("Ignore '{}' errors on this line", code),
+        edits: vec![edit],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use enderpy_python_parser::line_index::LineColRange;
+
+    use super::Fix;
+    use crate::build::BuildError;
+    use crate::ide::tests::build_project;
+    use crate::type_check::rules::Rule;
+
+    // Applies the edits of the fix with the title to the source
+    fn apply(source: &str, fixes: &[Fix], title: &str) -> String {
+        let fix = fixes
+            .iter()
+            .find(|fix| fix.title == title)
+            .unwrap_or_else(|| panic!("no fix '{}' in {:?}", title, fixes));
+        let mut source = source.to_string();
+        let mut edits = fix.edits.clone();
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+        for edit in edits {
+            source.replace_range(edit.start..edit.end, &edit.new_text);
+        }
+        source
+    }
+
+    // An error of the rule on the first occurrence of the text, without its trailing whitespace
+    fn error_at(path: &Path, source: &str, text: &str, rule: Rule) -> BuildError {
+        let start = source.find(text).unwrap();
+        let end = start + text.trim_end().len();
+        BuildError {
+            msg: String::new(),
+            advice: None,
+            code: rule.code(),
+            severity: rule.default_severity(),
+            path: path.to_path_buf(),
+            start: start as u32,
+            end: end as u32,
+            range: LineColRange::default(),
+            related: vec![],
+        }
+    }
+
+    #[test]
+    fn fixes() {
+        let source = r#""""Docs."""
+import os
+from lib import a, b
+
+def f(x: int = None):
+    return helper(a)
+
+value = 1  # type: ignore[type-error]
+y: Helper = None + value
+"#;
+        let (manager, dir) = build_project(&[
+            ("main.py", source),
+            ("lib.py", "a = 1\nb = 2\n\ndef helper():\n    pass\n"),
+            ("pkg/__init__.py", ""),
+            ("pkg/util.py", "class Helper:\n    pass\n"),
+        ]);
+        let path = dir.path().join("main.py");
+        let fixes = |text: &str, rule: Rule| manager.fixes(&error_at(&path, source, text, rule));
+
+        assert_eq!(
+            apply(
+                source,
+                &fixes("os", Rule::UnusedImport),
+                "Remove the unused import 'os'"
+            ),
+            source.replace("import os\n", "")
+        );
+        assert_eq!(
+            apply(
+                source,
+                &fixes("b\n", Rule::UnusedImport),
+                "Remove the unused import 'b'"
+            ),
+            source.replace("a, b", "a")
+        );
+        assert_eq!(
+            apply(
+                source,
+                &fixes("helper", Rule::UndefinedName),
+                "Add 'from lib import helper'"
+            ),
+            source.replace(
+                "from lib import a, b\n",
+                "from lib import a, b\nfrom lib import helper\n"
+            )
+        );
+        assert_eq!(
+            apply(
+                source,
+                &fixes("None", Rule::IncompatibleAssignment),
+                "Change the annotation to 'Optional[int]'"
+            ),
+            source
+                .replace(
+                    "from lib import a, b\n",
+                    "from lib import a, b\nfrom typing import Optional\n"
+                )
+                .replace("x: int", "x: Optional[int]")
+        );
+        assert_eq!(
+            apply(
+                source,
+                &fixes("Helper", Rule::UndefinedName),
+                "Add 'from pkg.util import Helper'"
+            ),
+            source.replace(
+                "from lib import a, b\n",
+                "from lib import a, b\nfrom pkg.util import Helper\n"
+            )
+        );
+        assert_eq!(
+            apply(
+                source,
+                &fixes("os", Rule::UnusedImport),
+                "Ignore 'unused-import' errors on this line"
+            ),
+            source.replace("import os\n", "import os  # type: ignore[unused-import]\n")
+        );
+    }
+
+    #[test]
+    fn import_fixes_from_modules_that_are_not_loaded() {
+        let source = "helper()\n";
+        let (manager, dir) = build_project(&[("main.py", source)]);
+        std::fs::write(dir.path().join("util.py"), "def helper():\n    pass\n").unwrap();
+        std::fs::write(dir.path().join("other.py"), "x = 1\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("helpers")).unwrap();
+        std::fs::write(dir.path().join("helpers/helper.py"), "").unwrap();
+        let path = dir.path().join("main.py");
+        assert!(!manager.contains_file(&dir.path().join("util.py")));
+
+        let fixes = manager.fixes(&error_at(&path, source, "helper", Rule::UndefinedName));
+        let titles = fixes
+            .iter()
+            .map(|fix| fix.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![
+                "Add 'from helpers import helper'",
+                "Add 'from util import helper'",
+                "Ignore 'undefined-name' errors on this line",
+            ]
+        );
+        assert_eq!(
+            apply(source, &fixes, "Add 'from util import helper'"),
+            "from util import helper\nhelper()\n"
+        );
+    }
+}
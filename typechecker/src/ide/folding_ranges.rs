@@ -0,0 +1,241 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{ConstantValue, Expression, GetNode, Statement};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldingRangeKind {
+    // The block of a compound statement, like the body of a function or an `else:`
+    Block,
+    // A bracketed expression, like a list or the arguments of a call
+    Brackets,
+    Docstring,
+}
+
+/// A byte range of the source that editors can collapse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldingRange {
+    pub start: usize,
+    pub end: usize,
+    pub kind: FoldingRangeKind,
+}
+
+impl BuildManager {
+    /// Returns the ranges of the module that span more than one line sorted by start
+    pub fn folding_ranges(&self, path: &Path) -> Vec<FoldingRange> {
+        let Some(state) = self.get_state(path) else {
+            return vec![];
+        };
+        let source = &state.file.source;
+        let mut collector = FoldingCollector {
+            source,
+            ranges: vec![],
+        };
+        collector.docstring(&state.file.body);
+        walk_body(&mut collector, &state.file.body);
+
+        let mut ranges = collector.ranges;
+        ranges.retain(|range| {
+            range.start < range.end && source[range.start..range.end].contains('\n')
+        });
+        ranges.sort_by_key(|range| (range.start, range.end));
+        ranges.dedup();
+        ranges
+    }
+}
+
+struct FoldingCollector<'a> {
+    source: &'a str,
+    ranges: Vec<FoldingRange>,
+}
+
+impl FoldingCollector<'_> {
+    fn push(&mut self, start: usize, end: usize, kind: FoldingRangeKind) {
+        // Compound statements end after the blank lines that follow them
+        let end = self.source[..end].trim_end().len();
+        self.ranges.push(FoldingRange { start, end, kind });
+    }
+
+    // Folds each block of a compound statement from its header to its last statement.
+    // The first block starts with the statement, the headers of the other blocks,
+    // like `else:` and `except E:`, end with the colon before their first statement.
+    fn blocks(&mut self, start: usize, blocks: &[&[Statement]]) {
+        let mut header_start = Some(start);
+        let mut previous_end = start;
+        for block in blocks {
+            let (Some(first), Some(last)) = (block.first(), block.last()) else {
+                continue;
+            };
+            let first_start = first.get_node().start;
+            let start = match header_start.take() {
+                Some(start) => start,
+                None => match self.source[previous_end..first_start].rfind(':') {
+                    Some(colon) => previous_end + colon,
+                    None => continue,
+                },
+            };
+            previous_end = last.get_node().end;
+            self.push(start, previous_end, FoldingRangeKind::Block);
+        }
+    }
+
+    // The string at the start of a module, class or function
+    fn docstring(&mut self, body: &[Statement]) {
+        if let Some(Statement::ExpressionStatement(Expression::Constant(c))) = body.first() {
+            if matches!(c.value, ConstantValue::Str(_)) {
+                self.push(c.node.start, c.node.end, FoldingRangeKind::Docstring);
+            }
+        }
+    }
+}
+
+impl<'a> AstWalker<'a> for FoldingCollector<'_> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        let start = node.node().start;
+        match node {
+            AstNode::Statement(statement) => match statement {
+                Statement::FunctionDef(f) => {
+                    self.blocks(start, &[&f.body]);
+                    self.docstring(&f.body);
+                }
+                Statement::AsyncFunctionDef(f) => {
+                    self.blocks(start, &[&f.body]);
+                    self.docstring(&f.body);
+                }
+                Statement::ClassDef(c) => {
+                    self.blocks(start, &[&c.body]);
+                    self.docstring(&c.body);
+                }
+                Statement::IfStatement(i) => {
+                    // An `elif` is the `if` statement in the `orelse`, it is folded when it is walked
+                    let is_elif = matches!(
+                        i.orelse.as_slice(),
+                        [Statement::IfStatement(elif)] if self.source[elif.node.start..].starts_with("elif")
+                    );
+                    if is_elif {
+                        self.blocks(start, &[&i.body]);
+                    } else {
+                        self.blocks(start, &[&i.body, &i.orelse]);
+                    }
+                }
+                Statement::WhileStatement(w) => self.blocks(start, &[&w.body, &w.orelse]),
+                Statement::ForStatement(f) => self.blocks(start, &[&f.body, &f.orelse]),
+                Statement::AsyncForStatement(f) => self.blocks(start, &[&f.body, &f.orelse]),
+                Statement::WithStatement(w) => self.blocks(start, &[&w.body]),
+                Statement::AsyncWithStatement(w) => self.blocks(start, &[&w.body]),
+                Statement::TryStatement(t) => {
+                    let mut blocks = vec![t.body.as_slice()];
+                    blocks.extend(t.handlers.iter().map(|handler| handler.body.as_slice()));
+                    blocks.extend([t.orelse.as_slice(), t.finalbody.as_slice()]);
+                    self.blocks(start, &blocks);
+                }
+                Statement::TryStarStatement(t) => {
+                    let mut blocks = vec![t.body.as_slice()];
+                    blocks.extend(t.handlers.iter().map(|handler| handler.body.as_slice()));
+                    blocks.extend([t.orelse.as_slice(), t.finalbody.as_slice()]);
+                    self.blocks(start, &blocks);
+                }
+                Statement::Match(m) => {
+                    if let Some(last) = m.cases.last().and_then(|case| case.body.last()) {
+                        self.push(start, last.get_node().end, FoldingRangeKind::Block);
+                    }
+                    // The cases are the blocks after the subject, all of them have headers
+                    let mut previous_end = m.subject.get_node().end;
+                    for case in &m.cases {
+                        let (Some(first), Some(last)) = (case.body.first(), case.body.last())
+                        else {
+                            continue;
+                        };
+                        let header = &self.source[previous_end..first.get_node().start];
+                        if let Some(colon) = header.rfind(':') {
+                            self.push(
+                                previous_end + colon,
+                                last.get_node().end,
+                                FoldingRangeKind::Block,
+                            );
+                        }
+                        previous_end = last.get_node().end;
+                    }
+                }
+                _ => {}
+            },
+            AstNode::Expression(
+                Expression::List(_)
+                | Expression::Tuple(_)
+                | Expression::Set(_)
+                | Expression::Dict(_)
+                | Expression::Call(_)
+                | Expression::ListComp(_)
+                | Expression::SetComp(_)
+                | Expression::DictComp(_)
+                | Expression::Generator(_),
+            ) => self.push(start, node.node().end, FoldingRangeKind::Brackets),
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FoldingRangeKind;
+    use crate::ide::tests::build_project;
+
+    #[test]
+    fn folding_ranges() {
+        let source = r#""""Module.
+
+Docs."""
+
+def f(a,
+      b):
+    """Doc."""
+    if a:
+        x = [
+            1,
+        ]
+    elif b:
+        pass
+    else:
+        pass
+
+try:
+    pass
+except ValueError:
+    pass
+
+match a:
+    case 1:
+        pass
+    case _:
+        pass
+"#;
+        let (manager, dir) = build_project(&[("main.py", source)]);
+        let path = dir.path().join("main.py");
+        let line = |offset: usize| source[..offset].matches('\n').count();
+        let ranges = manager
+            .folding_ranges(&path)
+            .into_iter()
+            .map(|range| (line(range.start), line(range.end), range.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ranges,
+            vec![
+                (0, 2, FoldingRangeKind::Docstring),
+                (4, 14, FoldingRangeKind::Block),
+                (7, 10, FoldingRangeKind::Block),
+                (8, 10, FoldingRangeKind::Brackets),
+                (11, 12, FoldingRangeKind::Block),
+                (13, 14, FoldingRangeKind::Block),
+                (16, 17, FoldingRangeKind::Block),
+                (18, 19, FoldingRangeKind::Block),
+                (21, 25, FoldingRangeKind::Block),
+                (22, 23, FoldingRangeKind::Block),
+                (24, 25, FoldingRangeKind::Block),
+            ]
+        );
+    }
+}
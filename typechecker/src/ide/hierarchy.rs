@@ -0,0 +1,480 @@
+use std::path::PathBuf;
+
+use enderpy_python_parser::ast::{self, ClassDef, Node, Statement};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+use crate::state::State;
+use crate::symbol_table::Declaration;
+
+use super::namespace::dotted_name;
+use super::symbols::SymbolKind;
+use super::{
+    attribute_name_range, declaration_name_range, declaration_with_node, find_class_def,
+    ImportTarget,
+};
+
+/// A function, class or module of the call hierarchy or a class of the type hierarchy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyItem {
+    pub name: String,
+    pub kind: SymbolKind,
+    // The module that declares the item, like `pkg.mod`
+    pub detail: Option<String>,
+    pub path: PathBuf,
+    // Byte range of the whole definition
+    pub start: usize,
+    pub end: usize,
+    // Byte range of the name
+    pub name_start: usize,
+    pub name_end: usize,
+}
+
+/// The calls between the item and another function.
+/// The ranges are the callee names of the calls, they are in the module of the caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyCall {
+    pub item: HierarchyItem,
+    pub ranges: Vec<Node>,
+}
+
+impl BuildManager {
+    /// Returns the function or class at the byte offset, imports are followed to the definition
+    pub fn prepare_call_hierarchy(
+        &self,
+        path: &std::path::Path,
+        offset: usize,
+    ) -> Option<HierarchyItem> {
+        let (name, declaration) = self.hierarchy_target(path, offset)?;
+        self.hierarchy_item(&name, &declaration)
+    }
+
+    /// Returns the functions of the project that call the item, with the calls in each of them.
+    /// Calls outside of functions and classes are made by their module.
+    pub fn incoming_calls(&self, item: &HierarchyItem) -> Vec<HierarchyCall> {
+        let Some((_, target)) = self.hierarchy_target(&item.path, item.name_start) else {
+            return vec![];
+        };
+        let mut incoming: Vec<HierarchyCall> = vec![];
+        for state in self.project_states() {
+            let mut collector = CallCollector {
+                definitions: vec![],
+                calls: vec![],
+            };
+            walk_body(&mut collector, &state.file.body);
+            for (caller, call) in collector.calls {
+                let calls_target = self
+                    .callee(state, &call.func)
+                    .is_some_and(|(_, callee)| same_declaration(&callee, &target));
+                let (Some(range), true) = (callee_name_range(&call.func), calls_target) else {
+                    continue;
+                };
+                let Some(item) = self.caller_item(state, caller) else {
+                    continue;
+                };
+                match incoming.iter_mut().find(|incoming| incoming.item == item) {
+                    Some(incoming) => incoming.ranges.push(range),
+                    None => incoming.push(HierarchyCall {
+                        item,
+                        ranges: vec![range],
+                    }),
+                }
+            }
+        }
+        incoming.sort_by(|a, b| (&a.item.path, a.item.start).cmp(&(&b.item.path, b.item.start)));
+        incoming
+    }
+
+    /// Returns the functions and classes that the item calls, with the calls of each of them.
+    /// The calls in the functions and classes nested in the item are left out.
+    pub fn outgoing_calls(&self, item: &HierarchyItem) -> Vec<HierarchyCall> {
+        let Some(state) = self.get_state(&item.path) else {
+            return vec![];
+        };
+        let target = match item.kind {
+            SymbolKind::Module => None,
+            _ => match self.hierarchy_target(&item.path, item.name_start) {
+                Some(target) => Some(target),
+                None => return vec![],
+            },
+        };
+        let body = match &target {
+            None => state.file.body.as_slice(),
+            Some((_, Declaration::Function(f))) => f.function_node.body.as_slice(),
+            Some((_, Declaration::Class(c))) => {
+                match find_class_def(&state.file.body, c.declaration_path.node) {
+                    Some(class) => class.body.as_slice(),
+                    None => return vec![],
+                }
+            }
+            Some(_) => return vec![],
+        };
+        let mut collector = CallCollector {
+            definitions: vec![],
+            calls: vec![],
+        };
+        walk_body(&mut collector, body);
+
+        let mut outgoing: Vec<HierarchyCall> = vec![];
+        for (caller, call) in collector.calls {
+            if caller.is_some() {
+                continue;
+            }
+            let Some(range) = callee_name_range(&call.func) else {
+                continue;
+            };
+            let Some(item) = self
+                .callee(state, &call.func)
+                .and_then(|(name, callee)| self.hierarchy_item(&name, &callee))
+            else {
+                continue;
+            };
+            match outgoing.iter_mut().find(|outgoing| outgoing.item == item) {
+                Some(outgoing) => outgoing.ranges.push(range),
+                None => outgoing.push(HierarchyCall {
+                    item,
+                    ranges: vec![range],
+                }),
+            }
+        }
+        outgoing
+    }
+
+    /// Returns the class at the byte offset, imports are followed to the definition
+    pub fn prepare_type_hierarchy(
+        &self,
+        path: &std::path::Path,
+        offset: usize,
+    ) -> Option<HierarchyItem> {
+        match self.hierarchy_target(path, offset)? {
+            (name, declaration @ Declaration::Class(_)) => self.hierarchy_item(&name, &declaration),
+            _ => None,
+        }
+    }
+
+    /// Returns the base classes of the class item that can be resolved, in the order of the bases
+    pub fn supertypes(&self, item: &HierarchyItem) -> Vec<HierarchyItem> {
+        let Some((_, Declaration::Class(class))) =
+            self.hierarchy_target(&item.path, item.name_start)
+        else {
+            return vec![];
+        };
+        let Some(state) = self.get_state_by_module_name(&class.declaration_path.module_name) else {
+            return vec![];
+        };
+        let Some(class_def) = find_class_def(&state.file.body, class.declaration_path.node) else {
+            return vec![];
+        };
+        self.class_bases(state, class_def)
+            .into_iter()
+            .filter_map(|(name, base)| self.hierarchy_item(&name, &base))
+            .collect()
+    }
+
+    /// Returns the classes of the project that have the class item as a direct base
+    pub fn subtypes(&self, item: &HierarchyItem) -> Vec<HierarchyItem> {
+        let Some((_, target @ Declaration::Class(_))) =
+            self.hierarchy_target(&item.path, item.name_start)
+        else {
+            return vec![];
+        };
+        let mut subtypes = vec![];
+        for state in self.project_states() {
+            let mut collector = ClassCollector { classes: vec![] };
+            walk_body(&mut collector, &state.file.body);
+            for class_def in collector.classes {
+                let is_subtype = self
+                    .class_bases(state, class_def)
+                    .iter()
+                    .any(|(_, base)| same_declaration(base, &target));
+                if !is_subtype {
+                    continue;
+                }
+                let declaration =
+                    declaration_with_node(state.symbol_table(), &class_def.name, class_def.node);
+                if let Some(item) = declaration
+                    .and_then(|declaration| self.hierarchy_item(&class_def.name, declaration))
+                {
+                    subtypes.push(item);
+                }
+            }
+        }
+        subtypes.sort_by(|a, b| (&a.path, a.start).cmp(&(&b.path, b.start)));
+        subtypes
+    }
+
+    // The project modules sorted by path, the calls and subclasses are searched in them
+    fn project_states(&self) -> Vec<&State> {
+        let mut states = self
+            .modules
+            .values()
+            .filter(|state| self.is_project_module(&state.file.path))
+            .collect::<Vec<_>>();
+        states.sort_by(|a, b| a.file.path.cmp(&b.file.path));
+        states
+    }
+
+    // The function or class that the identifier at the offset refers to
+    fn hierarchy_target(
+        &self,
+        path: &std::path::Path,
+        offset: usize,
+    ) -> Option<(String, Declaration)> {
+        match self.symbol_at_offset(path, offset)? {
+            ImportTarget::Declaration(name, declaration @ Declaration::Function(_))
+            | ImportTarget::Declaration(name, declaration @ Declaration::Class(_)) => {
+                Some((name, declaration))
+            }
+            _ => None,
+        }
+    }
+
+    // The function or class that a call runs, the imports are followed
+    fn callee(&self, state: &State, func: &ast::Expression) -> Option<(String, Declaration)> {
+        let target = match func {
+            ast::Expression::Name(n) => {
+                let declaration = self.lookup_declaration(state, &n.id, n.node.start)?;
+                self.follow_imports(&n.id, declaration)?
+            }
+            ast::Expression::Attribute(a) => {
+                let namespace = self.receiver_namespace(state, &a.value, a.node.start)?;
+                self.member(&namespace, &a.attr)?
+            }
+            _ => return None,
+        };
+        match target {
+            ImportTarget::Declaration(name, declaration @ Declaration::Function(_))
+            | ImportTarget::Declaration(name, declaration @ Declaration::Class(_)) => {
+                Some((name, declaration))
+            }
+            _ => None,
+        }
+    }
+
+    // The base classes that can be resolved, the arguments of generic bases like `Generic[T]` are left out
+    fn class_bases(&self, state: &State, class_def: &ClassDef) -> Vec<(String, Declaration)> {
+        class_def
+            .bases
+            .iter()
+            .filter_map(|base| {
+                let base = match base {
+                    ast::Expression::Subscript(s) => &s.value,
+                    base => base,
+                };
+                let parts = dotted_name(base)?;
+                match self.resolve_dotted_name(state, &parts, class_def.node.start)? {
+                    ImportTarget::Declaration(name, declaration @ Declaration::Class(_)) => {
+                        Some((name, declaration))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    // The item of the definition that contains a call, or of the module for the calls outside of them
+    fn caller_item(&self, state: &State, caller: Option<&Statement>) -> Option<HierarchyItem> {
+        let (name, node) = match caller {
+            Some(Statement::FunctionDef(f)) => (&f.name, f.node),
+            Some(Statement::AsyncFunctionDef(f)) => (&f.name, f.node),
+            Some(Statement::ClassDef(c)) => (&c.name, c.node),
+            _ => return Some(self.module_item(state)),
+        };
+        let declaration = declaration_with_node(state.symbol_table(), name, node)?;
+        self.hierarchy_item(name, declaration)
+    }
+
+    fn module_item(&self, state: &State) -> HierarchyItem {
+        let path = &state.file.path;
+        let name = self.import_name(path).unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        HierarchyItem {
+            name,
+            kind: SymbolKind::Module,
+            detail: None,
+            path: path.clone(),
+            start: 0,
+            end: state.file.source.trim_end().len(),
+            name_start: 0,
+            name_end: 0,
+        }
+    }
+
+    fn hierarchy_item(&self, name: &str, declaration: &Declaration) -> Option<HierarchyItem> {
+        let kind = match declaration {
+            Declaration::Class(_) => SymbolKind::Class,
+            Declaration::Function(f) if f.is_method => SymbolKind::Method,
+            Declaration::Function(_) => SymbolKind::Function,
+            _ => return None,
+        };
+        let state = self.get_state_by_module_name(&declaration.declaration_path().module_name)?;
+        let node = declaration.declaration_path().node;
+        let name_range = declaration_name_range(state, name, declaration);
+        // The blank lines after the definition are not included
+        let end = state.file.source[..node.end].trim_end().len();
+        Some(HierarchyItem {
+            name: name.to_string(),
+            kind,
+            detail: self.import_name(&state.file.path),
+            path: state.file.path.clone(),
+            start: node.start.min(name_range.start),
+            end: end.max(name_range.end),
+            name_start: name_range.start,
+            name_end: name_range.end,
+        })
+    }
+}
+
+fn same_declaration(a: &Declaration, b: &Declaration) -> bool {
+    a.declaration_path().module_name == b.declaration_path().module_name
+        && a.declaration_path().node == b.declaration_path().node
+}
+
+// The range of the name that is called, like `method` in `a.method()`
+fn callee_name_range(func: &ast::Expression) -> Option<Node> {
+    match func {
+        ast::Expression::Name(n) => Some(n.node),
+        ast::Expression::Attribute(a) => Some(attribute_name_range(a)),
+        _ => None,
+    }
+}
+
+// Collects the calls with the innermost function or class that contains them
+struct CallCollector<'a> {
+    definitions: Vec<&'a Statement>,
+    calls: Vec<(Option<&'a Statement>, &'a ast::Call)>,
+}
+
+impl<'a> AstWalker<'a> for CallCollector<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        match node {
+            AstNode::Statement(
+                statement @ (Statement::FunctionDef(_)
+                | Statement::AsyncFunctionDef(_)
+                | Statement::ClassDef(_)),
+            ) => self.definitions.push(statement),
+            AstNode::Expression(ast::Expression::Call(call)) => {
+                self.calls.push((self.definitions.last().copied(), call));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn leave(&mut self, node: AstNode<'a>) {
+        if let AstNode::Statement(
+            Statement::FunctionDef(_) | Statement::AsyncFunctionDef(_) | Statement::ClassDef(_),
+        ) = node
+        {
+            self.definitions.pop();
+        }
+    }
+}
+
+struct ClassCollector<'a> {
+    classes: Vec<&'a ClassDef>,
+}
+
+impl<'a> AstWalker<'a> for ClassCollector<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        if let AstNode::Statement(Statement::ClassDef(c)) = node {
+            self.classes.push(c);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::tests::build_project;
+
+    use super::HierarchyItem;
+
+    #[test]
+    fn call_and_type_hierarchy() {
+        let lib = r#"class Base:
+    def run(self):
+        return helper()
+
+def helper():
+    pass
+"#;
+        let main = r#"from lib import Base, helper
+
+class Child(Base):
+    def run(self):
+        helper()
+        return self.stop()
+
+    def stop(self):
+        pass
+
+class GrandChild(Child):
+    pass
+
+helper()
+Child().run()
+"#;
+        let (manager, dir) = build_project(&[("lib.py", lib), ("main.py", main)]);
+        let lib_path = dir.path().join("lib.py");
+        let main_path = dir.path().join("main.py");
+        let names =
+            |items: Vec<HierarchyItem>| items.into_iter().map(|item| item.name).collect::<Vec<_>>();
+
+        // Imports are followed to the definition
+        let helper = manager
+            .prepare_call_hierarchy(&main_path, main.find("helper()").unwrap())
+            .unwrap();
+        assert_eq!(helper.path, lib_path);
+        assert_eq!(&lib[helper.name_start..helper.name_end], "helper");
+        let incoming = manager
+            .incoming_calls(&helper)
+            .into_iter()
+            .map(|call| {
+                let source = std::fs::read_to_string(&call.item.path).unwrap();
+                let lines = call
+                    .ranges
+                    .iter()
+                    .map(|range| source[..range.start].matches('\n').count() + 1)
+                    .collect::<Vec<_>>();
+                (call.item.name, lines)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            incoming,
+            vec![
+                ("run".to_string(), vec![3]),
+                ("main".to_string(), vec![14]),
+                ("run".to_string(), vec![5]),
+            ]
+        );
+
+        let run = manager
+            .prepare_call_hierarchy(&main_path, main.find("run(self)").unwrap())
+            .unwrap();
+        let outgoing = manager.outgoing_calls(&run);
+        assert_eq!(
+            outgoing
+                .iter()
+                .map(|call| call.item.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["helper", "stop"]
+        );
+
+        let child = manager
+            .prepare_type_hierarchy(&main_path, main.find("Child(").unwrap())
+            .unwrap();
+        assert_eq!(names(manager.supertypes(&child)), vec!["Base"]);
+        assert_eq!(names(manager.subtypes(&child)), vec!["GrandChild"]);
+        let base = manager
+            .prepare_type_hierarchy(&lib_path, lib.find("Base").unwrap())
+            .unwrap();
+        assert_eq!(names(manager.subtypes(&base)), vec!["Child"]);
+        assert!(manager
+            .prepare_type_hierarchy(&main_path, main.find("helper()").unwrap())
+            .is_none());
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+
+use crate::build::BuildManager;
+use crate::symbol_table::Declaration;
+use crate::type_check::type_evaluator::TypeEvaluator;
+
+use super::{
+    docstring, find_class_def, format_class_signature, format_function_signature, format_parameter,
+    identifier_at_offset, ImportTarget,
+};
+
+/// Information about the identifier under the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    // Markdown that describes the declaration of the identifier
+    pub contents: String,
+    // Byte offsets of the identifier in the source
+    pub start: usize,
+    pub end: usize,
+}
+
+impl BuildManager {
+    /// Returns the type, kind and documentation of the identifier at the byte offset.
+    /// The modules must be built before calling this.
+    pub fn hover(&self, path: &Path, offset: usize) -> Option<Hover> {
+        let state = self.get_state(path)?;
+        let identifier = identifier_at_offset(&state.file.body, &state.file.source, offset)?;
+        let declaration = self.resolve_identifier(state, &identifier)?;
+        let (signature, docs) = match self.follow_imports(identifier.name(), declaration.clone()) {
+            Some(ImportTarget::Declaration(name, declaration)) => {
+                self.describe_declaration(&name, &declaration)?
+            }
+            Some(ImportTarget::Module(_)) => (format!("(module) {}", identifier.name()), None),
+            // Imports that cannot be resolved
+            None => self.describe_declaration(identifier.name(), &declaration)?,
+        };
+
+        let mut contents = format!("```python\n{}\n```", signature);
+        if let Some(docs) = docs {
+            contents.push_str("\n---\n");
+            contents.push_str(&docs);
+        }
+        Some(Hover {
+            contents,
+            start: identifier.range.start,
+            end: identifier.range.end,
+        })
+    }
+
+    // Returns the signature and the docstring of the declaration
+    fn describe_declaration(
+        &self,
+        name: &str,
+        declaration: &Declaration,
+    ) -> Option<(String, Option<String>)> {
+        let state = self.get_state_by_module_name(&declaration.declaration_path().module_name)?;
+        let source = &state.file.source;
+        let description = match declaration {
+            Declaration::Variable(_) => {
+                let evaluator = TypeEvaluator::new(state.get_symbol_table());
+                let python_type = evaluator
+                    .get_type_from_declaration(declaration)
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|_| "Unknown".to_string());
+                (format!("(variable) {}: {}", name, python_type), None)
+            }
+            Declaration::Parameter(p) => (
+                format!(
+                    "(parameter) {}",
+                    format_parameter(&p.parameter_node, p.default_value.as_ref(), source)
+                ),
+                None,
+            ),
+            Declaration::Function(f) => {
+                let kind = if f.is_method { "method" } else { "function" };
+                (
+                    format!(
+                        "({}) {}",
+                        kind,
+                        format_function_signature(&f.function_node, source)
+                    ),
+                    docstring(&f.function_node.body),
+                )
+            }
+            Declaration::Alias(_) => (format!("(import) {}", name), None),
+            Declaration::Class(c) => {
+                let class = find_class_def(&state.file.body, c.declaration_path.node)?;
+                (
+                    format!("(class) {}", format_class_signature(class, source)),
+                    docstring(&class.body),
+                )
+            }
+        };
+        Some(description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::build::{BuildManager, BuildSource, PythonHost};
+    use crate::settings::Settings;
+
+    fn hover_at(source: &str, text: &str) -> Option<String> {
+        let path = PathBuf::from("test.py");
+        let mut manager = BuildManager::with_host(
+            vec![BuildSource {
+                path: path.clone(),
+                module: "test".to_string(),
+                source: source.to_string(),
+                followed: false,
+            }],
+            Settings::test_settings(),
+            PythonHost::default(),
+        );
+        manager.build();
+        let offset = source.find(text).unwrap();
+        manager.hover(&path, offset).map(|hover| {
+            assert_eq!(hover.start, offset);
+            hover.contents
+        })
+    }
+
+    #[test]
+    fn hover_variable() {
+        let source = "x = 1\nx = x + 1\nprint(x)\ny: str = 'a'\nimport os as system\n";
+        assert_eq!(
+            hover_at(source, "x = 1").unwrap(),
+            "```python\n(variable) x: Int\n```"
+        );
+        assert_eq!(
+            hover_at(source, "x)").unwrap(),
+            "```python\n(variable) x: Int\n```"
+        );
+        assert_eq!(
+            hover_at(source, "y:").unwrap(),
+            "```python\n(variable) y: Str\n```"
+        );
+        assert_eq!(
+            hover_at(source, "system").unwrap(),
+            "```python\n(import) system\n```"
+        );
+        assert!(hover_at(source, "print").is_none());
+    }
+
+    #[test]
+    fn hover_function_and_parameters() {
+        let source = r#"def add(a: int, b=1) -> int:
+    """Adds two numbers."""
+    return a + b
+
+add(1)
+"#;
+        let function =
+            "```python\n(function) def add(a: int, b=1) -> int\n```\n---\nAdds two numbers.";
+        assert_eq!(hover_at(source, "add(a").unwrap(), function);
+        assert_eq!(hover_at(source, "add(1)").unwrap(), function);
+        assert_eq!(
+            hover_at(source, "a + b").unwrap(),
+            "```python\n(parameter) a: int\n```"
+        );
+        assert_eq!(
+            hover_at(source, "b=1").unwrap(),
+            "```python\n(parameter) b=1\n```"
+        );
+    }
+
+    #[test]
+    fn hover_class_and_methods() {
+        let source = r#"class A(object):
+    """A class."""
+
+    def method(self):
+        pass
+
+A.method
+"#;
+        assert_eq!(
+            hover_at(source, "A.").unwrap(),
+            "```python\n(class) class A(object)\n```\n---\nA class."
+        );
+        assert_eq!(
+            hover_at(source, "method\n").unwrap(),
+            "```python\n(method) def method(self)\n```"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{self, GetNode, Node, Statement};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+use crate::state::State;
+use crate::symbol_table::Declaration;
+use crate::type_check::builtins;
+use crate::type_check::type_evaluator::TypeEvaluator;
+use crate::type_check::types::PythonType;
+
+use super::namespace::dotted_name;
+use super::{declaration_with_node, def_name_range};
+
+/// The kinds of hints that are shown, parameter names are off by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InlayHintOptions {
+    // `: int` after the name of an assignment without annotation
+    pub variable_types: bool,
+    // `-> str` after the parameters of a function without return annotation
+    pub return_types: bool,
+    // `name=` before the positional arguments of calls
+    pub parameter_names: bool,
+}
+
+impl Default for InlayHintOptions {
+    fn default() -> Self {
+        Self {
+            variable_types: true,
+            return_types: true,
+            parameter_names: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlayHintKind {
+    Type,
+    Parameter,
+}
+
+/// A label that is shown at a byte offset of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlayHint {
+    pub offset: usize,
+    pub label: String,
+    pub kind: InlayHintKind,
+    // The text that turns the hint into code at the offset, like the annotation of a type hint
+    pub insert_text: Option<String>,
+}
+
+impl BuildManager {
+    /// Returns the hints of the byte range of the module sorted by offset.
+    /// Types that cannot be inferred or cannot be written as annotations are not shown.
+    pub fn inlay_hints(
+        &self,
+        path: &Path,
+        range: Node,
+        options: InlayHintOptions,
+    ) -> Vec<InlayHint> {
+        let Some(state) = self.get_state(path) else {
+            return vec![];
+        };
+        let mut collector = HintCollector {
+            range,
+            assignments: vec![],
+            functions: vec![],
+            calls: vec![],
+        };
+        walk_body(&mut collector, &state.file.body);
+        let evaluator = TypeEvaluator::new(state.get_symbol_table());
+
+        let mut hints = vec![];
+        if options.variable_types {
+            for assignment in collector.assignments {
+                hints.extend(variable_type_hint(state, &evaluator, assignment));
+            }
+        }
+        if options.return_types {
+            for function in collector.functions {
+                hints.extend(return_type_hint(state, &evaluator, function));
+            }
+        }
+        if options.parameter_names {
+            for call in collector.calls {
+                hints.extend(self.parameter_name_hints(state, call));
+            }
+        }
+        hints.retain(|hint| range.start <= hint.offset && hint.offset <= range.end);
+        hints.sort_by_key(|hint| hint.offset);
+        hints
+    }
+
+    // Hints the names of the parameters that the positional arguments of the call are passed to
+    fn parameter_name_hints(&self, state: &State, call: &ast::Call) -> Vec<InlayHint> {
+        let Some(callee) = dotted_name(&call.func) else {
+            return vec![];
+        };
+        let Some((function, bound)) = self.called_function(state, &callee, call.node.start) else {
+            return vec![];
+        };
+        let args = &function.function_node.args;
+        let parameters = args
+            .posonlyargs
+            .iter()
+            .chain(args.args.iter())
+            .skip(usize::from(bound));
+
+        let mut hints = vec![];
+        for (argument, parameter) in call.args.iter().zip(parameters) {
+            // The parameters after unpacked arguments are not known
+            if matches!(argument, ast::Expression::Starred(_)) {
+                break;
+            }
+            if matches!(argument, ast::Expression::Name(n) if n.id == parameter.arg) {
+                continue;
+            }
+            hints.push(InlayHint {
+                offset: argument.get_node().start,
+                label: format!("{}=", parameter.arg),
+                kind: InlayHintKind::Parameter,
+                insert_text: None,
+            });
+        }
+        hints
+    }
+}
+
+// The type of a name that is assigned without annotation, only at the first assignment of the name
+fn variable_type_hint(
+    state: &State,
+    evaluator: &TypeEvaluator,
+    assignment: &ast::Assign,
+) -> Option<InlayHint> {
+    let [ast::Expression::Name(target)] = assignment.targets.as_slice() else {
+        return None;
+    };
+    let symbol = state
+        .symbol_table()
+        .lookup_at_position(&target.id, assignment.node.start)?;
+    let mut variables = symbol.declarations.iter().filter_map(|decl| match decl {
+        Declaration::Variable(v) => Some(v),
+        _ => None,
+    });
+    let first = variables
+        .clone()
+        .min_by_key(|v| v.declaration_path.node.start)?;
+    // Annotated names are not hinted in their other assignments either
+    if first.declaration_path.node != assignment.node
+        || variables.any(|v| v.type_annotation.is_some())
+    {
+        return None;
+    }
+    let annotation = annotation(&evaluator.get_type(&assignment.value).ok()?)?;
+    Some(InlayHint {
+        offset: target.node.end,
+        label: format!(": {}", annotation),
+        kind: InlayHintKind::Type,
+        insert_text: Some(format!(": {}", annotation)),
+    })
+}
+
+fn return_type_hint(
+    state: &State,
+    evaluator: &TypeEvaluator,
+    function: &ast::FunctionDef,
+) -> Option<InlayHint> {
+    if function.returns.is_some() {
+        return None;
+    }
+    // Functions that the semantic analyzer skipped, like the ones in invalid code, are not hinted
+    declaration_with_node(state.symbol_table(), &function.name, function.node)?;
+    let annotation = annotation(&evaluator.infer_return_type(function))?;
+    Some(InlayHint {
+        offset: parameters_end(&state.file.source, function)?,
+        label: format!("-> {}", annotation),
+        kind: InlayHintKind::Type,
+        insert_text: Some(format!(" -> {}", annotation)),
+    })
+}
+
+// Returns the offset after the closing parenthesis of the parameters of the function
+fn parameters_end(source: &str, function: &ast::FunctionDef) -> Option<usize> {
+    let name = def_name_range(source, function.node, "def", &function.name)?;
+    let text = source.get(name.end..function.node.end)?;
+    let open = text.find('(')?;
+    let mut depth = 0;
+    let mut quote = None;
+    for (index, c) in text[open..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(name.end + open + index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Returns the annotation that spells the type, types of other modules are not spelled
+// because they may not be imported where the hint is inserted
+fn annotation(python_type: &PythonType) -> Option<String> {
+    let annotation = match python_type {
+        PythonType::None => "None".to_string(),
+        PythonType::Bool => "bool".to_string(),
+        PythonType::Int => "int".to_string(),
+        PythonType::Float => "float".to_string(),
+        PythonType::Str => "str".to_string(),
+        PythonType::Unknown | PythonType::Callable(_) => return None,
+        PythonType::Class(class) => {
+            let name = class.name.strip_prefix("builtins.")?;
+            // Unknown arguments are left out, like `list` for `[]`
+            let Some(args) = class
+                .args
+                .iter()
+                .map(annotation)
+                .collect::<Option<Vec<_>>>()
+                .filter(|args| !args.is_empty())
+            else {
+                return Some(name.to_string());
+            };
+            // The evaluator gives tuples a single type for all their elements
+            if class.name == builtins::TUPLE_TYPE {
+                format!("{}[{}, ...]", name, args.join(", "))
+            } else {
+                format!("{}[{}]", name, args.join(", "))
+            }
+        }
+    };
+    Some(annotation)
+}
+
+// Collects the nodes that can have hints and overlap the range
+struct HintCollector<'a> {
+    range: Node,
+    assignments: Vec<&'a ast::Assign>,
+    functions: Vec<&'a ast::FunctionDef>,
+    calls: Vec<&'a ast::Call>,
+}
+
+impl<'a> AstWalker<'a> for HintCollector<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        let range = node.node();
+        if range.end < self.range.start || self.range.end < range.start {
+            return false;
+        }
+        match node {
+            AstNode::Statement(Statement::AssignStatement(a)) => self.assignments.push(a),
+            AstNode::Statement(Statement::FunctionDef(f)) => self.functions.push(f),
+            AstNode::Expression(ast::Expression::Call(c)) => self.calls.push(c),
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enderpy_python_parser::ast::Node;
+
+    use super::InlayHintOptions;
+    use crate::ide::tests::build_project;
+
+    #[test]
+    fn inlay_hints() {
+        let source = r#"numbers = [1, 2]
+numbers = []
+names: list[str] = []
+pair = (1, 2)
+
+def f(a, b=")"):
+    if a:
+        return "a"
+    return b
+
+def g(a):
+    if a:
+        return 1
+
+def h():
+    yield 1
+
+class A:
+    def method(self, x, y):
+        return None
+
+a = A()
+f(1, b=2)
+a.method(1, y)
+"#;
+        let (manager, dir) = build_project(&[("main.py", source)]);
+        let path = dir.path().join("main.py");
+        let hints = |options| {
+            manager
+                .inlay_hints(&path, Node::new(0, source.len()), options)
+                .into_iter()
+                .map(|hint| {
+                    let line = source[..hint.offset].lines().last().unwrap_or_default();
+                    format!("{}|{}", line, hint.label)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            hints(InlayHintOptions::default()),
+            vec![
+                "numbers|: list[int]",
+                "pair|: tuple[int, ...]",
+                "    def method(self, x, y)|-> None",
+            ]
+        );
+        let options = InlayHintOptions {
+            variable_types: false,
+            return_types: false,
+            parameter_names: true,
+        };
+        assert_eq!(hints(options), vec!["f(|a=", "a.method(|x="]);
+
+        let edits = manager.inlay_hints(&path, Node::new(0, 8), InlayHintOptions::default());
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].insert_text.as_deref(), Some(": list[int]"));
+    }
+}
@@ -0,0 +1,734 @@
+// Language features used by the language server, like hover.
+// Positions are byte offsets in the source of a module, the language server
+// converts them to lines and columns with the line index of the module.
+
+pub mod completion;
+pub mod definition;
+pub mod fixes;
+pub mod folding_ranges;
+pub mod hierarchy;
+pub mod hover;
+pub mod inlay_hints;
+pub(crate) mod namespace;
+pub mod references;
+pub mod rename;
+pub mod selection_ranges;
+pub mod semantic_tokens;
+pub mod signature_help;
+pub mod symbols;
+
+use std::path::PathBuf;
+
+use enderpy_python_parser::ast::{
+    self, Alias, Arg, Arguments, Attribute, ClassDef, FunctionDef, GetNode, Name, Node, Statement,
+};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+use crate::state::State;
+use crate::symbol_table::{Declaration, SymbolTable};
+
+/// The identifier under the cursor and the node that it belongs to
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum NodeAtOffset<'a> {
+    // `is_store` is true when the name is assigned to, e.g. x in `x = 1`
+    Name { name: &'a Name, is_store: bool },
+    // The cursor is on the attribute name, not on the value
+    Attribute(&'a Attribute),
+    FunctionName(&'a FunctionDef),
+    ClassName(&'a ClassDef),
+    Arg(&'a Arg),
+    Alias(&'a Alias),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IdentifierAtOffset<'a> {
+    pub node: NodeAtOffset<'a>,
+    // Range of the identifier in the source
+    pub range: Node,
+}
+
+impl<'a> IdentifierAtOffset<'a> {
+    /// The name that the identifier binds or refers to
+    pub fn name(&self) -> &'a str {
+        match self.node {
+            NodeAtOffset::Name { name, .. } => &name.id,
+            NodeAtOffset::Attribute(a) => &a.attr,
+            NodeAtOffset::FunctionName(f) => &f.name,
+            NodeAtOffset::ClassName(c) => &c.name,
+            NodeAtOffset::Arg(a) => &a.arg,
+            NodeAtOffset::Alias(a) => alias_bound_name(a),
+        }
+    }
+}
+
+/// Finds the innermost identifier that contains the offset
+pub(crate) fn identifier_at_offset<'a>(
+    body: &'a [Statement],
+    source: &'a str,
+    offset: usize,
+) -> Option<IdentifierAtOffset<'a>> {
+    let mut finder = IdentifierFinder {
+        source,
+        offset,
+        store_targets: vec![],
+        found: None,
+    };
+    walk_body(&mut finder, body);
+    finder.found
+}
+
+struct IdentifierFinder<'a> {
+    source: &'a str,
+    offset: usize,
+    // Targets of the assignments that contain the offset
+    store_targets: Vec<Node>,
+    found: Option<IdentifierAtOffset<'a>>,
+}
+
+impl<'a> IdentifierFinder<'a> {
+    fn contains(&self, range: Node) -> bool {
+        range.start <= self.offset && self.offset <= range.end
+    }
+
+    fn found(&mut self, node: NodeAtOffset<'a>, range: Node) {
+        self.found = Some(IdentifierAtOffset { node, range });
+    }
+}
+
+impl<'a> AstWalker<'a> for IdentifierFinder<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        // The decorators are before the node of the definition
+        let decorators = match node {
+            AstNode::Statement(Statement::FunctionDef(f)) => f.decorator_list.first(),
+            AstNode::Statement(Statement::ClassDef(c)) => c.decorator_list.first(),
+            _ => None,
+        };
+        let mut range = node.node();
+        if let Some(decorator) = decorators {
+            range.start = range.start.min(decorator.get_node().start);
+        }
+        if !self.contains(range) {
+            return false;
+        }
+        match node {
+            AstNode::Statement(Statement::AssignStatement(a)) => {
+                self.store_targets
+                    .extend(a.targets.iter().map(|target| target.get_node()));
+            }
+            AstNode::Statement(Statement::AnnAssignStatement(a)) => {
+                self.store_targets.push(a.target.get_node());
+            }
+            AstNode::Statement(Statement::FunctionDef(f)) => {
+                if let Some(range) = def_name_range(self.source, f.node, "def", &f.name) {
+                    if self.contains(range) {
+                        self.found(NodeAtOffset::FunctionName(f), range);
+                        return false;
+                    }
+                }
+            }
+            AstNode::Statement(Statement::ClassDef(c)) => {
+                if let Some(range) = def_name_range(self.source, c.node, "class", &c.name) {
+                    if self.contains(range) {
+                        self.found(NodeAtOffset::ClassName(c), range);
+                        return false;
+                    }
+                }
+            }
+            AstNode::Expression(ast::Expression::Name(n)) => {
+                let is_store = self
+                    .store_targets
+                    .iter()
+                    .any(|target| target.start <= n.node.start && n.node.end <= target.end);
+                self.found(NodeAtOffset::Name { name: n, is_store }, n.node);
+            }
+            AstNode::Expression(ast::Expression::Attribute(a)) => {
+                let range = attribute_name_range(a);
+                if self.contains(range) && !a.attr.is_empty() {
+                    self.found(NodeAtOffset::Attribute(a), range);
+                    return false;
+                }
+            }
+            AstNode::Arg(a) => {
+                let range = arg_name_range(self.source, a);
+                if self.contains(range) {
+                    self.found(NodeAtOffset::Arg(a), range);
+                }
+            }
+            AstNode::Alias(a) => {
+                self.found(NodeAtOffset::Alias(a), alias_name_range(a));
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+impl BuildManager {
+    /// Returns the declaration that the identifier refers to
+    pub(crate) fn resolve_identifier(
+        &self,
+        state: &State,
+        identifier: &IdentifierAtOffset,
+    ) -> Option<Declaration> {
+        let symbol_table = state.symbol_table();
+        let declaration = match identifier.node {
+            NodeAtOffset::Name { name, is_store } => {
+                let position = name.node.start;
+                let symbol = symbol_table.lookup_at_position(&name.id, position)?;
+                let assigned = symbol.declarations.iter().find(|decl| {
+                    let node = decl.declaration_path().node;
+                    is_store && node.start <= position && position < node.end
+                });
+                // Names used in a function body before the definition refer to the last declaration
+                assigned
+                    .or_else(|| symbol.declaration_until_position(position))
+                    .or_else(|| symbol.last_declaration())
+            }
+            NodeAtOffset::FunctionName(f) => declaration_with_node(symbol_table, &f.name, f.node),
+            NodeAtOffset::ClassName(c) => declaration_with_node(symbol_table, &c.name, c.node),
+            NodeAtOffset::Arg(a) => declaration_with_node(symbol_table, &a.arg, a.node),
+            NodeAtOffset::Alias(a) => {
+                declaration_with_node(symbol_table, identifier.name(), a.node)
+            }
+            NodeAtOffset::Attribute(a) => {
+                // Only the attributes of classes are known, e.g. `A.method`
+                let ast::Expression::Name(value) = a.value.as_ref() else {
+                    return None;
+                };
+                let symbol = symbol_table.lookup_at_position(&value.id, value.node.start)?;
+                let Some(Declaration::Class(class)) =
+                    symbol.declaration_until_position(value.node.start)
+                else {
+                    return None;
+                };
+                symbol_table
+                    .scope_of_node(class.declaration_path.node)?
+                    .lookup(&a.attr)?
+                    .last_declaration()
+            }
+        };
+        declaration.cloned()
+    }
+}
+
+/// A range of a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    // Byte offsets in the source of the file
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A replacement of the text between the byte offsets of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub new_text: String,
+}
+
+/// What a name refers to after following the imports
+#[derive(Debug, Clone)]
+pub(crate) enum ImportTarget {
+    // A declaration and the name it is declared with in its module
+    Declaration(String, Declaration),
+    // A module, e.g. `os` in `import os`
+    Module(PathBuf),
+}
+
+// Imports of imports are followed up to this depth, it also stops import cycles
+const MAX_IMPORT_DEPTH: usize = 16;
+
+impl BuildManager {
+    /// Follows the names bound by imports to the declarations in the imported modules.
+    /// Returns None when an import cannot be resolved.
+    pub(crate) fn follow_imports(
+        &self,
+        name: &str,
+        declaration: Declaration,
+    ) -> Option<ImportTarget> {
+        let mut name = name.to_string();
+        let mut declaration = declaration;
+        for _ in 0..MAX_IMPORT_DEPTH {
+            let Declaration::Alias(alias) = &declaration else {
+                return Some(ImportTarget::Declaration(name, declaration));
+            };
+            let importer = self.get_state_by_module_name(&alias.declaration_path.module_name)?;
+            let module_path =
+                self.resolve_module(&importer.file.path, &alias.module, alias.level)?;
+            let Some(imported_name) = &alias.name else {
+                return Some(ImportTarget::Module(module_path));
+            };
+            let imported = self.get_state(&module_path).and_then(|module| {
+                module
+                    .symbol_table()
+                    .global_scope()
+                    .lookup(imported_name)?
+                    .last_declaration()
+                    .cloned()
+            });
+            match imported {
+                Some(imported) => {
+                    name = imported_name.clone();
+                    declaration = imported;
+                }
+                // `from a import b` can import the submodule `a.b`
+                None => {
+                    let submodule = format!("{}.{}", alias.module, imported_name);
+                    return self
+                        .resolve_module(&importer.file.path, &submodule, alias.level)
+                        .map(ImportTarget::Module);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the location of the name in the declaration
+    pub(crate) fn declaration_location(
+        &self,
+        name: &str,
+        declaration: &Declaration,
+    ) -> Option<Location> {
+        let state = self.get_state_by_module_name(&declaration.declaration_path().module_name)?;
+        let range = declaration_name_range(state, name, declaration);
+        Some(Location {
+            path: state.file.path.clone(),
+            start: range.start,
+            end: range.end,
+        })
+    }
+}
+
+/// Returns the range of the name in a declaration of the module,
+/// or the range of the whole declaration if the name is not found
+pub(crate) fn declaration_name_range(state: &State, name: &str, declaration: &Declaration) -> Node {
+    let source = &state.file.source;
+    let node = declaration.declaration_path().node;
+    match declaration {
+        Declaration::Function(_) => def_name_range(source, node, "def", name),
+        Declaration::Class(_) => def_name_range(source, node, "class", name),
+        Declaration::Parameter(p) => Some(arg_name_range(source, &p.parameter_node)),
+        Declaration::Variable(_) | Declaration::Alias(_) => {
+            name_range_in_node(&state.file.body, node, name)
+        }
+    }
+    .unwrap_or(node)
+}
+
+// Finds the declaration of a definition, the name is looked up in the scopes around the node
+pub(crate) fn declaration_with_node<'a>(
+    symbol_table: &'a SymbolTable,
+    name: &str,
+    node: Node,
+) -> Option<&'a Declaration> {
+    // The scope of a method starts with its node, which hides the class scope from
+    // `scopes_at_position`, so all the scopes are searched
+    symbol_table
+        .scopes()
+        .filter_map(|scope| scope.lookup(name))
+        .flat_map(|symbol| symbol.declarations.iter())
+        .find(|decl| decl.declaration_path().node == node)
+}
+
+// Returns the range of the first name that the node binds,
+// e.g. `x` in the statement `x: int = 1` or `b` in the alias `a as b`
+fn name_range_in_node(body: &[Statement], node: Node, name: &str) -> Option<Node> {
+    struct NameFinder<'n> {
+        node: Node,
+        name: &'n str,
+        found: Option<Node>,
+    }
+    impl<'a> AstWalker<'a> for NameFinder<'_> {
+        fn enter(&mut self, node: AstNode<'a>) -> bool {
+            let range = node.node();
+            if self.found.is_some() || range.end < self.node.start || self.node.end < range.start {
+                return false;
+            }
+            let inside = self.node.start <= range.start && range.end <= self.node.end;
+            match node {
+                AstNode::Expression(ast::Expression::Name(n)) if inside && n.id == self.name => {
+                    self.found = Some(n.node);
+                }
+                AstNode::Alias(a) if a.node == self.node => {
+                    self.found = Some(alias_name_range(a));
+                }
+                _ => {}
+            }
+            true
+        }
+    }
+    let mut finder = NameFinder {
+        node,
+        name,
+        found: None,
+    };
+    walk_body(&mut finder, body);
+    finder.found
+}
+
+/// Returns the range of the name of a function or class definition,
+/// the name is the first identifier after the keyword.
+pub(crate) fn def_name_range(source: &str, node: Node, keyword: &str, name: &str) -> Option<Node> {
+    let text = source.get(node.start..node.end)?;
+    let mut search_start = 0;
+    while let Some(index) = text[search_start..].find(keyword) {
+        let keyword_start = search_start + index;
+        let keyword_end = keyword_start + keyword.len();
+        search_start = keyword_end;
+        let is_word_start = text[..keyword_start]
+            .chars()
+            .next_back()
+            .map_or(true, |c| !is_identifier_char(c));
+        let rest = &text[keyword_end..];
+        let name_offset = keyword_end + rest.len() - rest.trim_start().len();
+        if !is_word_start || name_offset == keyword_end {
+            continue;
+        }
+        let after_name = text[name_offset..].strip_prefix(name)?;
+        if after_name.starts_with(is_identifier_char) {
+            continue;
+        }
+        let start = node.start + name_offset;
+        return Some(Node::new(start, start + name.len()));
+    }
+    None
+}
+
+pub(crate) fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Returns the range of the attribute name, e.g. `b` in `a.b`
+pub(crate) fn attribute_name_range(attribute: &Attribute) -> Node {
+    Node::new(
+        attribute.node.end.saturating_sub(attribute.attr.len()),
+        attribute.node.end,
+    )
+}
+
+/// Returns the range of the name that the import binds, e.g. `b` in `import a as b`
+/// or `a` in `import a.b`
+pub(crate) fn alias_name_range(alias: &Alias) -> Node {
+    match &alias.asname {
+        Some(asname) => Node::new(alias.node.end.saturating_sub(asname.len()), alias.node.end),
+        None => Node::new(
+            alias.node.start,
+            alias.node.start + alias_bound_name(alias).len(),
+        ),
+    }
+}
+
+// `import a.b` binds `a`, names imported with `from` never contain dots
+fn alias_bound_name(alias: &Alias) -> &str {
+    match &alias.asname {
+        Some(asname) => asname,
+        None => alias.name.split('.').next().unwrap_or_default(),
+    }
+}
+
+// The node of `*args` and `**kwargs` includes the stars
+pub(crate) fn arg_name_range(source: &str, arg: &Arg) -> Node {
+    let text = source.get(arg.node.start..arg.node.end).unwrap_or_default();
+    let start = arg.node.start + text.len() - text.trim_start_matches('*').len();
+    Node::new(start, start + arg.arg.len())
+}
+
+/// Returns the docstring of a module, class or function body
+pub(crate) fn docstring(body: &[Statement]) -> Option<String> {
+    match body.first() {
+        Some(Statement::ExpressionStatement(ast::Expression::Constant(c))) => match &c.value {
+            ast::ConstantValue::Str(s) => Some(clean_docstring(s)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Removes the common indentation of the lines after the first line, like `inspect.cleandoc`
+fn clean_docstring(docstring: &str) -> String {
+    let mut lines = docstring.lines();
+    let first = lines.next().unwrap_or_default().trim();
+    let rest = lines.collect::<Vec<&str>>();
+    let indent = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut cleaned = vec![first.to_string()];
+    cleaned.extend(rest.iter().map(|line| {
+        line.get(indent..)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string()
+    }));
+    cleaned.join("\n").trim().to_string()
+}
+
+/// Returns the callee and the arguments text when the text ends inside the parentheses of a call,
+/// e.g. `(["obj", "f"], "a, b")` for `obj.f(a, b`
+pub(crate) fn open_call(text: &str) -> Option<(Vec<&str>, &str)> {
+    let mut depth = 0;
+    let mut open = None;
+    for (index, c) in text.char_indices().rev() {
+        match c {
+            ')' | ']' | '}' => depth += 1,
+            '(' | '[' | '{' if depth > 0 => depth -= 1,
+            '(' => {
+                open = Some(index);
+                break;
+            }
+            // Inside a list, a set or a dict instead of a call
+            '[' | '{' => return None,
+            _ => {}
+        }
+    }
+    let open = open?;
+    let callee = text[..open].trim_end();
+    let start = callee
+        .trim_end_matches(|c| is_identifier_char(c) || c == '.')
+        .len();
+    let parts = callee[start..].split('.').collect::<Vec<_>>();
+    if parts
+        .iter()
+        .any(|part| !part.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+    {
+        return None;
+    }
+    Some((parts, &text[open + 1..]))
+}
+
+/// Splits the arguments text of a call at the commas that are not nested in brackets,
+/// the last argument is the one after the last comma and can be empty
+pub(crate) fn split_arguments(arguments: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut parts = vec![];
+    for (index, c) in arguments.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&arguments[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&arguments[start..]);
+    parts
+}
+
+/// Returns the name of a keyword argument, like `a` in `a=1`
+pub(crate) fn keyword_argument_name(argument: &str) -> Option<&str> {
+    let (name, value) = argument.split_once('=')?;
+    let name = name.trim();
+    let is_identifier = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(is_identifier_char);
+    // `a == 1` is a comparison
+    (is_identifier && !value.starts_with('=')).then_some(name)
+}
+
+/// Returns the source text of a node
+pub(crate) fn node_text(source: &str, node: Node) -> &str {
+    source.get(node.start..node.end).unwrap_or_default()
+}
+
+/// Formats the parameters of a function like they are written in the source,
+/// e.g. `a, b: int = 1, *args, c, **kwargs`
+pub(crate) fn format_arguments(args: &Arguments, source: &str) -> String {
+    format_parameters(args, source).join(", ")
+}
+
+/// Formats a parameter with its annotation and default value, e.g. `b: int = 1`
+pub(crate) fn format_parameter(
+    arg: &Arg,
+    default: Option<&ast::Expression>,
+    source: &str,
+) -> String {
+    let mut param = arg.arg.clone();
+    if let Some(annotation) = &arg.annotation {
+        param.push_str(": ");
+        param.push_str(node_text(source, annotation.get_node()));
+    }
+    if let Some(default) = default {
+        param.push_str(if arg.annotation.is_some() { " = " } else { "=" });
+        param.push_str(node_text(source, default.get_node()));
+    }
+    param
+}
+
+/// Formats each parameter of a function, the `/` and `*` separators are included
+pub(crate) fn format_parameters(args: &Arguments, source: &str) -> Vec<String> {
+    let format_arg = |arg: &Arg, default| format_parameter(arg, default, source);
+
+    let mut params = vec![];
+    let positional_count = args.posonlyargs.len() + args.args.len();
+    let first_default = positional_count.saturating_sub(args.defaults.len());
+    for (i, arg) in args.posonlyargs.iter().chain(args.args.iter()).enumerate() {
+        let default = i
+            .checked_sub(first_default)
+            .and_then(|index| args.defaults.get(index));
+        params.push(format_arg(arg, default));
+        if i + 1 == args.posonlyargs.len() {
+            params.push("/".to_string());
+        }
+    }
+    match &args.vararg {
+        Some(vararg) => params.push(format!("*{}", format_arg(vararg, None))),
+        None if !args.kwonlyargs.is_empty() => params.push("*".to_string()),
+        None => {}
+    }
+    for (i, arg) in args.kwonlyargs.iter().enumerate() {
+        let default = args.kw_defaults.get(i).and_then(|d| d.as_ref());
+        params.push(format_arg(arg, default));
+    }
+    if let Some(kwarg) = &args.kwarg {
+        params.push(format!("**{}", format_arg(kwarg, None)));
+    }
+    params
+}
+
+/// Formats the signature of a function, e.g. `def f(a: int) -> str`
+pub(crate) fn format_function_signature(function: &FunctionDef, source: &str) -> String {
+    let mut signature = format!(
+        "def {}({})",
+        function.name,
+        format_arguments(&function.args, source)
+    );
+    if let Some(returns) = &function.returns {
+        signature.push_str(" -> ");
+        signature.push_str(node_text(source, returns.get_node()));
+    }
+    signature
+}
+
+/// Formats the header of a class, e.g. `class A(B)`
+pub(crate) fn format_class_signature(class: &ClassDef, source: &str) -> String {
+    let bases = class
+        .bases
+        .iter()
+        .map(|base| node_text(source, base.get_node()))
+        .collect::<Vec<&str>>();
+    if bases.is_empty() {
+        format!("class {}", class.name)
+    } else {
+        format!("class {}({})", class.name, bases.join(", "))
+    }
+}
+
+/// Finds the class definition with the node in the body
+pub(crate) fn find_class_def(body: &[Statement], node: Node) -> Option<&ClassDef> {
+    struct ClassFinder<'a> {
+        node: Node,
+        found: Option<&'a ClassDef>,
+    }
+    impl<'a> AstWalker<'a> for ClassFinder<'a> {
+        fn enter(&mut self, node: AstNode<'a>) -> bool {
+            if self.found.is_some()
+                || !(node.node().start <= self.node.start && self.node.end <= node.node().end)
+            {
+                return false;
+            }
+            if let AstNode::Statement(Statement::ClassDef(c)) = node {
+                if c.node == self.node {
+                    self.found = Some(c);
+                    return false;
+                }
+            }
+            true
+        }
+    }
+    let mut finder = ClassFinder { node, found: None };
+    walk_body(&mut finder, body);
+    finder.found
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::build::{BuildSource, PythonHost};
+    use crate::settings::Settings;
+    use enderpy_python_parser::Parser;
+    use std::fs;
+
+    // Builds a project with the files and returns the manager and the project root
+    pub(crate) fn build_project(files: &[(&str, &str)]) -> (BuildManager, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sources = vec![];
+        for (name, source) in files {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, source).unwrap();
+            sources.push(path);
+        }
+        let settings = Settings {
+            root: dir.path().to_path_buf(),
+            ..Settings::test_settings()
+        };
+        let mut manager = BuildManager::with_host(
+            sources
+                .into_iter()
+                .map(|path| BuildSource::from_path(path, false).unwrap())
+                .collect(),
+            settings,
+            PythonHost::default(),
+        );
+        manager.build();
+        (manager, dir)
+    }
+
+    #[test]
+    fn test_identifier_at_offset() {
+        let source = "@decorator\ndef f(a, *args: int):\n    x = a.b\n";
+        let module = Parser::new(source.to_string(), "".into()).parse();
+        let at = |text: &str| {
+            let offset = source.find(text).unwrap();
+            identifier_at_offset(&module.body, source, offset).map(|found| found.node)
+        };
+
+        assert!(matches!(at("f("), Some(NodeAtOffset::FunctionName(f)) if f.name == "f"));
+        assert!(
+            matches!(at("decorator"), Some(NodeAtOffset::Name { name, .. }) if name.id == "decorator")
+        );
+        assert!(matches!(at("args"), Some(NodeAtOffset::Arg(a)) if a.arg == "args"));
+        assert!(matches!(
+            at("x ="),
+            Some(NodeAtOffset::Name { is_store: true, .. })
+        ));
+        assert!(
+            matches!(at("a.b"), Some(NodeAtOffset::Name { name, is_store: false }) if name.id == "a")
+        );
+        assert!(matches!(at("b\n"), Some(NodeAtOffset::Attribute(a)) if a.attr == "b"));
+        assert!(at("= a").is_none());
+    }
+
+    #[test]
+    fn test_format_arguments() {
+        let source = "def f(a, b: int = 1, /, c=2, *args, d, e: str = 'e', **kwargs) -> str: ...\n";
+        let module = Parser::new(source.to_string(), "".into()).parse();
+        let Statement::FunctionDef(f) = &module.body[0] else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            format_function_signature(f, source),
+            "def f(a, b: int = 1, /, c=2, *args, d, e: str = 'e', **kwargs) -> str"
+        );
+    }
+
+    #[test]
+    fn test_docstring() {
+        let source = "def f():\n    \"\"\"Summary.\n\n    Details\n      indented\n    \"\"\"\n";
+        let module = Parser::new(source.to_string(), "".into()).parse();
+        let Statement::FunctionDef(f) = &module.body[0] else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            docstring(&f.body).unwrap(),
+            "Summary.\n\nDetails\n  indented"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use std::path::{Path, PathBuf};
+
+use enderpy_python_parser::ast::{self, ClassDef, Node, Statement};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+use crate::state::State;
+use crate::symbol_table::{Class, Declaration, Function};
+
+use super::{declaration_with_node, find_class_def, ImportTarget, MAX_IMPORT_DEPTH};
+
+/// A value whose members can be accessed with a dot
+#[derive(Debug, Clone)]
+pub(crate) enum Namespace {
+    Module(PathBuf),
+    // A class or an instance of the class
+    Class { class: Box<Class>, instance: bool },
+}
+
+impl BuildManager {
+    /// Returns the declaration that the name refers to at the position of the module
+    pub(crate) fn lookup_declaration(
+        &self,
+        state: &State,
+        name: &str,
+        position: usize,
+    ) -> Option<Declaration> {
+        let symbol = state.symbol_table().lookup_at_position(name, position)?;
+        symbol
+            .declaration_until_position(position)
+            .or_else(|| symbol.last_declaration())
+            .cloned()
+    }
+
+    /// Resolves a dotted name like `a.b.c` at the position of the module,
+    /// the imports are followed.
+    pub(crate) fn resolve_dotted_name(
+        &self,
+        state: &State,
+        parts: &[&str],
+        position: usize,
+    ) -> Option<ImportTarget> {
+        let (first, rest) = parts.split_first()?;
+        let declaration = self.lookup_declaration(state, first, position)?;
+        let mut target = self.follow_imports(first, declaration)?;
+        for part in rest {
+            let namespace = self.namespace_of(target)?;
+            target = self.member(&namespace, part)?;
+        }
+        Some(target)
+    }
+
+    /// Returns the namespace of the value that the target refers to
+    pub(crate) fn namespace_of(&self, target: ImportTarget) -> Option<Namespace> {
+        let mut target = target;
+        for _ in 0..MAX_IMPORT_DEPTH {
+            let (name, declaration) = match target {
+                ImportTarget::Module(path) => return Some(Namespace::Module(path)),
+                ImportTarget::Declaration(name, declaration) => (name, declaration),
+            };
+            let state =
+                self.get_state_by_module_name(&declaration.declaration_path().module_name)?;
+            let position = declaration.declaration_path().node.start;
+            // The class of an instance is found from the annotation or the constructor call
+            let class_expression = match &declaration {
+                Declaration::Class(class) => {
+                    return Some(Namespace::Class {
+                        class: class.clone(),
+                        instance: false,
+                    })
+                }
+                Declaration::Alias(_) => {
+                    target = self.follow_imports(&name, declaration)?;
+                    continue;
+                }
+                Declaration::Variable(v) => match (&v.type_annotation, &v.inferred_type_source) {
+                    (Some(annotation), _) => annotation.clone(),
+                    (None, Some(ast::Expression::Call(call))) => *call.func.clone(),
+                    _ => return None,
+                },
+                Declaration::Parameter(p) => match &p.type_annotation {
+                    Some(annotation) => annotation.clone(),
+                    // `self` is an instance of the class of the method
+                    None => {
+                        let class = method_class(&state.file.body, p.parameter_node.node)?;
+                        let Declaration::Class(class) =
+                            declaration_with_node(state.symbol_table(), &class.name, class.node)?
+                        else {
+                            return None;
+                        };
+                        return Some(Namespace::Class {
+                            class: class.clone(),
+                            instance: true,
+                        });
+                    }
+                },
+                Declaration::Function(_) => return None,
+            };
+            let parts = dotted_name(&class_expression)?;
+            return match self.resolve_dotted_name(state, &parts, position)? {
+                ImportTarget::Declaration(_, Declaration::Class(class)) => Some(Namespace::Class {
+                    class,
+                    instance: true,
+                }),
+                _ => None,
+            };
+        }
+        None
+    }
+
+    /// Returns the namespace of the value before the dot of an attribute,
+    /// a dotted name or a constructor call like `A()`
+    pub(crate) fn receiver_namespace(
+        &self,
+        state: &State,
+        receiver: &ast::Expression,
+        position: usize,
+    ) -> Option<Namespace> {
+        if let ast::Expression::Call(call) = receiver {
+            let parts = dotted_name(&call.func)?;
+            return match self.namespace_of(self.resolve_dotted_name(state, &parts, position)?)? {
+                Namespace::Class { class, .. } => Some(Namespace::Class {
+                    class,
+                    instance: true,
+                }),
+                Namespace::Module(_) => None,
+            };
+        }
+        let parts = dotted_name(receiver)?;
+        self.namespace_of(self.resolve_dotted_name(state, &parts, position)?)
+    }
+
+    /// Returns the names and declarations of the members of the namespace.
+    /// Members of classes include the members of their base classes.
+    pub(crate) fn members(&self, namespace: &Namespace) -> Vec<(String, Declaration)> {
+        let mut members: Vec<(String, Declaration)> = vec![];
+        let mut pending = vec![namespace.clone()];
+        let mut depth = 0;
+        while let Some(namespace) = pending.pop() {
+            depth += 1;
+            if depth > MAX_IMPORT_DEPTH {
+                break;
+            }
+            let scope = match &namespace {
+                Namespace::Module(path) => self
+                    .get_state(path)
+                    .map(|state| state.symbol_table().global_scope()),
+                Namespace::Class { class, .. } => {
+                    let Some(state) =
+                        self.get_state_by_module_name(&class.declaration_path.module_name)
+                    else {
+                        continue;
+                    };
+                    let node = class.declaration_path.node;
+                    // Bases are searched after the class, in order
+                    if let Some(class_def) = find_class_def(&state.file.body, node) {
+                        for base in class_def.bases.iter().rev() {
+                            let base = dotted_name(base)
+                                .and_then(|parts| {
+                                    self.resolve_dotted_name(state, &parts, node.start)
+                                })
+                                .and_then(|target| self.namespace_of(target));
+                            pending.extend(base);
+                        }
+                    }
+                    state.symbol_table().scope_of_node(node)
+                }
+            };
+            let Some(scope) = scope else {
+                continue;
+            };
+            for symbol in scope.symbols() {
+                if members.iter().any(|(name, _)| *name == symbol.name) {
+                    continue;
+                }
+                if let Some(declaration) = symbol.last_declaration() {
+                    members.push((symbol.name.clone(), declaration.clone()));
+                }
+            }
+        }
+        members.sort_by(|a, b| a.0.cmp(&b.0));
+        members
+    }
+
+    /// Returns the function that a call of the dotted name runs, calling a class runs `__init__`.
+    /// The flag is true when the first parameter of the function is bound, like `self`.
+    pub(crate) fn called_function(
+        &self,
+        state: &State,
+        callee: &[&str],
+        position: usize,
+    ) -> Option<(Box<Function>, bool)> {
+        let function = match self.resolve_dotted_name(state, callee, position)? {
+            ImportTarget::Declaration(_, Declaration::Function(f)) => f,
+            ImportTarget::Declaration(_, Declaration::Class(class)) => {
+                let init = self.member(
+                    &Namespace::Class {
+                        class,
+                        instance: false,
+                    },
+                    "__init__",
+                )?;
+                match init {
+                    ImportTarget::Declaration(_, Declaration::Function(f)) => f,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        // Methods accessed from the class are not bound
+        let (receiver, _) = callee.split_at(callee.len() - 1);
+        let is_unbound = !receiver.is_empty()
+            && matches!(
+                self.resolve_dotted_name(state, receiver, position)
+                    .and_then(|target| self.namespace_of(target)),
+                Some(Namespace::Class {
+                    instance: false,
+                    ..
+                })
+            );
+        let bound = function.is_method && !is_unbound;
+        Some((function, bound))
+    }
+
+    /// Returns the member of the namespace with the name
+    pub(crate) fn member(&self, namespace: &Namespace, name: &str) -> Option<ImportTarget> {
+        let member = self
+            .members(namespace)
+            .into_iter()
+            .find(|(member, _)| member == name);
+        match (member, namespace) {
+            (Some((name, declaration)), _) => Some(ImportTarget::Declaration(name, declaration)),
+            (None, Namespace::Module(path)) => submodule(path, name).map(ImportTarget::Module),
+            (None, Namespace::Class { .. }) => None,
+        }
+    }
+}
+
+/// Returns the names of a dotted name expression, e.g. `["a", "b"]` for `a.b`
+pub(crate) fn dotted_name(expression: &ast::Expression) -> Option<Vec<&str>> {
+    match expression {
+        ast::Expression::Name(n) => Some(vec![n.id.as_str()]),
+        ast::Expression::Attribute(a) => {
+            let mut parts = dotted_name(&a.value)?;
+            parts.push(a.attr.as_str());
+            Some(parts)
+        }
+        _ => None,
+    }
+}
+
+// Returns the path of the submodule of a package
+fn submodule(package: &Path, name: &str) -> Option<PathBuf> {
+    let is_package = package.file_stem().is_some_and(|stem| stem == "__init__");
+    if !is_package {
+        return None;
+    }
+    let dir = package.parent()?;
+    [
+        dir.join(name).join("__init__.pyi"),
+        dir.join(name).join("__init__.py"),
+        dir.join(format!("{}.pyi", name)),
+        dir.join(format!("{}.py", name)),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+// Returns the class of the method whose first parameter is the node, like `self` in
+// `def method(self)`
+fn method_class(body: &[Statement], parameter: Node) -> Option<&ClassDef> {
+    struct MethodFinder<'a> {
+        parameter: Node,
+        found: Option<&'a ClassDef>,
+    }
+    impl<'a> AstWalker<'a> for MethodFinder<'a> {
+        fn enter(&mut self, node: AstNode<'a>) -> bool {
+            let range = node.node();
+            if self.found.is_some()
+                || !(range.start <= self.parameter.start && self.parameter.end <= range.end)
+            {
+                return false;
+            }
+            if let AstNode::Statement(Statement::ClassDef(c)) = node {
+                let is_first_parameter = c.body.iter().any(|stmt| match stmt {
+                    Statement::FunctionDef(f) => f
+                        .args
+                        .posonlyargs
+                        .iter()
+                        .chain(f.args.args.iter())
+                        .next()
+                        .is_some_and(|arg| arg.node == self.parameter),
+                    _ => false,
+                });
+                if is_first_parameter {
+                    self.found = Some(c);
+                    return false;
+                }
+            }
+            true
+        }
+    }
+    let mut finder = MethodFinder {
+        parameter,
+        found: None,
+    };
+    walk_body(&mut finder, body);
+    finder.found
+}
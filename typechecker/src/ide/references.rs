@@ -0,0 +1,260 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{self, Node};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+use crate::state::State;
+use crate::symbol_table::Declaration;
+
+use super::{attribute_name_range, identifier_at_offset, ImportTarget, Location};
+
+// A symbol of a module, symbols are identified by their name and the scope that contains them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SymbolId {
+    pub module_name: String,
+    pub scope: Node,
+    pub name: String,
+}
+
+impl BuildManager {
+    /// Returns the locations of the uses of the symbol at the byte offset in all the modules.
+    /// The declarations of the symbol are included if `include_declaration` is true.
+    pub fn references(
+        &self,
+        path: &Path,
+        offset: usize,
+        include_declaration: bool,
+    ) -> Vec<Location> {
+        match self.symbol_at_offset(path, offset) {
+            Some(ImportTarget::Declaration(name, declaration)) => self
+                .symbol_of_declaration(&name, &declaration)
+                .map(|symbol| self.symbol_locations(&symbol, include_declaration))
+                .unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    // Returns the target of the identifier at the offset.
+    // Imports are followed unless they bind another name with `as`, that name is a new symbol.
+    pub(crate) fn symbol_at_offset(&self, path: &Path, offset: usize) -> Option<ImportTarget> {
+        let state = self.get_state(path)?;
+        let identifier = identifier_at_offset(&state.file.body, &state.file.source, offset)?;
+        let declaration = self.resolve_identifier(state, &identifier)?;
+        match &declaration {
+            Declaration::Alias(alias)
+                if alias
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| name != identifier.name()) =>
+            {
+                Some(ImportTarget::Declaration(
+                    identifier.name().to_string(),
+                    declaration,
+                ))
+            }
+            _ => self.follow_imports(identifier.name(), declaration),
+        }
+    }
+
+    // Returns the symbol that contains the declaration
+    pub(crate) fn symbol_of_declaration(
+        &self,
+        name: &str,
+        declaration: &Declaration,
+    ) -> Option<SymbolId> {
+        let module_name = &declaration.declaration_path().module_name;
+        let node = declaration.declaration_path().node;
+        let state = self.get_state_by_module_name(module_name)?;
+        state.symbol_table().scopes().find_map(|scope| {
+            let symbol = scope.lookup(name)?;
+            symbol
+                .declarations
+                .iter()
+                .any(|decl| decl.declaration_path().node == node)
+                .then(|| SymbolId {
+                    module_name: module_name.clone(),
+                    scope: scope.node,
+                    name: name.to_string(),
+                })
+        })
+    }
+
+    // Returns the locations of the symbol in all the modules, sorted by path and offset
+    fn symbol_locations(&self, target: &SymbolId, include_declaration: bool) -> Vec<Location> {
+        let mut locations = vec![];
+        for state in self.modules.values() {
+            let location = |node: Node| Location {
+                path: state.file.path.clone(),
+                start: node.start,
+                end: node.end,
+            };
+            for scope in state.symbol_table().scopes() {
+                for symbol in scope.symbols() {
+                    let is_target = state.file.module_name == target.module_name
+                        && scope.node == target.scope
+                        && symbol.name == target.name;
+                    if is_target {
+                        if include_declaration {
+                            locations.extend(
+                                symbol.declarations.iter().filter_map(|decl| {
+                                    self.declaration_location(&symbol.name, decl)
+                                }),
+                            );
+                        }
+                        locations.extend(symbol.references.iter().copied().map(location));
+                        continue;
+                    }
+                    // Names imported from the module of the target
+                    for declaration in symbol.declarations.iter() {
+                        let Declaration::Alias(alias) = declaration else {
+                            continue;
+                        };
+                        let Some(imported_name) = &alias.name else {
+                            continue;
+                        };
+                        let imports_target = match self
+                            .follow_imports(&symbol.name, declaration.clone())
+                        {
+                            Some(ImportTarget::Declaration(name, decl)) => {
+                                self.symbol_of_declaration(&name, &decl).as_ref() == Some(target)
+                            }
+                            _ => false,
+                        };
+                        if !imports_target {
+                            continue;
+                        }
+                        let node = alias.declaration_path.node;
+                        locations.push(location(Node::new(
+                            node.start,
+                            node.start + imported_name.len(),
+                        )));
+                        // Without `as`, the uses of the import are uses of the target
+                        if symbol.name == *imported_name {
+                            locations.extend(symbol.references.iter().copied().map(location));
+                        }
+                    }
+                }
+            }
+            locations.extend(
+                self.attribute_references(state, target)
+                    .into_iter()
+                    .map(location),
+            );
+        }
+        locations.sort_by(|a, b| (&a.path, a.start).cmp(&(&b.path, b.start)));
+        locations.dedup();
+        locations
+    }
+
+    // Returns the attributes of the module that refer to the target, like `method` in `self.method`
+    fn attribute_references(&self, state: &State, target: &SymbolId) -> Vec<Node> {
+        struct AttributeCollector<'a> {
+            name: &'a str,
+            attributes: Vec<&'a ast::Attribute>,
+        }
+        impl<'a> AstWalker<'a> for AttributeCollector<'a> {
+            fn enter(&mut self, node: AstNode<'a>) -> bool {
+                if let AstNode::Expression(ast::Expression::Attribute(a)) = node {
+                    if a.attr == self.name {
+                        self.attributes.push(a);
+                    }
+                }
+                true
+            }
+        }
+        let mut collector = AttributeCollector {
+            name: &target.name,
+            attributes: vec![],
+        };
+        walk_body(&mut collector, &state.file.body);
+
+        collector
+            .attributes
+            .into_iter()
+            .filter(|attribute| {
+                let member = self
+                    .receiver_namespace(state, &attribute.value, attribute.node.start)
+                    .and_then(|namespace| self.member(&namespace, &attribute.attr));
+                match member {
+                    Some(ImportTarget::Declaration(name, declaration)) => {
+                        self.symbol_of_declaration(&name, &declaration).as_ref() == Some(target)
+                    }
+                    _ => false,
+                }
+            })
+            .map(attribute_name_range)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::tests::build_project;
+
+    #[test]
+    fn references_across_modules() {
+        let files = [
+            (
+                "lib.py",
+                "class A:\n    def method(self):\n        return self.method()\n\ndef helper(a):\n    return A().method()\n",
+            ),
+            (
+                "main.py",
+                "import lib\nfrom lib import helper, A as B\n\nhelper(B)\nlib.helper(1)\nB.method\n",
+            ),
+        ];
+        let (manager, dir) = build_project(&files);
+        let references = |file: &str, text: &str, include_declaration: bool| {
+            let path = dir.path().join(file);
+            let source = std::fs::read_to_string(&path).unwrap();
+            manager
+                .references(&path, source.find(text).unwrap(), include_declaration)
+                .into_iter()
+                .map(|location| {
+                    let source = std::fs::read_to_string(&location.path).unwrap();
+                    let file = location.path.file_name().unwrap().to_string_lossy();
+                    let line = source[..location.start].matches('\n').count() + 1;
+                    format!(
+                        "{}:{}:{}",
+                        file,
+                        line,
+                        &source[location.start..location.end]
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            references("main.py", "helper(B)", true),
+            vec![
+                "lib.py:5:helper",
+                "main.py:2:helper",
+                "main.py:4:helper",
+                "main.py:5:helper"
+            ]
+        );
+        assert_eq!(
+            references("lib.py", "helper", false),
+            vec!["main.py:2:helper", "main.py:4:helper", "main.py:5:helper"]
+        );
+        // The uses of the alias `B` are not uses of `A`
+        assert_eq!(
+            references("lib.py", "A:", true),
+            vec!["lib.py:1:A", "lib.py:6:A", "main.py:2:A"]
+        );
+        assert_eq!(
+            references("main.py", "B)", true),
+            vec!["main.py:2:B", "main.py:4:B", "main.py:6:B"]
+        );
+        assert_eq!(
+            references("lib.py", "method(self)", true),
+            vec![
+                "lib.py:2:method",
+                "lib.py:3:method",
+                "lib.py:6:method",
+                "main.py:6:method"
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use enderpy_python_parser::token::Kind;
+use enderpy_python_parser::Lexer;
+use miette::{bail, Result};
+
+use crate::build::BuildManager;
+use crate::type_check::builtins::BUILTINS;
+
+use super::{identifier_at_offset, ImportTarget, Location, TextEdit};
+
+impl BuildManager {
+    /// Returns the location of the identifier at the byte offset if its symbol can be renamed
+    pub fn prepare_rename(&self, path: &Path, offset: usize) -> Result<Location> {
+        let Some(state) = self.get_state(path) else {
+            bail!("{} is not part of the project", path.display());
+        };
+        let Some(identifier) = identifier_at_offset(&state.file.body, &state.file.source, offset)
+        else {
+            bail!("there is no symbol to rename at the position");
+        };
+        let name = identifier.name();
+        let declaration = match self.symbol_at_offset(path, offset) {
+            Some(ImportTarget::Declaration(_, declaration)) => declaration,
+            Some(ImportTarget::Module(_)) => bail!("modules cannot be renamed"),
+            None if BUILTINS.contains(&name) => bail!("cannot rename the builtin '{}'", name),
+            None => bail!("cannot find the declaration of '{}'", name),
+        };
+        let module_path = self
+            .get_state_by_module_name(&declaration.declaration_path().module_name)
+            .map(|state| state.file.path.clone());
+        match module_path {
+            Some(module_path) if self.is_third_party(&module_path) => bail!(
+                "cannot rename '{}', it is defined outside the project in {}",
+                name,
+                module_path.display()
+            ),
+            Some(_) => Ok(Location {
+                path: path.to_path_buf(),
+                start: identifier.range.start,
+                end: identifier.range.end,
+            }),
+            None => bail!("cannot find the declaration of '{}'", name),
+        }
+    }
+
+    /// Returns the edits of each file that rename the symbol at the byte offset,
+    /// its uses in all the modules of the project are renamed.
+    pub fn rename(
+        &self,
+        path: &Path,
+        offset: usize,
+        new_name: &str,
+    ) -> Result<HashMap<PathBuf, Vec<TextEdit>>> {
+        self.prepare_rename(path, offset)?;
+        if !is_identifier(new_name) {
+            bail!("'{}' is not a valid identifier", new_name);
+        }
+        let mut edits: HashMap<PathBuf, Vec<TextEdit>> = HashMap::new();
+        for location in self.references(path, offset, true) {
+            edits.entry(location.path).or_default().push(TextEdit {
+                start: location.start,
+                end: location.end,
+                new_text: new_name.to_string(),
+            });
+        }
+        Ok(edits)
+    }
+}
+
+// Keywords are not identifiers, the lexer tells them apart
+fn is_identifier(name: &str) -> bool {
+    let mut lexer = Lexer::new(name);
+    lexer
+        .next_token()
+        .is_ok_and(|token| token.kind == Kind::Identifier && token.end == name.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::tests::build_project;
+
+    #[test]
+    fn rename_across_modules() {
+        let files = [
+            ("lib.py", "def helper():\n    pass\n\nhelper()\n"),
+            (
+                "main.py",
+                "import lib\nfrom lib import helper\n\nhelper()\nlib.helper()\nprint(1)\n",
+            ),
+        ];
+        let (manager, dir) = build_project(&files);
+        let main = dir.path().join("main.py");
+        let offset = files[1].1.find("helper()").unwrap();
+
+        let edits = manager.rename(&main, offset, "run").unwrap();
+        let mut renamed = edits
+            .into_iter()
+            .map(|(path, mut edits)| {
+                let mut source = std::fs::read_to_string(&path).unwrap();
+                edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+                for edit in edits {
+                    source.replace_range(edit.start..edit.end, &edit.new_text);
+                }
+                source
+            })
+            .collect::<Vec<_>>();
+        renamed.sort();
+        assert_eq!(
+            renamed,
+            vec![
+                "def run():\n    pass\n\nrun()\n",
+                "import lib\nfrom lib import run\n\nrun()\nlib.run()\nprint(1)\n"
+            ]
+        );
+
+        assert!(manager.rename(&main, offset, "class").is_err());
+        assert!(manager.rename(&main, offset, "a b").is_err());
+        let builtin = files[1].1.find("print").unwrap();
+        assert!(manager.prepare_rename(&main, builtin).is_err());
+        let module = files[1].1.find("lib.helper").unwrap();
+        assert!(manager.prepare_rename(&main, module).is_err());
+    }
+}
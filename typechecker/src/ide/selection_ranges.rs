@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{GetNode, Node, Statement};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+
+impl BuildManager {
+    /// Returns the ranges of the nodes around the offset from the innermost to the whole module.
+    /// Each range contains the previous one, editors expand the selection through them.
+    pub fn selection_ranges(&self, path: &Path, offset: usize) -> Vec<Node> {
+        let Some(state) = self.get_state(path) else {
+            return vec![];
+        };
+        let source = &state.file.source;
+        let mut collector = SelectionCollector {
+            source,
+            offset,
+            ranges: vec![],
+        };
+        walk_body(&mut collector, &state.file.body);
+
+        let mut nodes = collector.ranges;
+        nodes.push(Node::new(0, source.len()));
+        // Nodes that only touch the offset, like the left operand when the offset
+        // is at the operator, are skipped unless they contain the smaller ranges
+        nodes.sort_by_key(|node| node.end - node.start);
+        let mut ranges: Vec<Node> = vec![];
+        for node in nodes {
+            let contains_last = ranges.last().map_or(true, |last| {
+                node.start <= last.start && last.end <= node.end && node != *last
+            });
+            if contains_last {
+                ranges.push(node);
+            }
+        }
+        ranges
+    }
+}
+
+// Collects the ranges of the nodes that contain the offset
+struct SelectionCollector<'a> {
+    source: &'a str,
+    offset: usize,
+    ranges: Vec<Node>,
+}
+
+impl<'a> AstWalker<'a> for SelectionCollector<'_> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        // The decorators are before the node of the definition
+        let decorators = match node {
+            AstNode::Statement(Statement::FunctionDef(f)) => f.decorator_list.first(),
+            AstNode::Statement(Statement::AsyncFunctionDef(f)) => f.decorator_list.first(),
+            AstNode::Statement(Statement::ClassDef(c)) => c.decorator_list.first(),
+            _ => None,
+        };
+        let mut range = node.node();
+        if let Some(decorator) = decorators {
+            range.start = range.start.min(decorator.get_node().start);
+        }
+        // Compound statements end after the blank lines that follow them
+        range.end = range.start.max(self.source[..range.end].trim_end().len());
+        if self.offset < range.start || range.end < self.offset {
+            return false;
+        }
+        self.ranges.push(range);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::tests::build_project;
+
+    #[test]
+    fn selection_ranges() {
+        let source = "def f(a):\n    return [a + 1, 2]\n\nx = 1\n";
+        let (manager, dir) = build_project(&[("main.py", source)]);
+        let path = dir.path().join("main.py");
+        let ranges = manager
+            .selection_ranges(&path, source.find("1,").unwrap())
+            .into_iter()
+            .map(|range| &source[range.start..range.end])
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ranges,
+            vec![
+                "1",
+                "a + 1",
+                "[a + 1, 2]",
+                "return [a + 1, 2]",
+                "def f(a):\n    return [a + 1, 2]",
+                source,
+            ]
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{self, GetNode, Node, Statement};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::BuildManager;
+use crate::state::State;
+use crate::symbol_table::Declaration;
+use crate::type_check::builtins::{BUILTINS, BUILTIN_CLASSES};
+
+use super::namespace::Namespace;
+use super::{
+    alias_name_range, arg_name_range, attribute_name_range, def_name_range, IdentifierAtOffset,
+    ImportTarget, NodeAtOffset,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemanticTokenKind {
+    Class,
+    Function,
+    Method,
+    Parameter,
+    Variable,
+    Module,
+    // Variables that are accessed as attributes of classes and instances
+    Property,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SemanticTokenModifiers {
+    // The identifier declares the symbol, like the name of a function definition
+    pub declaration: bool,
+    // Constants, like `MAX_SIZE = 1` or `x: Final = 1`
+    pub readonly: bool,
+    // Builtins and symbols of the standard library and installed packages
+    pub default_library: bool,
+}
+
+/// The classification of an identifier of a module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub start: usize,
+    pub end: usize,
+    pub kind: SemanticTokenKind,
+    pub modifiers: SemanticTokenModifiers,
+}
+
+impl BuildManager {
+    /// Returns the tokens of the identifiers of the module sorted by position.
+    /// Only the identifiers in the byte range are returned if it is given.
+    /// Identifiers that cannot be resolved are skipped so the clients can fall back to their grammar.
+    pub fn semantic_tokens(&self, path: &Path, range: Option<Node>) -> Vec<SemanticToken> {
+        let Some(state) = self.get_state(path) else {
+            return vec![];
+        };
+        let mut collector = IdentifierCollector {
+            source: &state.file.source,
+            range: range.unwrap_or(Node::new(0, state.file.source.len())),
+            store_targets: vec![],
+            identifiers: vec![],
+        };
+        walk_body(&mut collector, &state.file.body);
+
+        let mut tokens = collector
+            .identifiers
+            .into_iter()
+            .filter(|identifier| identifier.range.start < identifier.range.end)
+            .filter_map(|identifier| {
+                let (kind, modifiers) = self.classify(state, &identifier)?;
+                Some(SemanticToken {
+                    start: identifier.range.start,
+                    end: identifier.range.end,
+                    kind,
+                    modifiers,
+                })
+            })
+            .collect::<Vec<_>>();
+        tokens.sort_by_key(|token| token.start);
+        tokens.dedup_by_key(|token| token.start);
+        tokens
+    }
+
+    fn classify(
+        &self,
+        state: &State,
+        identifier: &IdentifierAtOffset,
+    ) -> Option<(SemanticTokenKind, SemanticTokenModifiers)> {
+        let declaration = match identifier.node {
+            NodeAtOffset::Attribute(a) => {
+                let namespace = self.receiver_namespace(state, &a.value, a.node.start)?;
+                let is_class_member = matches!(namespace, Namespace::Class { .. });
+                let (kind, modifiers) = self.classify_target(self.member(&namespace, &a.attr)?);
+                // Variables of classes are properties of their instances
+                let kind = match kind {
+                    SemanticTokenKind::Variable if is_class_member => SemanticTokenKind::Property,
+                    kind => kind,
+                };
+                return Some((kind, modifiers));
+            }
+            _ => self.resolve_identifier(state, identifier),
+        };
+        let is_declaration = match identifier.node {
+            NodeAtOffset::Name { is_store, .. } => is_store,
+            _ => true,
+        };
+        let (kind, modifiers) = match declaration {
+            Some(declaration) => {
+                match self.follow_imports(identifier.name(), declaration.clone()) {
+                    Some(target) => self.classify_target(target),
+                    // Imports that cannot be resolved
+                    None => match declaration {
+                        Declaration::Alias(alias) if alias.name.is_none() => {
+                            (SemanticTokenKind::Module, SemanticTokenModifiers::default())
+                        }
+                        _ => (
+                            SemanticTokenKind::Variable,
+                            SemanticTokenModifiers::default(),
+                        ),
+                    },
+                }
+            }
+            None if BUILTINS.contains(&identifier.name()) => {
+                let kind = if BUILTIN_CLASSES.contains(&identifier.name()) {
+                    SemanticTokenKind::Class
+                } else {
+                    SemanticTokenKind::Function
+                };
+                let modifiers = SemanticTokenModifiers {
+                    default_library: true,
+                    ..SemanticTokenModifiers::default()
+                };
+                (kind, modifiers)
+            }
+            None => return None,
+        };
+        Some((
+            kind,
+            SemanticTokenModifiers {
+                declaration: is_declaration,
+                ..modifiers
+            },
+        ))
+    }
+
+    fn classify_target(&self, target: ImportTarget) -> (SemanticTokenKind, SemanticTokenModifiers) {
+        let declaration = match target {
+            ImportTarget::Module(path) => {
+                let modifiers = SemanticTokenModifiers {
+                    default_library: self.is_third_party(&path),
+                    ..SemanticTokenModifiers::default()
+                };
+                return (SemanticTokenKind::Module, modifiers);
+            }
+            ImportTarget::Declaration(_, declaration) => declaration,
+        };
+        let default_library = self
+            .get_state_by_module_name(&declaration.declaration_path().module_name)
+            .is_some_and(|state| self.is_third_party(&state.file.path));
+        let mut modifiers = SemanticTokenModifiers {
+            default_library,
+            ..SemanticTokenModifiers::default()
+        };
+        let kind = match &declaration {
+            Declaration::Class(_) => SemanticTokenKind::Class,
+            Declaration::Function(f) if f.is_method => SemanticTokenKind::Method,
+            Declaration::Function(_) => SemanticTokenKind::Function,
+            Declaration::Parameter(_) => SemanticTokenKind::Parameter,
+            Declaration::Variable(v) => {
+                modifiers.readonly = v.is_constant;
+                SemanticTokenKind::Variable
+            }
+            // Imports are followed before, this is an import that cannot be followed further
+            Declaration::Alias(_) => SemanticTokenKind::Variable,
+        };
+        (kind, modifiers)
+    }
+}
+
+// Collects the identifiers of the module that overlap the range
+struct IdentifierCollector<'a> {
+    source: &'a str,
+    range: Node,
+    // Targets of the enclosing assignments
+    store_targets: Vec<Vec<Node>>,
+    identifiers: Vec<IdentifierAtOffset<'a>>,
+}
+
+impl<'a> IdentifierCollector<'a> {
+    fn push(&mut self, node: NodeAtOffset<'a>, range: Node) {
+        if range.start < self.range.end && self.range.start < range.end {
+            self.identifiers.push(IdentifierAtOffset { node, range });
+        }
+    }
+}
+
+impl<'a> AstWalker<'a> for IdentifierCollector<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        // The decorators are before the node of the definition
+        let decorators = match node {
+            AstNode::Statement(Statement::FunctionDef(f)) => f.decorator_list.first(),
+            AstNode::Statement(Statement::ClassDef(c)) => c.decorator_list.first(),
+            _ => None,
+        };
+        let mut range = node.node();
+        if let Some(decorator) = decorators {
+            range.start = range.start.min(decorator.get_node().start);
+        }
+        if range.end < self.range.start || self.range.end < range.start {
+            return false;
+        }
+        match node {
+            AstNode::Statement(Statement::AssignStatement(a)) => {
+                self.store_targets
+                    .push(a.targets.iter().map(|target| target.get_node()).collect());
+            }
+            AstNode::Statement(Statement::AnnAssignStatement(a)) => {
+                self.store_targets.push(vec![a.target.get_node()]);
+            }
+            AstNode::Statement(Statement::FunctionDef(f)) => {
+                if let Some(range) = def_name_range(self.source, f.node, "def", &f.name) {
+                    self.push(NodeAtOffset::FunctionName(f), range);
+                }
+            }
+            AstNode::Statement(Statement::ClassDef(c)) => {
+                if let Some(range) = def_name_range(self.source, c.node, "class", &c.name) {
+                    self.push(NodeAtOffset::ClassName(c), range);
+                }
+            }
+            AstNode::Expression(ast::Expression::Name(n)) => {
+                let is_store = self
+                    .store_targets
+                    .iter()
+                    .flatten()
+                    .any(|target| target.start <= n.node.start && n.node.end <= target.end);
+                self.push(NodeAtOffset::Name { name: n, is_store }, n.node);
+            }
+            AstNode::Expression(ast::Expression::Attribute(a)) if !a.attr.is_empty() => {
+                self.push(NodeAtOffset::Attribute(a), attribute_name_range(a));
+            }
+            AstNode::Arg(a) => {
+                self.push(NodeAtOffset::Arg(a), arg_name_range(self.source, a));
+            }
+            AstNode::Alias(a) => {
+                self.push(NodeAtOffset::Alias(a), alias_name_range(a));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn leave(&mut self, node: AstNode<'a>) {
+        if matches!(
+            node,
+            AstNode::Statement(Statement::AssignStatement(_) | Statement::AnnAssignStatement(_))
+        ) {
+            self.store_targets.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enderpy_python_parser::ast::Node;
+
+    use crate::ide::tests::build_project;
+
+    #[test]
+    fn semantic_tokens() {
+        let source = r#"import lib
+from lib import helper
+
+MAX_SIZE = 1
+
+class A:
+    value = 2
+
+    def method(self, x):
+        return self.value + x + MAX_SIZE
+
+def f(a: int):
+    print(a)
+    return helper(A().method(a), lib)
+"#;
+        let (manager, dir) = build_project(&[
+            ("main.py", source),
+            ("lib.py", "def helper(*args):\n    pass\n"),
+        ]);
+        let path = dir.path().join("main.py");
+        let tokens = manager
+            .semantic_tokens(&path, None)
+            .into_iter()
+            .map(|token| {
+                let mut modifiers = vec![];
+                if token.modifiers.declaration {
+                    modifiers.push("declaration");
+                }
+                if token.modifiers.readonly {
+                    modifiers.push("readonly");
+                }
+                if token.modifiers.default_library {
+                    modifiers.push("defaultLibrary");
+                }
+                format!(
+                    "{} {:?} {}",
+                    &source[token.start..token.end],
+                    token.kind,
+                    modifiers.join(",")
+                )
+                .trim_end()
+                .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            vec![
+                "lib Module declaration",
+                "helper Function declaration",
+                "MAX_SIZE Variable declaration,readonly",
+                "A Class declaration",
+                "value Variable declaration",
+                "method Method declaration",
+                "self Parameter declaration",
+                "x Parameter declaration",
+                "self Parameter",
+                "value Property",
+                "x Parameter",
+                "MAX_SIZE Variable readonly",
+                "f Function declaration",
+                "a Parameter declaration",
+                "int Class defaultLibrary",
+                "print Function defaultLibrary",
+                "a Parameter",
+                "helper Function",
+                "A Class",
+                "method Method",
+                "a Parameter",
+                "lib Module",
+            ]
+        );
+
+        let start = source.find("def f").unwrap();
+        let range = manager.semantic_tokens(&path, Some(Node::new(start, start + 14)));
+        assert_eq!(range.len(), 3);
+    }
+}
@@ -0,0 +1,287 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{self, Arguments, GetNode};
+
+use crate::build::BuildManager;
+use crate::symbol_table::Function;
+
+use super::{
+    docstring, format_parameter, keyword_argument_name, node_text, open_call, split_arguments,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureHelp {
+    // The signature without `def`, e.g. `f(a: int, b=1) -> str`
+    pub label: String,
+    pub documentation: Option<String>,
+    pub parameters: Vec<ParameterLabel>,
+    pub active_parameter: Option<usize>,
+}
+
+/// A parameter of a signature, given by its byte range in the label of the signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterLabel {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParameterKind {
+    PositionalOnly,
+    Positional,
+    VarPositional,
+    KeywordOnly,
+    VarKeyword,
+}
+
+struct Parameter {
+    name: String,
+    kind: ParameterKind,
+    // The parameter with its annotation and default value
+    text: String,
+}
+
+impl BuildManager {
+    /// Returns the signature of the function that is called at the byte offset,
+    /// the offset is inside the parentheses of the call.
+    pub fn signature_help(&self, path: &Path, offset: usize) -> Option<SignatureHelp> {
+        let state = self.get_state(path)?;
+        let before = state.file.source.get(..offset)?;
+        let (callee, arguments) = open_call(before)?;
+        let (function, bound) = self.called_function(state, &callee, offset)?;
+        let source = &self
+            .get_state_by_module_name(&function.declaration_path.module_name)?
+            .file
+            .source;
+
+        let mut parameters = parameters(&function.function_node.args, source);
+        if bound && !parameters.is_empty() {
+            parameters.remove(0);
+        }
+        let (label, labels) = signature_label(&function, &parameters, source);
+        Some(SignatureHelp {
+            label,
+            documentation: docstring(&function.function_node.body),
+            active_parameter: active_parameter(&parameters, &split_arguments(arguments)),
+            parameters: labels,
+        })
+    }
+}
+
+fn parameters(args: &Arguments, source: &str) -> Vec<Parameter> {
+    let parameter = |arg: &ast::Arg, kind, default, prefix: &str| Parameter {
+        name: arg.arg.clone(),
+        kind,
+        text: format!("{}{}", prefix, format_parameter(arg, default, source)),
+    };
+
+    let mut parameters = vec![];
+    let first_default =
+        (args.posonlyargs.len() + args.args.len()).saturating_sub(args.defaults.len());
+    for (i, arg) in args.posonlyargs.iter().chain(args.args.iter()).enumerate() {
+        let kind = if i < args.posonlyargs.len() {
+            ParameterKind::PositionalOnly
+        } else {
+            ParameterKind::Positional
+        };
+        let default = i
+            .checked_sub(first_default)
+            .and_then(|index| args.defaults.get(index));
+        parameters.push(parameter(arg, kind, default, ""));
+    }
+    if let Some(vararg) = &args.vararg {
+        parameters.push(parameter(vararg, ParameterKind::VarPositional, None, "*"));
+    }
+    for (i, arg) in args.kwonlyargs.iter().enumerate() {
+        let default = args.kw_defaults.get(i).and_then(|d| d.as_ref());
+        parameters.push(parameter(arg, ParameterKind::KeywordOnly, default, ""));
+    }
+    if let Some(kwarg) = &args.kwarg {
+        parameters.push(parameter(kwarg, ParameterKind::VarKeyword, None, "**"));
+    }
+    parameters
+}
+
+// Returns the label of the signature and the ranges of the parameters in it,
+// the `/` and `*` separators are written but are not parameters
+fn signature_label(
+    function: &Function,
+    parameters: &[Parameter],
+    source: &str,
+) -> (String, Vec<ParameterLabel>) {
+    let mut label = format!("{}(", function.function_node.name);
+    let mut labels = vec![];
+    let push = |label: &mut String, text: &str| {
+        if !label.ends_with('(') {
+            label.push_str(", ");
+        }
+        label.push_str(text);
+    };
+    for (i, parameter) in parameters.iter().enumerate() {
+        let is_first_keyword_only = parameter.kind == ParameterKind::KeywordOnly
+            && !parameters[..i].iter().any(|p| {
+                matches!(
+                    p.kind,
+                    ParameterKind::VarPositional | ParameterKind::KeywordOnly
+                )
+            });
+        if is_first_keyword_only {
+            push(&mut label, "*");
+        }
+        push(&mut label, &parameter.text);
+        labels.push(ParameterLabel {
+            name: parameter.name.clone(),
+            start: label.len() - parameter.text.len(),
+            end: label.len(),
+        });
+        let is_last_positional_only = parameter.kind == ParameterKind::PositionalOnly
+            && parameters
+                .get(i + 1)
+                .map_or(true, |next| next.kind != ParameterKind::PositionalOnly);
+        if is_last_positional_only {
+            push(&mut label, "/");
+        }
+    }
+    label.push(')');
+    if let Some(returns) = &function.function_node.returns {
+        label.push_str(" -> ");
+        label.push_str(node_text(source, returns.get_node()));
+    }
+    (label, labels)
+}
+
+// Returns the index of the parameter that the last argument is passed to
+fn active_parameter(parameters: &[Parameter], arguments: &[&str]) -> Option<usize> {
+    let (current, passed) = arguments.split_last()?;
+    let index_of_kind = |kind| parameters.iter().position(|p| p.kind == kind);
+
+    if let Some(name) = keyword_argument_name(current) {
+        return parameters
+            .iter()
+            .position(|p| {
+                p.name == name
+                    && matches!(
+                        p.kind,
+                        ParameterKind::Positional | ParameterKind::KeywordOnly
+                    )
+            })
+            .or_else(|| index_of_kind(ParameterKind::VarKeyword));
+    }
+    let current = current.trim_start();
+    if current.starts_with("**") {
+        return index_of_kind(ParameterKind::VarKeyword);
+    }
+    if current.starts_with('*') {
+        return index_of_kind(ParameterKind::VarPositional);
+    }
+    // Unpacked arguments fill an unknown number of parameters, they are not counted
+    let positional = passed
+        .iter()
+        .filter(|argument| {
+            keyword_argument_name(argument).is_none() && !argument.trim_start().starts_with('*')
+        })
+        .count();
+    parameters
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| {
+            matches!(
+                p.kind,
+                ParameterKind::PositionalOnly | ParameterKind::Positional
+            )
+        })
+        .nth(positional)
+        .map(|(index, _)| index)
+        .or_else(|| index_of_kind(ParameterKind::VarPositional))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ide::tests::build_project;
+
+    #[test]
+    fn signature_help() {
+        let source = r#"def f(a, b: int = 1, /, c=2, *args, d, e: str = "", **kwargs) -> None:
+    """Does nothing."""
+
+class A:
+    def __init__(self, x):
+        pass
+
+    def method(self, y):
+        pass
+
+a = A(1)
+f(1, 2, c=
+f(1, 2, 3, 4, 5
+f(d=1, e
+f(**opts, x=
+A(
+a.method(
+A.method(g(1, 2),
+"#;
+        let (manager, dir) = build_project(&[("a.py", source)]);
+        let path = dir.path().join("a.py");
+        let help = |line: &str| {
+            let offset = source.find(line).unwrap() + line.len();
+            manager.signature_help(&path, offset).map(|help| {
+                let active = help
+                    .active_parameter
+                    .map(|index| help.parameters[index].name.clone());
+                (help.label, active)
+            })
+        };
+        let signature = r#"f(a, b: int = 1, /, c=2, *args, d, e: str = "", **kwargs) -> None"#;
+
+        assert_eq!(
+            help("f(1, 2, c="),
+            Some((signature.to_string(), Some("c".to_string())))
+        );
+        assert_eq!(
+            help("f(1, 2, 3, 4, 5"),
+            Some((signature.to_string(), Some("args".to_string())))
+        );
+        assert_eq!(
+            help("f(d=1, e"),
+            Some((signature.to_string(), Some("a".to_string())))
+        );
+        assert_eq!(
+            help("f(**opts, x="),
+            Some((signature.to_string(), Some("kwargs".to_string())))
+        );
+        assert_eq!(
+            help("A("),
+            Some(("__init__(x)".to_string(), Some("x".to_string())))
+        );
+        assert_eq!(
+            help("a.method("),
+            Some(("method(y)".to_string(), Some("y".to_string())))
+        );
+        assert_eq!(
+            help("A.method(g(1, 2),"),
+            Some(("method(self, y)".to_string(), Some("y".to_string())))
+        );
+
+        let offset = source.find("f(1, 2, c=").unwrap() + 2;
+        let help = manager.signature_help(&path, offset).unwrap();
+        assert_eq!(help.documentation.as_deref(), Some("Does nothing."));
+        let labels = help
+            .parameters
+            .iter()
+            .map(|p| &help.label[p.start..p.end])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec![
+                "a",
+                "b: int = 1",
+                "c=2",
+                "*args",
+                "d",
+                "e: str = \"\"",
+                "**kwargs"
+            ]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::path::Path;
+
+use enderpy_python_parser::ast::{GetNode, Node};
+
+use crate::build::BuildManager;
+use crate::state::State;
+use crate::symbol_table::{Declaration, SymbolTableScope, SymbolTableType};
+
+use super::{find_class_def, node_text, Location};
+
+// The number of workspace symbols is limited, clients ask again as the query gets longer
+const MAX_WORKSPACE_SYMBOLS: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+    Method,
+    Class,
+    // Only used by the call hierarchy for the calls outside of functions
+    Module,
+}
+
+/// A symbol in the outline of a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // Byte range of the whole declaration, like a function with its body
+    pub start: usize,
+    pub end: usize,
+    // Byte range of the name
+    pub name_start: usize,
+    pub name_end: usize,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // The name of the class or function that contains the symbol
+    pub container: Option<String>,
+    // Location of the name
+    pub location: Location,
+}
+
+impl BuildManager {
+    /// Returns the outline of the module: its classes, functions and variables,
+    /// with the methods of classes and the functions nested in functions as children.
+    pub fn document_symbols(&self, path: &Path) -> Vec<DocumentSymbol> {
+        let Some(state) = self.get_state(path) else {
+            return vec![];
+        };
+        self.scope_symbols(state, state.symbol_table().global_scope())
+    }
+
+    /// Returns the symbols of the project modules whose names fuzzy match the query,
+    /// the best matches first.
+    pub fn workspace_symbols(&self, query: &str) -> Vec<WorkspaceSymbol> {
+        let mut matches = vec![];
+        for state in self.modules.values() {
+            let path = &state.file.path;
+            if !self.is_project_module(path) {
+                continue;
+            }
+            let mut pending = self
+                .document_symbols(path)
+                .into_iter()
+                .map(|symbol| (symbol, None))
+                .collect::<Vec<_>>();
+            while let Some((symbol, container)) = pending.pop() {
+                pending.extend(
+                    symbol
+                        .children
+                        .iter()
+                        .cloned()
+                        .map(|child| (child, Some(symbol.name.clone()))),
+                );
+                let Some(score) = fuzzy_score(query, &symbol.name) else {
+                    continue;
+                };
+                matches.push((
+                    score,
+                    WorkspaceSymbol {
+                        location: Location {
+                            path: path.clone(),
+                            start: symbol.name_start,
+                            end: symbol.name_end,
+                        },
+                        name: symbol.name,
+                        kind: symbol.kind,
+                        container,
+                    },
+                ));
+            }
+        }
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            (
+                a_score,
+                a.name.len(),
+                &a.name,
+                &a.location.path,
+                a.location.start,
+            )
+                .cmp(&(
+                    b_score,
+                    b.name.len(),
+                    &b.name,
+                    &b.location.path,
+                    b.location.start,
+                ))
+        });
+        matches
+            .into_iter()
+            .take(MAX_WORKSPACE_SYMBOLS)
+            .map(|(_, symbol)| symbol)
+            .collect()
+    }
+
+    // The symbols declared in the scope, sorted by their position.
+    // Only the functions and classes of function scopes are in the outline, not their locals.
+    fn scope_symbols(&self, state: &State, scope: &SymbolTableScope) -> Vec<DocumentSymbol> {
+        let is_function_scope = scope.symbol_table_type == SymbolTableType::Function;
+        let mut symbols = vec![];
+        for symbol in scope.symbols() {
+            // A symbol that is declared more than once is shown at its first declaration
+            let first_declaration = symbol
+                .declarations
+                .iter()
+                .min_by_key(|declaration| declaration.declaration_path().node.start);
+            let Some(declaration) = first_declaration else {
+                continue;
+            };
+            let kind = match declaration {
+                Declaration::Class(_) => SymbolKind::Class,
+                Declaration::Function(f) if f.is_method => SymbolKind::Method,
+                Declaration::Function(_) => SymbolKind::Function,
+                Declaration::Variable(_) if !is_function_scope => SymbolKind::Variable,
+                Declaration::Variable(_) | Declaration::Parameter(_) | Declaration::Alias(_) => {
+                    continue
+                }
+            };
+            let node = declaration.declaration_path().node;
+            let children = match declaration {
+                Declaration::Class(_) | Declaration::Function(_) => state
+                    .symbol_table()
+                    .scope_of_node(node)
+                    .map(|scope| self.scope_symbols(state, scope))
+                    .unwrap_or_default(),
+                _ => vec![],
+            };
+            let name = self
+                .declaration_location(&symbol.name, declaration)
+                .map_or((node.start, node.end), |location| {
+                    (location.start, location.end)
+                });
+            // Decorators are before the node and the blank lines after it are not included
+            let decorators = match declaration {
+                Declaration::Function(f) => f.function_node.decorator_list.first(),
+                Declaration::Class(_) => find_class_def(&state.file.body, node)
+                    .and_then(|class| class.decorator_list.first()),
+                _ => None,
+            };
+            let source = &state.file.source;
+            let start = decorators.map_or(node.start, |decorator| {
+                // The node of the decorator starts after the `@`
+                let decorator_start = decorator.get_node().start;
+                source[..decorator_start]
+                    .trim_end()
+                    .strip_suffix('@')
+                    .map_or(decorator_start, str::len)
+            });
+            let end = start
+                + node_text(source, Node::new(start, node.end))
+                    .trim_end()
+                    .len();
+            symbols.push(DocumentSymbol {
+                name: symbol.name.clone(),
+                kind,
+                start: start.min(name.0),
+                end: end.max(name.1),
+                name_start: name.0,
+                name_end: name.1,
+                children,
+            });
+        }
+        symbols.sort_by_key(|symbol| symbol.start);
+        symbols
+    }
+}
+
+// Returns how well the name matches the query, lower is better.
+// The characters of the query must appear in the name in order, the case is ignored.
+fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    let mut chars = name.char_indices();
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars() {
+        let (index, _) = chars.find(|(_, c)| c.eq_ignore_ascii_case(&q))?;
+        // Matches after the start of the name and gaps between matched characters
+        // make the match worse
+        score += match previous {
+            Some(previous) => index - previous - 1,
+            None => index,
+        };
+        previous = Some(index);
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_score, DocumentSymbol};
+    use crate::ide::tests::build_project;
+
+    // Formats the outline with one symbol per line, children are indented
+    fn outline(symbols: &[DocumentSymbol], depth: usize) -> String {
+        symbols
+            .iter()
+            .map(|symbol| {
+                format!(
+                    "{}{} {:?}\n{}",
+                    "  ".repeat(depth),
+                    symbol.name,
+                    symbol.kind,
+                    outline(&symbol.children, depth + 1)
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn document_symbols() {
+        let source = "import os\n\nVALUE = 1\n\nclass A:\n    attribute = 2\n\n    def method(self):\n        local = 3\n\n        def inner():\n            pass\n\n@decorator\ndef f():\n    pass\n\nVALUE = 4\n";
+        let (manager, dir) = build_project(&[("a.py", source)]);
+        let symbols = manager.document_symbols(&dir.path().join("a.py"));
+
+        assert_eq!(
+            outline(&symbols, 0),
+            "VALUE Variable\nA Class\n  attribute Variable\n  method Method\n    inner Function\nf Function\n"
+        );
+        let f = &symbols[2];
+        assert_eq!(&source[f.start..f.end], "@decorator\ndef f():\n    pass");
+        assert_eq!(&source[f.name_start..f.name_end], "f");
+    }
+
+    #[test]
+    fn workspace_symbols() {
+        let (manager, _dir) = build_project(&[
+            (
+                "a.py",
+                "class HttpClient:\n    def send_request(self):\n        pass\n",
+            ),
+            ("b.py", "def search():\n    pass\n\nrequest = 1\n"),
+        ]);
+        let names = |query: &str| {
+            manager
+                .workspace_symbols(query)
+                .into_iter()
+                .map(|symbol| (symbol.name, symbol.container))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names("req"),
+            vec![
+                ("request".to_string(), None),
+                ("send_request".to_string(), Some("HttpClient".to_string()))
+            ]
+        );
+        assert_eq!(names("hc"), vec![("HttpClient".to_string(), None)]);
+        assert_eq!(fuzzy_score("sr", "send_request"), Some(4));
+        assert_eq!(fuzzy_score("x", "send_request"), None);
+    }
+}
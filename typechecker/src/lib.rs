@@ -1,6 +1,7 @@
 mod ast_visitor;
 mod ast_visitor_generic;
 mod ast_visitor_immut;
+mod ast_walker;
 mod nodes;
 mod ruff_python_import_resolver;
 mod semanal_utils;
@@ -9,6 +10,7 @@ mod symbol_table;
 mod type_check;
 
 pub mod build;
+pub mod ide;
 pub mod project;
 pub mod semantic_analyzer;
 pub mod settings;
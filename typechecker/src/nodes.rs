@@ -7,9 +7,11 @@
 
 use enderpy_python_parser as parser;
 use enderpy_python_parser::ast::{Import, ImportFrom, Module, Statement};
+use enderpy_python_parser::LineIndex;
 use std::path::PathBuf;
 
 use crate::ast_visitor::TraversalVisitor;
+use crate::build::BuildError;
 
 #[derive(Clone, Debug)]
 pub enum ImportKinds {
@@ -29,10 +31,23 @@ pub struct EnderpyFile {
     pub body: Vec<Statement>,
     pub source: String,
     pub path: PathBuf,
+    // If this file was found by following an import
+    pub followed: bool,
+    // Errors found while parsing the file
+    pub syntax_errors: Vec<BuildError>,
+    // Maps the byte offsets of the nodes to lines and columns
+    pub line_index: LineIndex,
 }
 
 impl<'a> EnderpyFile {
-    pub fn from(ast: Module, module_name: String, source: String, path: PathBuf) -> Self {
+    pub fn from(
+        ast: Module,
+        module_name: String,
+        source: String,
+        path: PathBuf,
+        followed: bool,
+        line_index: LineIndex,
+    ) -> Self {
         let mut file = Self {
             module_name,
             defs: vec![],
@@ -40,6 +55,9 @@ impl<'a> EnderpyFile {
             body: vec![],
             source: source.clone(),
             path,
+            followed,
+            syntax_errors: vec![],
+            line_index,
         };
 
         for stmt in &ast.body {
@@ -79,6 +97,7 @@ impl<'a> TraversalVisitor for EnderpyFile {
             Statement::FunctionDef(f) => self.visit_function_def(f),
             Statement::ClassDef(c) => self.visit_class_def(c),
             Statement::Match(m) => self.visit_match(m),
+            Statement::Invalid(_) => {}
             Statement::AsyncForStatement(f) => self.visit_async_for(f),
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
@@ -114,6 +133,7 @@ impl<'a> TraversalVisitor for EnderpyFile {
             parser::ast::Expression::IfExp(i) => self.visit_if_exp(i),
             parser::ast::Expression::JoinedStr(j) => self.visit_joined_str(j),
             parser::ast::Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            parser::ast::Expression::Invalid(_) => {}
         }
     }
 
@@ -1,5 +1,11 @@
 use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+
+use crate::settings::Settings;
+
 const PROJECT_ROOT_MARKERS: [&str; 1] = ["pyproject.toml"];
+const PYTHON_SOURCE_EXTENSIONS: [&str; 2] = ["py", "pyi"];
 
 pub fn find_project_root(path: &PathBuf) -> &Path {
     let root = path
@@ -16,3 +22,164 @@ pub fn find_project_root(path: &PathBuf) -> &Path {
         }
     }
 }
+
+/// Collects the python files to check from the given paths.
+/// When no path is given the `include` setting is used, and if that is empty the project root.
+/// Files are returned as is, directories are searched recursively
+/// skipping hidden directories, `__pycache__` and the `exclude` patterns.
+pub fn find_source_files(paths: &[PathBuf], settings: &Settings) -> std::io::Result<Vec<PathBuf>> {
+    let paths = if !paths.is_empty() {
+        paths.to_vec()
+    } else if !settings.include.is_empty() {
+        expand_include_patterns(settings)
+    } else {
+        vec![settings.root.clone()]
+    };
+    let exclude = settings
+        .exclude
+        .iter()
+        .filter_map(|pattern| match Pattern::new(pattern) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                log::warn!("invalid exclude pattern {}: {}", pattern, e);
+                None
+            }
+        })
+        .collect::<Vec<Pattern>>();
+
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            collect_source_files(&path, &settings.root, &exclude, &mut files)?;
+        } else if path.exists() {
+            files.push(path.clone());
+        } else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            ));
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn expand_include_patterns(settings: &Settings) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    for pattern in &settings.include {
+        let full_pattern = settings.root.join(pattern);
+        match glob::glob(&full_pattern.to_string_lossy()) {
+            Ok(matches) => paths.extend(matches.flatten()),
+            Err(e) => log::warn!("invalid include pattern {}: {}", pattern, e),
+        }
+    }
+    paths
+}
+
+fn is_excluded(path: &Path, root: &Path, exclude: &[Pattern]) -> bool {
+    let relative_path = path.strip_prefix(root).unwrap_or(path);
+    exclude
+        .iter()
+        .any(|pattern| pattern.matches_path(relative_path))
+}
+
+fn collect_source_files(
+    dir: &Path,
+    root: &Path,
+    exclude: &[Pattern],
+    files: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_excluded(&path, root, exclude) {
+            continue;
+        }
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if path.is_dir() {
+            if file_name.starts_with('.') || file_name == "__pycache__" {
+                continue;
+            }
+            collect_source_files(&path, root, exclude, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| PYTHON_SOURCE_EXTENSIONS.contains(&ext))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn find_source_files_in_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/pkg")).unwrap();
+        fs::create_dir_all(root.join("src/.hidden")).unwrap();
+        fs::create_dir_all(root.join("src/__pycache__")).unwrap();
+        fs::write(root.join("src/a.py"), "").unwrap();
+        fs::write(root.join("src/pkg/b.pyi"), "").unwrap();
+        fs::write(root.join("src/pkg/data.txt"), "").unwrap();
+        fs::write(root.join("src/.hidden/c.py"), "").unwrap();
+        fs::write(root.join("src/__pycache__/d.py"), "").unwrap();
+        fs::write(root.join("main.py"), "").unwrap();
+
+        let files = find_source_files(
+            &[
+                root.join("src"),
+                root.join("main.py"),
+                root.join("src/a.py"),
+            ],
+            &Settings::test_settings(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                root.join("main.py"),
+                root.join("src/a.py"),
+                root.join("src/pkg/b.pyi"),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_source_files_missing_path() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(
+            find_source_files(&[dir.path().join("missing.py")], &Settings::test_settings())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn find_source_files_with_include_and_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/migrations")).unwrap();
+        fs::create_dir_all(root.join("scripts")).unwrap();
+        fs::write(root.join("src/a.py"), "").unwrap();
+        fs::write(root.join("src/migrations/m.py"), "").unwrap();
+        fs::write(root.join("scripts/s.py"), "").unwrap();
+
+        let settings = Settings {
+            root: root.to_path_buf(),
+            include: vec!["src".to_string()],
+            exclude: vec!["**/migrations".to_string()],
+            ..Settings::test_settings()
+        };
+
+        assert_eq!(
+            find_source_files(&[], &settings).unwrap(),
+            vec![root.join("src/a.py")]
+        );
+    }
+}
@@ -1,6 +1,10 @@
 //! Expose the host environment to the resolver.
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard, OnceLock};
 
 use crate::ruff_python_import_resolver::python_platform::PythonPlatform;
 use crate::ruff_python_import_resolver::python_version::PythonVersion;
@@ -41,3 +45,334 @@ impl Host for StaticHost {
         PythonPlatform::Darwin
     }
 }
+
+/// A host that exposes the environment of a Python interpreter.
+///
+/// The interpreter is found with [`find_python_executable`] and queried for its
+/// `sys.path`, version and platform, so imports resolve the same way they do at runtime.
+#[derive(Debug, Clone)]
+pub struct PythonHost {
+    executable: Option<PathBuf>,
+    search_paths: Vec<PathBuf>,
+    version: PythonVersion,
+    platform: PythonPlatform,
+}
+
+impl Default for PythonHost {
+    fn default() -> Self {
+        Self {
+            executable: None,
+            search_paths: vec![],
+            version: PythonVersion::Py311,
+            platform: PythonPlatform::current(),
+        }
+    }
+}
+
+impl PythonHost {
+    /// Finds the Python interpreter of the project at `root` and queries it.
+    /// Falls back to a host without search paths when no interpreter is found.
+    /// The interpreter of a project is queried once, until [`PythonHost::invalidate`] is called.
+    pub fn discover(root: &Path, python_executable: Option<&Path>) -> Self {
+        let Some(executable) =
+            find_python_executable(root, python_executable, |key| std::env::var_os(key))
+        else {
+            log::warn!("cannot find a python interpreter, third party imports are not resolved");
+            return Self::default();
+        };
+        let key = (root.to_path_buf(), executable);
+        if let Some(host) = hosts().get(&key) {
+            return host.clone();
+        }
+        // The interpreter runs without the lock, so it does not block the other projects.
+        // Failures are not kept, the interpreter may work the next time.
+        match Self::from_interpreter(&key.1) {
+            Ok(host) => {
+                hosts().insert(key, host.clone());
+                host
+            }
+            Err(e) => {
+                log::warn!("cannot query python interpreter {}: {}", key.1.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Forgets the hosts of the project at `root`, so the next discovery queries the
+    /// interpreter again, e.g. after its settings changed or packages were installed.
+    pub fn invalidate(root: &Path) {
+        hosts().retain(|(host_root, _), _| host_root != root);
+    }
+
+    /// Asks the interpreter for its search paths, version and platform.
+    pub fn from_interpreter(executable: &Path) -> std::io::Result<Self> {
+        let output = Command::new(executable)
+            .arg("-c")
+            .arg(INTERPRETER_QUERY)
+            .output()?;
+        if !output.status.success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ));
+        }
+        let mut host = Self::from_query_output(&String::from_utf8_lossy(&output.stdout));
+        host.executable = Some(executable.to_path_buf());
+        log::debug!("python host: {:?}", host);
+        Ok(host)
+    }
+
+    // The output has the version on the first line, the platform on the second
+    // and one `sys.path` entry on each of the remaining lines.
+    fn from_query_output(output: &str) -> Self {
+        let mut host = Self::default();
+        let mut lines = output.lines();
+        if let Some(version) = lines.next() {
+            match version.parse() {
+                Ok(version) => host.version = version,
+                Err(e) => log::warn!("{}, using {:?}", e, host.version),
+            }
+        }
+        if let Some(platform) = lines.next() {
+            match platform.parse() {
+                Ok(platform) => host.platform = platform,
+                Err(e) => log::warn!("{}, using {:?}", e, host.platform),
+            }
+        }
+        // The empty entry is the working directory of the interpreter and
+        // entries that are not directories are zip files that cannot be resolved.
+        // The standard library is kept, typeshed stubs are not bundled to take its place.
+        host.search_paths = lines
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .filter(|path| path.is_dir())
+            .collect();
+        host
+    }
+
+    /// The interpreter that was queried, if any.
+    pub fn executable(&self) -> Option<&Path> {
+        self.executable.as_deref()
+    }
+}
+
+impl Host for PythonHost {
+    fn python_search_paths(&self) -> Vec<PathBuf> {
+        self.search_paths.clone()
+    }
+
+    fn python_version(&self) -> PythonVersion {
+        self.version
+    }
+
+    fn python_platform(&self) -> PythonPlatform {
+        self.platform
+    }
+}
+
+// The queried hosts by project root and interpreter
+fn hosts() -> MutexGuard<'static, HashMap<(PathBuf, PathBuf), PythonHost>> {
+    static HOSTS: OnceLock<Mutex<HashMap<(PathBuf, PathBuf), PythonHost>>> = OnceLock::new();
+    HOSTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+const INTERPRETER_QUERY: &str = r#"import sys
+print("%d.%d" % sys.version_info[:2])
+print(sys.platform)
+for path in sys.path:
+    print(path)
+"#;
+
+/// Finds the Python interpreter of the project at `root`, in order:
+/// the configured executable, the activated virtual environment (`VIRTUAL_ENV`),
+/// a `.venv` directory in the root, the activated conda environment (`CONDA_PREFIX`),
+/// the pyenv version of the project and finally `python3` or `python` on `PATH`.
+pub fn find_python_executable(
+    root: &Path,
+    configured: Option<&Path>,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+    if let Some(configured) = configured {
+        if configured.is_dir() {
+            return interpreter_in(configured);
+        }
+        return Some(configured.to_path_buf());
+    }
+
+    let prefixes = [
+        env("VIRTUAL_ENV").map(PathBuf::from),
+        Some(root.join(".venv")),
+        env("CONDA_PREFIX").map(PathBuf::from),
+        pyenv_prefix(root, &env),
+    ];
+    if let Some(executable) = prefixes
+        .into_iter()
+        .flatten()
+        .find_map(|prefix| interpreter_in(&prefix))
+    {
+        return Some(executable);
+    }
+
+    let path = env("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        ["python3", "python", "python.exe"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+// Finds the interpreter inside an environment prefix, e.g. a virtual environment
+fn interpreter_in(prefix: &Path) -> Option<PathBuf> {
+    [
+        "bin/python3",
+        "bin/python",
+        "Scripts/python.exe",
+        "python.exe",
+    ]
+    .iter()
+    .map(|name| prefix.join(name))
+    .find(|candidate| candidate.is_file())
+}
+
+// The pyenv version is selected by `PYENV_VERSION` or a `.python-version` file in the root
+fn pyenv_prefix(root: &Path, env: &impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let version = match env("PYENV_VERSION") {
+        Some(version) => version.to_string_lossy().to_string(),
+        None => std::fs::read_to_string(root.join(".python-version"))
+            .ok()?
+            .lines()
+            .next()?
+            .trim()
+            .to_string(),
+    };
+    let pyenv_root = env("PYENV_ROOT")
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".pyenv")))?;
+    Some(pyenv_root.join("versions").join(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn create_interpreter(prefix: &Path) -> PathBuf {
+        let executable = prefix.join("bin/python");
+        fs::create_dir_all(executable.parent().unwrap()).unwrap();
+        fs::write(&executable, "").unwrap();
+        executable
+    }
+
+    #[test]
+    fn find_configured_python_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let venv = dir.path().join("env");
+        let executable = create_interpreter(&venv);
+        create_interpreter(&dir.path().join(".venv"));
+
+        let found = find_python_executable(dir.path(), Some(&venv), |_| None);
+        assert_eq!(found, Some(executable.clone()));
+        let found = find_python_executable(dir.path(), Some(&executable), |_| None);
+        assert_eq!(found, Some(executable));
+    }
+
+    #[test]
+    fn find_python_executable_in_environments() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        let virtual_env = dir.path().join("virtual_env");
+        let conda = dir.path().join("conda");
+        let pyenv = dir.path().join("pyenv");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join(".python-version"), "3.11.7\n").unwrap();
+
+        let env = |key: &str| match key {
+            "VIRTUAL_ENV" => Some(virtual_env.clone().into_os_string()),
+            "CONDA_PREFIX" => Some(conda.clone().into_os_string()),
+            "PYENV_ROOT" => Some(pyenv.clone().into_os_string()),
+            _ => None,
+        };
+
+        let pyenv_python = create_interpreter(&pyenv.join("versions/3.11.7"));
+        assert_eq!(find_python_executable(&root, None, env), Some(pyenv_python));
+        let conda_python = create_interpreter(&conda);
+        assert_eq!(find_python_executable(&root, None, env), Some(conda_python));
+        let dot_venv_python = create_interpreter(&root.join(".venv"));
+        assert_eq!(
+            find_python_executable(&root, None, env),
+            Some(dot_venv_python)
+        );
+        let virtual_env_python = create_interpreter(&virtual_env);
+        assert_eq!(
+            find_python_executable(&root, None, env),
+            Some(virtual_env_python)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn discover_python_host_again_after_invalidate() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(&root).unwrap();
+        let executable = dir.path().join("python");
+        let write_interpreter = |version: &str| {
+            let script = dir.path().join("script");
+            fs::write(
+                &script,
+                format!("#!/bin/sh\necho {}\necho linux\n", version),
+            )
+            .unwrap();
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+            fs::rename(&script, &executable).unwrap();
+        };
+
+        // An interpreter that cannot run is tried again
+        let host = PythonHost::discover(&root, Some(&executable));
+        assert!(host.executable().is_none());
+        write_interpreter("3.10");
+        let host = PythonHost::discover(&root, Some(&executable));
+        assert!(matches!(host.python_version(), PythonVersion::Py310));
+
+        write_interpreter("3.12");
+        let host = PythonHost::discover(&root, Some(&executable));
+        assert!(matches!(host.python_version(), PythonVersion::Py310));
+        PythonHost::invalidate(&root);
+        let host = PythonHost::discover(&root, Some(&executable));
+        assert!(matches!(host.python_version(), PythonVersion::Py312));
+    }
+
+    #[test]
+    fn python_host_from_query_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let stdlib = dir.path().join("lib/python3.10");
+        let lib_dynload = stdlib.join("lib-dynload");
+        let site_packages = stdlib.join("site-packages");
+        let extra = dir.path().join("extra");
+        for path in [&lib_dynload, &site_packages, &extra] {
+            fs::create_dir_all(path).unwrap();
+        }
+        let output = format!(
+            "3.10\nlinux\n\n{}\n{}\n{}\n{}\n{}\n",
+            extra.display(),
+            dir.path().join("lib/python310.zip").display(),
+            stdlib.display(),
+            lib_dynload.display(),
+            site_packages.display()
+        );
+
+        let host = PythonHost::from_query_output(&output);
+        assert!(matches!(host.python_version(), PythonVersion::Py310));
+        assert_eq!(host.python_platform(), PythonPlatform::Linux);
+        assert_eq!(
+            host.python_search_paths(),
+            vec![extra, stdlib, lib_dynload, site_packages]
+        );
+    }
+}
@@ -11,7 +11,7 @@ mod py_typed;
 pub mod python_platform;
 pub mod python_version;
 pub mod resolver;
-mod search;
+pub(crate) mod search;
 
 #[cfg(test)]
 mod tests {
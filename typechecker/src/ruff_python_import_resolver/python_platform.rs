@@ -18,3 +18,38 @@ impl PythonPlatform {
         }
     }
 }
+
+impl PythonPlatform {
+    /// The platform the program is running on.
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            PythonPlatform::Darwin
+        } else if cfg!(target_os = "windows") {
+            PythonPlatform::Windows
+        } else {
+            PythonPlatform::Linux
+        }
+    }
+}
+
+impl std::str::FromStr for PythonPlatform {
+    type Err = String;
+
+    /// Accepts both the names used in configuration (`Linux`) and
+    /// the values of `sys.platform` (`linux`, `darwin`, `win32`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "darwin" => Ok(PythonPlatform::Darwin),
+            "linux" => Ok(PythonPlatform::Linux),
+            "windows" | "win32" => Ok(PythonPlatform::Windows),
+            _ => Err(format!("unsupported python platform `{}`", s)),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PythonPlatform {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -1,5 +1,5 @@
 /// Enum to represent a Python version.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PythonVersion {
     Py37,
     Py38,
@@ -22,3 +22,27 @@ impl PythonVersion {
         }
     }
 }
+
+impl std::str::FromStr for PythonVersion {
+    type Err = String;
+
+    /// Parses versions in the `3.X` form, e.g. `3.11`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "3.7" => Ok(PythonVersion::Py37),
+            "3.8" => Ok(PythonVersion::Py38),
+            "3.9" => Ok(PythonVersion::Py39),
+            "3.10" => Ok(PythonVersion::Py310),
+            "3.11" => Ok(PythonVersion::Py311),
+            "3.12" => Ok(PythonVersion::Py312),
+            _ => Err(format!("unsupported python version `{}`", s)),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PythonVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
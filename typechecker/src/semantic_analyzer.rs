@@ -1,13 +1,15 @@
 use enderpy_python_parser as parser;
 use enderpy_python_parser::ast::Expression;
 
-use parser::ast::Statement;
+use parser::ast::{Node, Statement};
+use std::collections::HashSet;
 
 use crate::{
     ast_visitor::TraversalVisitor,
+    ast_walker::{walk_body, AstNode, AstWalker},
     nodes::EnderpyFile,
     symbol_table::{
-        Class, Declaration, DeclarationPath, Function, Paramter, SymbolScope, SymbolTable,
+        Alias, Class, Declaration, DeclarationPath, Function, Paramter, SymbolScope, SymbolTable,
         SymbolTableNode, SymbolTableScope, SymbolTableType, Variable,
     },
 };
@@ -39,6 +41,7 @@ impl SemanticAnalyzer {
         let symbol_node = SymbolTableNode {
             name,
             declarations: vec![decl],
+            references: vec![],
         };
         self.globals.add_symbol(symbol_node)
     }
@@ -60,13 +63,14 @@ impl SemanticAnalyzer {
     ) {
         match target {
             Expression::Name(n) => {
+                let is_constant = is_constant_name(&n.id) || is_final(type_annotation.as_ref());
                 let decl = Declaration::Variable(Box::new(Variable {
                     declaration_path,
                     // TODO: Hacky way
                     scope: SymbolScope::Global,
                     type_annotation,
                     inferred_type_source: value,
-                    is_constant: false,
+                    is_constant,
                 }));
                 self.create_symbol(n.id.clone(), decl)
             }
@@ -90,34 +94,50 @@ impl SemanticAnalyzer {
         &mut self,
         alias: &parser::ast::Alias,
         declaration_path: DeclarationPath,
+        from_module: Option<(&str, usize)>,
     ) {
-        let import_symbol_name = if let Some(asname) = &alias.asname {
-            asname.clone()
-        } else {
-            alias.name.clone()
+        let decl = match from_module {
+            Some((module, level)) => Alias {
+                declaration_path,
+                module: module.trim_start_matches('.').to_string(),
+                level,
+                name: Some(alias.name.clone()),
+            },
+            // `import a.b` binds `a` while `import a.b as c` binds `c` to `a.b`
+            None => Alias {
+                declaration_path,
+                module: match alias.asname {
+                    Some(_) => alias.name.clone(),
+                    None => alias.name.split('.').next().unwrap_or_default().to_string(),
+                },
+                level: 0,
+                name: None,
+            },
         };
-        // TODO: imports are not variable, just ignoring for now and will fix later
-        let decl = Declaration::Variable(Box::new(Variable {
-            declaration_path,
-            scope: SymbolScope::Global,
-            type_annotation: None,
-            inferred_type_source: None,
-            is_constant: false,
-        }));
-        self.create_symbol(import_symbol_name, decl);
+        let import_symbol_name = match &alias.asname {
+            Some(asname) => asname.clone(),
+            None if from_module.is_none() => decl.module.clone(),
+            None => alias.name.clone(),
+        };
+        self.create_symbol(import_symbol_name, Declaration::Alias(Box::new(decl)));
     }
 
     fn add_arguments_definitions(&mut self, args: &parser::ast::Arguments) {
-        let defaults_len = args.defaults.len();
+        // Defaults belong to the last positional parameters
+        let first_default =
+            (args.posonlyargs.len() + args.args.len()).saturating_sub(args.defaults.len());
+        let positional_default = |index: usize| {
+            index
+                .checked_sub(first_default)
+                .and_then(|i| args.defaults.get(i))
+                .cloned()
+        };
         for (pos_only, index) in args.posonlyargs.iter().zip(0..args.posonlyargs.len()) {
             let declaration_path = DeclarationPath {
                 module_name: self.file.module_name.clone(),
                 node: pos_only.node,
             };
-            let default_value = args
-                .defaults
-                .get(defaults_len.wrapping_sub(index.wrapping_sub(1)))
-                .cloned();
+            let default_value = positional_default(index);
 
             self.create_symbol(
                 pos_only.arg.clone(),
@@ -136,13 +156,7 @@ impl SemanticAnalyzer {
                 node: arg.node,
             };
 
-            let default_value = args
-                .defaults
-                .get(
-                    defaults_len
-                        .wrapping_sub(args.posonlyargs.len().wrapping_sub(index).wrapping_sub(1)),
-                )
-                .cloned();
+            let default_value = positional_default(args.posonlyargs.len() + index);
 
             self.create_symbol(
                 arg.arg.clone(),
@@ -155,7 +169,7 @@ impl SemanticAnalyzer {
             );
         }
 
-        for arg in args.kwonlyargs.iter() {
+        for (arg, index) in args.kwonlyargs.iter().zip(0..) {
             let declaration_path = DeclarationPath {
                 module_name: self.file.module_name.clone(),
                 node: arg.node,
@@ -166,7 +180,7 @@ impl SemanticAnalyzer {
                     declaration_path,
                     parameter_node: arg.clone(),
                     type_annotation: arg.annotation.clone(),
-                    default_value: None,
+                    default_value: args.kw_defaults.get(index).cloned().flatten(),
                 })),
             );
         }
@@ -209,6 +223,77 @@ impl SemanticAnalyzer {
             None => (),
         }
     }
+
+    /// Records the names that use each symbol, after all the declarations of the module are known.
+    /// Names that are assigned to are declarations and not references.
+    pub fn record_references(&mut self, body: &[Statement]) {
+        let mut collector = ReferenceCollector {
+            bindings: HashSet::new(),
+            references: vec![],
+        };
+        walk_body(&mut collector, body);
+        for (name, node) in collector.references {
+            self.globals.add_reference(name, node);
+        }
+    }
+}
+
+// Names in upper case like `MAX_SIZE` are constants by convention
+fn is_constant_name(name: &str) -> bool {
+    name.chars().any(|c| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+// `Final`, `typing.Final` or `Final[int]`
+fn is_final(annotation: Option<&Expression>) -> bool {
+    match annotation {
+        Some(Expression::Name(n)) => n.id == "Final",
+        Some(Expression::Attribute(a)) => a.attr == "Final",
+        Some(Expression::Subscript(s)) => is_final(Some(&s.value)),
+        _ => false,
+    }
+}
+
+// Collects the names that are used, skipping the names that are assigned to
+struct ReferenceCollector<'a> {
+    bindings: HashSet<Node>,
+    references: Vec<(&'a str, Node)>,
+}
+
+impl ReferenceCollector<'_> {
+    fn add_bindings(&mut self, target: &Expression) {
+        match target {
+            Expression::Name(n) => {
+                self.bindings.insert(n.node);
+            }
+            Expression::Tuple(t) => {
+                for elm in t.elements.iter() {
+                    self.add_bindings(elm);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> AstWalker<'a> for ReferenceCollector<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        match node {
+            AstNode::Statement(Statement::AssignStatement(a)) => {
+                for target in a.targets.iter() {
+                    self.add_bindings(target);
+                }
+            }
+            AstNode::Statement(Statement::AnnAssignStatement(a)) => self.add_bindings(&a.target),
+            AstNode::Expression(Expression::Name(n)) if !self.bindings.contains(&n.node) => {
+                self.references.push((&n.id, n.node));
+            }
+            _ => {}
+        }
+        true
+    }
 }
 
 impl TraversalVisitor for SemanticAnalyzer {
@@ -238,6 +323,7 @@ impl TraversalVisitor for SemanticAnalyzer {
             parser::ast::Statement::FunctionDef(f) => self.visit_function_def(f),
             parser::ast::Statement::ClassDef(c) => self.visit_class_def(c),
             parser::ast::Statement::Match(m) => self.visit_match(m),
+            parser::ast::Statement::Invalid(_) => {}
             Statement::AsyncForStatement(f) => self.visit_async_for(f),
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
@@ -273,6 +359,7 @@ impl TraversalVisitor for SemanticAnalyzer {
             parser::ast::Expression::IfExp(i) => self.visit_if_exp(i),
             parser::ast::Expression::JoinedStr(j) => self.visit_joined_str(j),
             parser::ast::Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            parser::ast::Expression::Invalid(_) => {}
         }
     }
 
@@ -284,6 +371,7 @@ impl TraversalVisitor for SemanticAnalyzer {
                     module_name: self.file.module_name.clone(),
                     node: alias.node,
                 },
+                None,
             );
         }
     }
@@ -294,7 +382,7 @@ impl TraversalVisitor for SemanticAnalyzer {
                 module_name: self.file.module_name.clone(),
                 node: alias.node,
             };
-            self.create_import_alias_symbol(alias, declaration_path);
+            self.create_import_alias_symbol(alias, declaration_path, Some((&_i.module, _i.level)));
         }
     }
 
@@ -395,6 +483,7 @@ impl TraversalVisitor for SemanticAnalyzer {
         self.globals.enter_scope(SymbolTableScope::new(
             crate::symbol_table::SymbolTableType::Function,
             f.name.clone(),
+            f.node,
         ));
 
         self.add_arguments_definitions(&f.args);
@@ -438,6 +527,7 @@ impl TraversalVisitor for SemanticAnalyzer {
         self.globals.enter_scope(SymbolTableScope::new(
             SymbolTableType::Class,
             c.name.clone(),
+            c.node,
         ));
         let mut methods = vec![];
         for stmt in &c.body {
@@ -528,20 +618,19 @@ impl TraversalVisitor for SemanticAnalyzer {
 
     fn visit_assign(&mut self, assign: &parser::ast::Assign) {
         let value = &assign.value;
-        if assign.targets.len() > 1 {
-            panic!("multiple assignment not suported");
+        // Each target of `a = b = value` is assigned the value
+        for target in &assign.targets {
+            let declaration_path = DeclarationPath {
+                module_name: self.file.module_name.clone(),
+                node: assign.node,
+            };
+            self.create_variable_declaration_symbol(
+                target,
+                Some(value.clone()),
+                declaration_path,
+                None,
+            );
         }
-        let target = assign.targets.last().unwrap();
-        let declaration_path = DeclarationPath {
-            module_name: self.file.module_name.clone(),
-            node: assign.node,
-        };
-        self.create_variable_declaration_symbol(
-            target,
-            Some(value.clone()),
-            declaration_path,
-            None,
-        );
 
         self.visit_expr(&assign.value);
     }
@@ -1,39 +1,284 @@
-use config::{Config, ConfigError, File};
+use config::{Config, ConfigError, File, FileFormat};
 use serde::Deserialize;
-use std::{env, path::PathBuf};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 
-#[derive(Debug, Deserialize)]
-#[allow(unused)]
+pub use crate::ruff_python_import_resolver::python_platform::PythonPlatform;
+pub use crate::ruff_python_import_resolver::python_version::PythonVersion;
+pub use crate::type_check::rules::Rule;
+
+const PYPROJECT_FILE: &str = "pyproject.toml";
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
 pub struct ImportDiscovery {
     pub python_executable: Option<PathBuf>,
 }
 
-#[derive(Debug, Deserialize)]
-#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    /// The rule is disabled and its errors are not reported
+    Off,
+}
+
+/// Settings of a project, read from the `[tool.enderpy]` table in `pyproject.toml`.
+///
+/// ```toml
+/// [tool.enderpy]
+/// python-version = "3.11"
+/// python-platform = "Linux"
+/// include = ["src"]
+/// exclude = ["**/migrations"]
+/// extra-paths = ["vendor"]
+/// stub-path = "typings"
+/// venv-path = "."
+/// venv = ".venv"
+///
+/// [tool.enderpy.rules]
+/// unsupported-operator = "warning"
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
 pub struct Settings {
+    #[serde(skip)]
     pub debug: bool,
+    #[serde(skip)]
     pub root: PathBuf,
+    #[serde(flatten)]
     pub import_discovery: ImportDiscovery,
+    pub python_version: Option<PythonVersion>,
+    pub python_platform: Option<PythonPlatform>,
+    /// Paths or glob patterns of the files to check when no path is given
+    pub include: Vec<String>,
+    /// Glob patterns of the paths that are skipped when searching directories
+    pub exclude: Vec<String>,
+    /// Additional paths used to resolve imports
+    pub extra_paths: Vec<PathBuf>,
+    /// Path to custom typings (stub) modules
+    pub stub_path: Option<PathBuf>,
+    /// Path to the directory containing the virtual environment
+    pub venv_path: Option<PathBuf>,
+    /// Name of the virtual environment directory inside `venv_path`
+    pub venv: Option<PathBuf>,
+    /// Severity of each rule, rules that are not listed use their default severity
+    pub rules: HashMap<String, Severity>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PyProject {
+    #[serde(default)]
+    tool: Tool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Tool {
+    #[serde(default)]
+    enderpy: Option<Settings>,
 }
 
 impl Settings {
-    pub fn new() -> Result<Self, ConfigError> {
-        let _run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
-        let s = Config::builder()
-            // Start off by merging in the "default" configuration file
-            .add_source(File::with_name("examples/hierarchical-env/config/default"))
-            .build()?;
+    /// Reads the settings from the `pyproject.toml` inside root.
+    /// Default settings are used when the file or the `[tool.enderpy]` table does not exist.
+    /// Rules that do not exist are an error, so a misspelled rule is not ignored.
+    pub fn from_project_root(root: &Path) -> Result<Self, ConfigError> {
+        let pyproject = root.join(PYPROJECT_FILE);
+        let mut settings = if pyproject.is_file() {
+            let s = Config::builder()
+                .add_source(File::from(pyproject).format(FileFormat::Toml))
+                .build()?;
+            s.try_deserialize::<PyProject>()?
+                .tool
+                .enderpy
+                .unwrap_or_default()
+        } else {
+            Settings::default()
+        };
 
-        s.try_deserialize()
+        let mut unknown_rules = settings
+            .rules
+            .keys()
+            .filter(|code| Rule::from_code(code).is_none())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !unknown_rules.is_empty() {
+            unknown_rules.sort_unstable();
+            return Err(ConfigError::Message(format!(
+                "unknown rules in {}: {}",
+                PYPROJECT_FILE,
+                unknown_rules.join(", ")
+            )));
+        }
+
+        settings.root = root.to_path_buf();
+        settings.resolve_paths();
+        Ok(settings)
+    }
+
+    // Paths in the configuration are relative to the project root
+    fn resolve_paths(&mut self) {
+        let root = self.root.clone();
+        self.extra_paths = self.extra_paths.iter().map(|p| root.join(p)).collect();
+        self.stub_path = self.stub_path.as_ref().map(|p| root.join(p));
+        self.venv_path = self.venv_path.as_ref().map(|p| root.join(p));
+        self.import_discovery.python_executable = self
+            .import_discovery
+            .python_executable
+            .as_ref()
+            .map(|p| resolve_python_executable(&root, p, |key| std::env::var_os(key)));
+    }
+
+    /// Returns the configured severity of the rule or its default severity
+    pub fn rule_severity(&self, rule: Rule) -> Severity {
+        self.rules
+            .get(rule.code())
+            .copied()
+            .unwrap_or(rule.default_severity())
     }
 
     pub fn test_settings() -> Self {
-        Settings {
-            debug: false,
-            root: PathBuf::from(""),
-            import_discovery: ImportDiscovery {
-                python_executable: None,
-            },
-        }
+        Settings::default()
+    }
+}
+
+/// Resolves a configured python executable, a path is relative to the project root
+/// and a bare name like `python3` is looked up on `PATH`.
+/// Names that are not found on `PATH` are kept as they are.
+pub fn resolve_python_executable(
+    root: &Path,
+    executable: &Path,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> PathBuf {
+    if executable.components().count() > 1 {
+        return root.join(executable);
+    }
+    env("PATH")
+        .and_then(|path| {
+            std::env::split_paths(&path)
+                .map(|dir| dir.join(executable))
+                .find(|candidate| candidate.is_file())
+        })
+        .unwrap_or_else(|| executable.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn settings_from_pyproject() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PYPROJECT_FILE),
+            r#"
+[project]
+name = "test"
+
+[tool.enderpy]
+python-version = "3.10"
+python-platform = "Darwin"
+python-executable = "bin/python"
+include = ["src"]
+exclude = ["**/migrations"]
+extra-paths = ["vendor"]
+stub-path = "typings"
+venv = ".venv"
+
+[tool.enderpy.rules]
+unsupported-operator = "warning"
+type-error = "off"
+"#,
+        )
+        .unwrap();
+
+        let settings = Settings::from_project_root(dir.path()).unwrap();
+        let root = dir.path();
+        assert!(matches!(
+            settings.python_version,
+            Some(PythonVersion::Py310)
+        ));
+        assert_eq!(settings.python_platform, Some(PythonPlatform::Darwin));
+        assert_eq!(
+            settings.import_discovery.python_executable,
+            Some(root.join("bin/python"))
+        );
+        assert_eq!(settings.include, vec!["src".to_string()]);
+        assert_eq!(settings.exclude, vec!["**/migrations".to_string()]);
+        assert_eq!(settings.extra_paths, vec![root.join("vendor")]);
+        assert_eq!(settings.stub_path, Some(root.join("typings")));
+        assert_eq!(settings.venv, Some(PathBuf::from(".venv")));
+        assert_eq!(
+            settings.rule_severity(Rule::UnsupportedOperator),
+            Severity::Warning
+        );
+        assert_eq!(settings.rule_severity(Rule::TypeError), Severity::Off);
+        assert_eq!(settings.rule_severity(Rule::SyntaxError), Severity::Error);
+    }
+
+    #[test]
+    fn resolve_python_executable_names_on_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        let bin = dir.path().join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join("python3"), "").unwrap();
+        let env = |key: &str| (key == "PATH").then(|| bin.clone().into_os_string());
+
+        let resolve =
+            |executable: &str| resolve_python_executable(&root, Path::new(executable), env);
+        assert_eq!(resolve("python3"), bin.join("python3"));
+        assert_eq!(resolve("python3.12"), PathBuf::from("python3.12"));
+        assert_eq!(resolve(".venv/bin/python"), root.join(".venv/bin/python"));
+        assert_eq!(
+            resolve("/usr/bin/python3"),
+            PathBuf::from("/usr/bin/python3")
+        );
+    }
+
+    #[test]
+    fn settings_without_pyproject() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings::from_project_root(dir.path()).unwrap();
+        assert_eq!(settings.root, dir.path());
+        assert!(settings.python_version.is_none());
+        assert!(settings.rules.is_empty());
+        assert_eq!(settings.rule_severity(Rule::UndefinedName), Severity::Error);
+        assert_eq!(
+            settings.rule_severity(Rule::UnusedImport),
+            Severity::Warning
+        );
+    }
+
+    #[test]
+    fn settings_invalid_python_version() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PYPROJECT_FILE),
+            "[tool.enderpy]\npython-version = \"2.7\"\n",
+        )
+        .unwrap();
+        assert!(Settings::from_project_root(dir.path()).is_err());
+    }
+
+    #[test]
+    fn settings_unknown_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PYPROJECT_FILE),
+            "[tool.enderpy.rules]\nundefind-name = \"off\"\ntype-error = \"off\"\n",
+        )
+        .unwrap();
+        let err = Settings::from_project_root(dir.path()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown rules in pyproject.toml: undefind-name"
+        );
     }
 }
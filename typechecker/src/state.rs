@@ -22,10 +22,15 @@ impl State {
         for stmt in &self.file.body {
             sem_anal.visit_stmt(stmt)
         }
+        sem_anal.record_references(&self.file.body);
         self.symbol_table = sem_anal.globals
     }
 
     pub fn get_symbol_table(&self) -> SymbolTable {
         self.symbol_table.clone()
     }
+
+    pub fn symbol_table(&self) -> &SymbolTable {
+        &self.symbol_table
+    }
 }
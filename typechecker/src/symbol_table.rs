@@ -13,20 +13,31 @@ pub struct SymbolTable {
 pub struct SymbolTableScope {
     pub symbol_table_type: SymbolTableType,
     pub name: String,
+    // The node that creates the scope, the module scope spans the whole file
+    pub node: Node,
     symbols: HashMap<String, SymbolTableNode>,
 }
 
 impl SymbolTableScope {
-    pub fn new(symbol_table_type: SymbolTableType, name: String) -> Self {
+    pub fn new(symbol_table_type: SymbolTableType, name: String, node: Node) -> Self {
         SymbolTableScope {
             symbol_table_type,
             name,
+            node,
             symbols: HashMap::new(),
         }
     }
+
+    pub fn lookup(&self, name: &str) -> Option<&SymbolTableNode> {
+        self.symbols.get(name)
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &SymbolTableNode> {
+        self.symbols.values()
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SymbolTableType {
     Module,
     Class,
@@ -37,6 +48,8 @@ pub enum SymbolTableType {
 pub struct SymbolTableNode {
     pub name: String,
     pub declarations: Vec<Declaration>,
+    // Nodes of the names that use the symbol, the declarations are not included
+    pub references: Vec<Node>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +65,7 @@ pub enum Declaration {
     Class(Box<Class>),
 
     Parameter(Box<Paramter>),
+    Alias(Box<Alias>),
     // TypeParameterDeclaration represents a type parameter in a generic class or function. It models type parameters declared on classes and functions like T in List[T].
 }
 
@@ -62,6 +76,7 @@ impl Declaration {
             Declaration::Function(f) => &f.declaration_path,
             Declaration::Class(c) => &c.declaration_path,
             Declaration::Parameter(p) => &p.declaration_path,
+            Declaration::Alias(a) => &a.declaration_path,
         }
     }
 }
@@ -109,6 +124,18 @@ pub struct Paramter {
     pub default_value: Option<ast::Expression>,
 }
 
+/// A name bound by an import, e.g. `path` in `from os import path`
+#[derive(Debug, Clone)]
+pub struct Alias {
+    pub declaration_path: DeclarationPath,
+    // Module that is imported, without the leading dots of relative imports
+    pub module: String,
+    // Number of leading dots of a relative import
+    pub level: usize,
+    // Name that is imported from the module, None when the module itself is imported
+    pub name: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum SymbolScope {
     Global,
@@ -123,6 +150,7 @@ impl SymbolTable {
             symbol_table_type,
             symbols: HashMap::new(),
             name: String::from("global"),
+            node: Node::new(0, usize::MAX),
         };
         SymbolTable {
             scopes: vec![global_scope],
@@ -148,6 +176,70 @@ impl SymbolTable {
         return cur_scope.symbols.get(name);
     }
 
+    pub fn global_scope(&self) -> &SymbolTableScope {
+        &self.scopes[0]
+    }
+
+    /// Returns the scopes that can be used to look up names at the position,
+    /// from the innermost scope to the module scope.
+    /// Like python, class scopes are only visible from the class body and not from its methods.
+    pub fn scopes_at_position(&self, position: usize) -> Vec<&SymbolTableScope> {
+        let mut scopes = self
+            .all_scopes
+            .iter()
+            .filter(|scope| scope.node.start <= position && position < scope.node.end)
+            .collect::<Vec<&SymbolTableScope>>();
+        scopes.sort_by_key(|scope| std::cmp::Reverse(scope.node.start));
+        let mut visible = vec![];
+        for (i, scope) in scopes.into_iter().enumerate() {
+            if i == 0 || scope.symbol_table_type != SymbolTableType::Class {
+                visible.push(scope);
+            }
+        }
+        visible.push(self.global_scope());
+        visible
+    }
+
+    /// Looks up the name in the scopes that enclose the position
+    pub fn lookup_at_position(&self, name: &str, position: usize) -> Option<&SymbolTableNode> {
+        self.scopes_at_position(position)
+            .into_iter()
+            .find_map(|scope| scope.lookup(name))
+    }
+
+    /// Returns the scope created by the class or function node
+    pub fn scope_of_node(&self, node: Node) -> Option<&SymbolTableScope> {
+        self.all_scopes.iter().find(|scope| scope.node == node)
+    }
+
+    /// Returns the module scope and all the scopes inside it
+    pub fn scopes(&self) -> impl Iterator<Item = &SymbolTableScope> {
+        std::iter::once(self.global_scope()).chain(self.all_scopes.iter())
+    }
+
+    /// Records a use of the name at the node in the symbol that the name refers to.
+    /// Names that are not declared in the enclosing scopes are ignored.
+    pub fn add_reference(&mut self, name: &str, node: Node) {
+        let Some(scope_node) = self
+            .scopes_at_position(node.start)
+            .into_iter()
+            .find(|scope| scope.lookup(name).is_some())
+            .map(|scope| scope.node)
+        else {
+            return;
+        };
+        let scope = if scope_node == self.global_scope().node {
+            self.scopes.first_mut()
+        } else {
+            self.all_scopes
+                .iter_mut()
+                .find(|scope| scope.node == scope_node)
+        };
+        if let Some(symbol) = scope.and_then(|scope| scope.symbols.get_mut(name)) {
+            symbol.references.push(node);
+        }
+    }
+
     pub fn enter_scope(&mut self, new_scope: SymbolTableScope) {
         self.scopes.push(new_scope);
     }
@@ -188,7 +280,12 @@ impl SymbolTableNode {
         let mut filtered_declarations = self
             .declarations
             .iter()
-            .filter(|decl| decl.declaration_path().node.start < position)
+            .filter(|decl| match decl {
+                // A variable is bound after its value is evaluated, in `x = x + 1`
+                // the x in the value refers to the previous declaration
+                Declaration::Variable(v) => v.declaration_path.node.end <= position,
+                _ => decl.declaration_path().node.start < position,
+            })
             .collect::<Vec<&Declaration>>();
 
         filtered_declarations.sort_by(|a, b| {
@@ -264,6 +361,7 @@ impl std::fmt::Display for Declaration {
             Declaration::Function(fun) => write!(f, "{:?}", fun),
             Declaration::Class(c) => write!(f, "{:?}", c),
             Declaration::Parameter(p) => write!(f, "{:?}", p),
+            Declaration::Alias(a) => write!(f, "{:?}", a),
         }
     }
 }
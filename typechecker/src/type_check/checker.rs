@@ -3,10 +3,32 @@ use enderpy_python_parser as parser;
 use enderpy_python_parser::ast::{self, *};
 
 use crate::{
-    ast_visitor::TraversalVisitor, settings::Settings, state::State, symbol_table::SymbolTable,
+    ast_visitor::TraversalVisitor,
+    build::RelatedInformation,
+    settings::Settings,
+    state::State,
+    symbol_table::{Declaration, SymbolTable},
 };
 
-use super::{type_evaluator::TypeEvaluator, type_inference::type_check_bin_op, types::PythonType};
+use super::{
+    builtins::BUILTIN_CLASSES, rules::Rule, type_evaluator::TypeEvaluator,
+    type_inference::type_check_bin_op, types::PythonType,
+};
+
+// Generic aliases of the typing module, they never accept None
+const TYPING_GENERICS: &[&str] = &[
+    "Callable",
+    "Dict",
+    "FrozenSet",
+    "Iterable",
+    "Iterator",
+    "List",
+    "Mapping",
+    "Sequence",
+    "Set",
+    "Tuple",
+    "Type",
+];
 
 pub struct TypeChecker<'a> {
     pub errors: Vec<TypeCheckError>,
@@ -14,12 +36,15 @@ pub struct TypeChecker<'a> {
     symbol_table: SymbolTable,
     pub options: &'a Settings,
     type_evaluator: TypeEvaluator,
+    source: &'a str,
 }
 
 pub struct TypeCheckError {
     pub msg: String,
+    pub rule: Rule,
     pub start: usize,
     pub end: usize,
+    pub related: Vec<RelatedInformation>,
 }
 
 #[allow(unused)]
@@ -31,6 +56,7 @@ impl<'a> TypeChecker<'a> {
             symbol_table,
             options,
             type_evaluator: TypeEvaluator::new(module.get_symbol_table()),
+            source: &module.file.source,
         }
     }
 
@@ -45,6 +71,7 @@ impl<'a> TypeChecker<'a> {
                 if emit_error {
                     self.make_error(
                         e.to_string().as_str(),
+                        Rule::TypeError,
                         expr.get_node().start,
                         expr.get_node().end,
                     );
@@ -54,11 +81,13 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
-    fn make_error(&mut self, msg: &str, start: usize, end: usize) {
+    fn make_error(&mut self, msg: &str, rule: Rule, start: usize, end: usize) {
         let error = TypeCheckError {
             msg: msg.to_string(),
+            rule,
             start,
             end,
+            related: vec![],
         };
         // check error doesn't already exist
         for e in &self.errors {
@@ -68,6 +97,47 @@ impl<'a> TypeChecker<'a> {
         }
         self.errors.push(error);
     }
+
+    // Reports `None` assigned to an annotation that does not accept it, like `x: int = None`
+    fn check_none_assignment(&mut self, annotation: &Expression, value: &Expression) {
+        let is_none = matches!(
+            value,
+            Expression::Constant(c) if c.value == ConstantValue::None
+        );
+        if !is_none || !self.rejects_none(annotation) {
+            return;
+        }
+        let annotation = annotation.get_node();
+        let msg = format!(
+            "'None' is not assignable to '{}'",
+            &self.source[annotation.start..annotation.end]
+        );
+        let value = value.get_node();
+        self.make_error(&msg, Rule::IncompatibleAssignment, value.start, value.end);
+    }
+
+    // Only the annotations that are known to be classes are checked,
+    // other names may be aliases or type variables that accept None
+    fn rejects_none(&self, annotation: &Expression) -> bool {
+        let is_class = |name: &Name| match self
+            .symbol_table
+            .lookup_at_position(&name.id, name.node.start)
+        {
+            Some(symbol) => symbol
+                .declarations
+                .iter()
+                .all(|declaration| matches!(declaration, Declaration::Class(_))),
+            None => BUILTIN_CLASSES.contains(&name.id.as_str()) && name.id != "object",
+        };
+        match annotation {
+            Expression::Name(n) => is_class(n),
+            Expression::Subscript(s) => match &*s.value {
+                Expression::Name(n) => TYPING_GENERICS.contains(&n.id.as_str()) || is_class(n),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 #[allow(unused)]
 impl<'a> TraversalVisitor for TypeChecker<'a> {
@@ -98,6 +168,7 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
             Statement::FunctionDef(f) => self.visit_function_def(f),
             Statement::ClassDef(c) => self.visit_class_def(c),
             Statement::Match(m) => self.visit_match(m),
+            Statement::Invalid(_) => {}
             Statement::AsyncForStatement(f) => self.visit_async_for(f),
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
@@ -139,6 +210,7 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
             Expression::IfExp(i) => self.visit_if_exp(i),
             Expression::JoinedStr(j) => self.visit_joined_str(j),
             Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            Expression::Invalid(_) => {}
         }
     }
 
@@ -227,6 +299,24 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
     }
 
     fn visit_function_def(&mut self, f: &parser::ast::FunctionDef) {
+        let args = &f.args;
+        let positional = args.posonlyargs.iter().chain(args.args.iter());
+        let first_default =
+            (args.posonlyargs.len() + args.args.len()).saturating_sub(args.defaults.len());
+        let defaults = positional
+            .skip(first_default)
+            .zip(args.defaults.iter())
+            .chain(
+                args.kwonlyargs
+                    .iter()
+                    .zip(args.kw_defaults.iter())
+                    .filter_map(|(arg, default)| Some((arg, default.as_ref()?))),
+            );
+        for (arg, default) in defaults {
+            if let Some(annotation) = &arg.annotation {
+                self.check_none_assignment(annotation, default);
+            }
+        }
         for stmt in &f.body {
             self.visit_stmt(stmt);
         }
@@ -350,8 +440,10 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
             );
             self.errors.push(TypeCheckError {
                 msg,
+                rule: Rule::UnsupportedOperator,
                 start: b.node.start,
                 end: b.node.end,
+                related: vec![],
             });
         }
     }
@@ -502,6 +594,7 @@ impl<'a> TraversalVisitor for TypeChecker<'a> {
     fn visit_ann_assign(&mut self, _a: &AnnAssign) {
         if let Some(value) = &_a.value {
             self.visit_expr(value);
+            self.check_none_assignment(&_a.annotation, value);
         }
     }
 
@@ -1,6 +1,7 @@
-mod builtins;
+pub(crate) mod builtins;
 pub mod checker;
-mod rules;
+pub(crate) mod names;
+pub mod rules;
 pub mod type_evaluator;
 mod type_inference;
-mod types;
+pub(crate) mod types;
@@ -0,0 +1,312 @@
+use std::collections::HashSet;
+
+use enderpy_python_parser::ast::{
+    self, ConstantValue, Expression, GetNode, MatchPattern, Statement,
+};
+
+use crate::ast_walker::{walk_body, AstNode, AstWalker};
+use crate::build::RelatedInformation;
+use crate::ide::{declaration_name_range, node_text, Location};
+use crate::state::State;
+use crate::symbol_table::{Declaration, SymbolTableNode};
+
+use super::builtins::{BUILTINS, BUILTIN_NAMES};
+use super::checker::TypeCheckError;
+use super::rules::Rule;
+
+/// Reports the names that are used but never bound, the imports that are never used
+/// and the names that are declared again with another type.
+///
+/// The symbol table does not have the names bound by loops, comprehensions, `with`, `except`
+/// and `match` yet, so a name is undefined only if nothing in the module binds it.
+pub fn check_names(state: &State) -> Vec<TypeCheckError> {
+    let mut collector = NameCollector {
+        bound: HashSet::new(),
+        loads: vec![],
+        has_star_import: false,
+    };
+    walk_body(&mut collector, &state.file.body);
+
+    let mut errors = vec![];
+    // The names of a star import are not known
+    if !collector.has_star_import {
+        for name in collector.loads {
+            let id = name.id.as_str();
+            if collector.bound.contains(id) || BUILTINS.contains(&id) || BUILTIN_NAMES.contains(&id)
+            {
+                continue;
+            }
+            errors.push(TypeCheckError {
+                msg: format!("'{}' is not defined", id),
+                rule: Rule::UndefinedName,
+                start: name.node.start,
+                end: name.node.end,
+                related: vec![],
+            });
+        }
+    }
+    errors.extend(unused_imports(state));
+    errors.extend(incompatible_redeclarations(state));
+    errors
+}
+
+// Imports of packages are their interface, and the names in `__all__`
+// or imported as themselves like `import a as a` are exported
+fn unused_imports(state: &State) -> Vec<TypeCheckError> {
+    let is_package = state
+        .file
+        .path
+        .file_stem()
+        .is_some_and(|stem| stem == "__init__");
+    if is_package || state.file.path.extension().is_some_and(|ext| ext == "pyi") {
+        return vec![];
+    }
+    let symbol_table = state.symbol_table();
+    let exported = exported_names(symbol_table.global_scope().lookup("__all__"));
+
+    let mut errors = vec![];
+    for scope in symbol_table.scopes() {
+        for symbol in scope.symbols() {
+            if !symbol.references.is_empty() || exported.contains(&symbol.name.as_str()) {
+                continue;
+            }
+            for declaration in symbol.declarations.iter() {
+                let Declaration::Alias(alias) = declaration else {
+                    continue;
+                };
+                // The node of a star import does not cover its name
+                if symbol.name == "*" {
+                    continue;
+                }
+                let node = alias.declaration_path.node;
+                let text = &state.file.source[node.start..node.end];
+                let is_reexport = text
+                    .split_once(" as ")
+                    .is_some_and(|(name, asname)| name.trim() == asname.trim());
+                if is_reexport || (alias.name.is_some() && alias.module == "__future__") {
+                    continue;
+                }
+                errors.push(TypeCheckError {
+                    msg: format!("'{}' is imported but not used", symbol.name),
+                    rule: Rule::UnusedImport,
+                    start: node.start,
+                    end: node.end,
+                    related: vec![],
+                });
+            }
+        }
+    }
+    errors
+}
+
+// The declarations that give a name a type in a scope must agree on it.
+// Functions can be defined again, like overloads and property setters, and the types
+// of imports and variables without annotation are inferred, so they are not compared.
+fn incompatible_redeclarations(state: &State) -> Vec<TypeCheckError> {
+    let source = &state.file.source;
+    let mut errors = vec![];
+    for scope in state.symbol_table().scopes() {
+        for symbol in scope.symbols() {
+            let mut declarations = symbol
+                .declarations
+                .iter()
+                .filter_map(|declaration| Some((declaration, declared_type(source, declaration)?)))
+                .collect::<Vec<_>>();
+            declarations.sort_by_key(|(declaration, _)| declaration.declaration_path().node.start);
+            for pair in declarations.windows(2) {
+                let [(previous, previous_type), (declaration, declared_type)] = pair else {
+                    continue;
+                };
+                if previous_type == declared_type {
+                    continue;
+                }
+                let range = declaration_name_range(state, &symbol.name, declaration);
+                let previous_range = declaration_name_range(state, &symbol.name, previous);
+                errors.push(TypeCheckError {
+                    msg: format!(
+                        "'{}' is redeclared as {}, it was declared as {}",
+                        symbol.name, declared_type, previous_type
+                    ),
+                    rule: Rule::IncompatibleRedeclaration,
+                    start: range.start,
+                    end: range.end,
+                    related: vec![RelatedInformation {
+                        msg: "previous declaration here".to_string(),
+                        location: Location {
+                            path: state.file.path.clone(),
+                            start: previous_range.start,
+                            end: previous_range.end,
+                        },
+                    }],
+                });
+            }
+        }
+    }
+    errors
+}
+
+// Describes the type that the declaration gives its name, like `a class` or `'int'`
+fn declared_type(source: &str, declaration: &Declaration) -> Option<String> {
+    match declaration {
+        Declaration::Class(_) => Some("a class".to_string()),
+        Declaration::Function(_) => Some("a function".to_string()),
+        Declaration::Variable(v) => v
+            .type_annotation
+            .as_ref()
+            .map(|annotation| format!("'{}'", node_text(source, annotation.get_node()))),
+        Declaration::Parameter(_) | Declaration::Alias(_) => None,
+    }
+}
+
+// The strings of `__all__ = ["a", "b"]`
+fn exported_names(all: Option<&SymbolTableNode>) -> Vec<&str> {
+    let mut names = vec![];
+    for declaration in all.iter().flat_map(|symbol| symbol.declarations.iter()) {
+        let Declaration::Variable(v) = declaration else {
+            continue;
+        };
+        let elements = match &v.inferred_type_source {
+            Some(Expression::List(l)) => &l.elements,
+            Some(Expression::Tuple(t)) => &t.elements,
+            _ => continue,
+        };
+        for element in elements {
+            if let Expression::Constant(c) = element {
+                if let ConstantValue::Str(name) = &c.value {
+                    names.push(name.as_str());
+                }
+            }
+        }
+    }
+    names
+}
+
+// Collects the names that are used and all the names that the module binds in any scope
+struct NameCollector<'a> {
+    bound: HashSet<&'a str>,
+    loads: Vec<&'a ast::Name>,
+    has_star_import: bool,
+}
+
+impl<'a> NameCollector<'a> {
+    // Binds the names of an assignment target, e.g. `a` and `b` in `a, *b = ...`
+    fn bind_target(&mut self, target: &'a Expression) {
+        match target {
+            Expression::Name(n) => {
+                self.bound.insert(&n.id);
+            }
+            Expression::Tuple(t) => t.elements.iter().for_each(|e| self.bind_target(e)),
+            Expression::List(l) => l.elements.iter().for_each(|e| self.bind_target(e)),
+            Expression::Starred(s) => self.bind_target(&s.value),
+            _ => {}
+        }
+    }
+
+    fn bind_pattern(&mut self, pattern: &'a MatchPattern) {
+        match pattern {
+            MatchPattern::MatchAs(m) => {
+                if let Some(name) = &m.name {
+                    self.bound.insert(name);
+                }
+                if let Some(pattern) = &m.pattern {
+                    self.bind_pattern(pattern);
+                }
+            }
+            MatchPattern::MatchStar(e) => self.bind_target(e),
+            MatchPattern::MatchSequence(patterns) | MatchPattern::MatchOr(patterns) => {
+                patterns.iter().for_each(|p| self.bind_pattern(p));
+            }
+            MatchPattern::MatchMapping(m) => {
+                if let Some(rest) = &m.rest {
+                    self.bound.insert(rest);
+                }
+                m.patterns.iter().for_each(|p| self.bind_pattern(p));
+            }
+            MatchPattern::MatchClass(m) => {
+                m.patterns
+                    .iter()
+                    .chain(m.kwd_patterns.iter())
+                    .for_each(|p| self.bind_pattern(p));
+            }
+            MatchPattern::MatchValue(_) | MatchPattern::MatchSingleton(_) => {}
+        }
+    }
+
+    fn bind_comprehensions(&mut self, generators: &'a [ast::Comprehension]) {
+        for generator in generators {
+            self.bind_target(&generator.target);
+        }
+    }
+}
+
+impl<'a> AstWalker<'a> for NameCollector<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        match node {
+            AstNode::Statement(statement) => match statement {
+                Statement::AssignStatement(a) => {
+                    a.targets.iter().for_each(|t| self.bind_target(t));
+                }
+                Statement::AnnAssignStatement(a) => self.bind_target(&a.target),
+                Statement::AugAssignStatement(a) => self.bind_target(&a.target),
+                Statement::ForStatement(f) => self.bind_target(&f.target),
+                Statement::AsyncForStatement(f) => self.bind_target(&f.target),
+                Statement::WithStatement(w) => w
+                    .items
+                    .iter()
+                    .filter_map(|item| item.optional_vars.as_ref())
+                    .for_each(|vars| self.bind_target(vars)),
+                Statement::AsyncWithStatement(w) => w
+                    .items
+                    .iter()
+                    .filter_map(|item| item.optional_vars.as_ref())
+                    .for_each(|vars| self.bind_target(vars)),
+                Statement::Delete(d) => d.targets.iter().for_each(|t| self.bind_target(t)),
+                Statement::Global(g) => self.bound.extend(g.names.iter().map(String::as_str)),
+                Statement::Nonlocal(n) => self.bound.extend(n.names.iter().map(String::as_str)),
+                Statement::FunctionDef(f) => {
+                    self.bound.insert(&f.name);
+                }
+                Statement::AsyncFunctionDef(f) => {
+                    self.bound.insert(&f.name);
+                }
+                Statement::ClassDef(c) => {
+                    self.bound.insert(&c.name);
+                }
+                Statement::ImportFrom(i) => {
+                    self.has_star_import |= i.names.iter().any(|alias| alias.name == "*");
+                }
+                Statement::Match(m) => m
+                    .cases
+                    .iter()
+                    .for_each(|case| self.bind_pattern(&case.pattern)),
+                _ => {}
+            },
+            AstNode::Expression(expression) => match expression {
+                Expression::Name(n) => self.loads.push(n),
+                Expression::NamedExpr(n) => self.bind_target(&n.target),
+                Expression::ListComp(l) => self.bind_comprehensions(&l.generators),
+                Expression::SetComp(s) => self.bind_comprehensions(&s.generators),
+                Expression::DictComp(d) => self.bind_comprehensions(&d.generators),
+                Expression::Generator(g) => self.bind_comprehensions(&g.generators),
+                _ => {}
+            },
+            AstNode::Arg(a) => {
+                self.bound.insert(&a.arg);
+            }
+            AstNode::Alias(a) => {
+                let name = match &a.asname {
+                    Some(asname) => asname.as_str(),
+                    None => a.name.split('.').next().unwrap_or_default(),
+                };
+                self.bound.insert(name);
+            }
+            AstNode::ExceptHandler(h) => {
+                if let Some(name) = &h.name {
+                    self.bound.insert(name);
+                }
+            }
+            AstNode::Keyword(_) => {}
+        }
+        true
+    }
+}
@@ -1,3 +1,5 @@
+use crate::settings::Severity;
+
 use super::types::PythonType;
 
 pub fn is_reassignment_valid(old_type: &PythonType, new_type: &PythonType) -> bool {
@@ -10,3 +12,62 @@ pub fn is_reassignment_valid(old_type: &PythonType, new_type: &PythonType) -> bo
 
     false
 }
+
+/// Rules that the type checker reports errors for.
+/// Each rule has a code that is used to configure its severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// The source code cannot be parsed
+    SyntaxError,
+    /// The type of an expression cannot be evaluated, e.g. calling a non callable
+    TypeError,
+    /// A binary operator is used with types that do not support it
+    UnsupportedOperator,
+    /// A name is used but it is not bound in the module and it is not a builtin
+    UndefinedName,
+    /// An imported name is never used
+    UnusedImport,
+    /// A value is assigned to a variable or parameter whose annotation does not accept it,
+    /// only `None` assigned to non optional annotations is checked for now
+    IncompatibleAssignment,
+    /// A name is declared again in the same scope with another type,
+    /// like a class that is redefined as a function
+    IncompatibleRedeclaration,
+}
+
+impl Rule {
+    pub const ALL: &'static [Rule] = &[
+        Rule::SyntaxError,
+        Rule::TypeError,
+        Rule::UnsupportedOperator,
+        Rule::UndefinedName,
+        Rule::UnusedImport,
+        Rule::IncompatibleAssignment,
+        Rule::IncompatibleRedeclaration,
+    ];
+
+    /// Returns the rule with the code, e.g. `type-error`
+    pub fn from_code(code: &str) -> Option<Rule> {
+        Self::ALL.iter().copied().find(|rule| rule.code() == code)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Rule::SyntaxError => "syntax-error",
+            Rule::TypeError => "type-error",
+            Rule::UnsupportedOperator => "unsupported-operator",
+            Rule::UndefinedName => "undefined-name",
+            Rule::UnusedImport => "unused-import",
+            Rule::IncompatibleAssignment => "incompatible-assignment",
+            Rule::IncompatibleRedeclaration => "incompatible-redeclaration",
+        }
+    }
+
+    /// The severity of the rule when it is not configured
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::UnusedImport => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
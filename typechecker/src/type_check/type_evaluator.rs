@@ -9,6 +9,7 @@ use parser::ast::Statement;
 
 use crate::{
     ast_visitor_generic::TraversalVisitorImmutGeneric,
+    ast_walker::{walk_body, AstNode, AstWalker},
     symbol_table::{Declaration, SymbolTable, SymbolTableNode},
 };
 
@@ -65,8 +66,13 @@ impl TypeEvaluator {
                         if builtins::BUILTINS.contains(&n.id.as_str()) {
                             return Ok(PythonType::Unknown);
                         }
-                        let f_type =
-                            self.infer_type_from_symbol_table(n.id.as_str(), n.node.start)?;
+                        // Undefined names are reported by the checker of names
+                        let Some(symbol) =
+                            self.symbol_table.lookup_at_position(&n.id, n.node.start)
+                        else {
+                            return Ok(PythonType::Unknown);
+                        };
+                        let f_type = self.get_symbol_node_type(symbol, n.node.start)?;
                         match f_type {
                             PythonType::Callable(callable_type) => Ok(callable_type.return_type),
                             _ => Err(miette!("{} is not callable", n.id)),
@@ -139,7 +145,8 @@ impl TypeEvaluator {
             ast::Expression::YieldFrom(yf) => {
                 let yield_type = match *yf.value.clone() {
                     ast::Expression::List(l) => self.get_sequence_type_from_elements(&l.elements),
-                    _ => panic!("TODO: infer type from yield from"),
+                    // TODO: infer type from yield from
+                    _ => PythonType::Unknown,
                 };
                 Ok(PythonType::Class(super::types::ClassType {
                     name: builtins::ITER_TYPE.to_string(),
@@ -188,10 +195,11 @@ impl TypeEvaluator {
             ast::Expression::IfExp(_) => Ok(PythonType::Unknown),
             ast::Expression::JoinedStr(_) => Ok(PythonType::Str),
             ast::Expression::FormattedValue(f) => self.get_type(&f.value),
+            ast::Expression::Invalid(_) => Ok(PythonType::Unknown),
         }
     }
 
-    fn get_type_from_declaration(&self, declaration: &Declaration) -> Result<PythonType> {
+    pub fn get_type_from_declaration(&self, declaration: &Declaration) -> Result<PythonType> {
         match declaration {
             Declaration::Variable(v) => {
                 if let Some(type_annotation) = &v.type_annotation {
@@ -221,11 +229,46 @@ impl TypeEvaluator {
             }
             Declaration::Class(_) => Ok(PythonType::Unknown),
             Declaration::Parameter(_) => Ok(PythonType::Unknown),
+            // TODO: follow the import to the declaration in the imported module
+            Declaration::Alias(_) => Ok(PythonType::Unknown),
+        }
+    }
+
+    /// Infers the return type of a function without a return annotation from its return statements.
+    /// The type is unknown for generators and when the function returns values of different types.
+    pub fn infer_return_type(&self, function: &ast::FunctionDef) -> PythonType {
+        let mut collector = ReturnCollector {
+            returns: vec![],
+            is_generator: false,
+        };
+        walk_body(&mut collector, &function.body);
+        if collector.is_generator {
+            return PythonType::Unknown;
+        }
+        let mut types = collector
+            .returns
+            .iter()
+            .map(|r| match &r.value {
+                Some(value) => self.get_type(value).unwrap_or(PythonType::Unknown),
+                None => PythonType::None,
+            })
+            .collect::<Vec<PythonType>>();
+        // A function that reaches the end of its body returns None
+        let falls_through = !matches!(
+            function.body.last(),
+            Some(Statement::Return(_) | Statement::Raise(_))
+        );
+        if falls_through {
+            types.push(PythonType::None);
+        }
+        match types.split_first() {
+            Some((first, rest)) if rest.iter().all(|t| t == first) => first.clone(),
+            _ => PythonType::Unknown,
         }
     }
 
     fn infer_type_from_symbol_table(&self, name: &str, position: usize) -> Result<PythonType> {
-        match self.symbol_table.lookup_in_scope(name) {
+        match self.symbol_table.lookup_at_position(name, position) {
             Some(symbol) => self.get_symbol_node_type(symbol, position),
             None => Ok(PythonType::Unknown),
         }
@@ -246,6 +289,29 @@ impl TypeEvaluator {
     }
 }
 
+// Collects the return statements of a function body, nested functions and classes are skipped
+struct ReturnCollector<'a> {
+    returns: Vec<&'a ast::Return>,
+    is_generator: bool,
+}
+
+impl<'a> AstWalker<'a> for ReturnCollector<'a> {
+    fn enter(&mut self, node: AstNode<'a>) -> bool {
+        match node {
+            AstNode::Statement(
+                Statement::FunctionDef(_) | Statement::AsyncFunctionDef(_) | Statement::ClassDef(_),
+            )
+            | AstNode::Expression(ast::Expression::Lambda(_)) => return false,
+            AstNode::Statement(Statement::Return(r)) => self.returns.push(r),
+            AstNode::Expression(ast::Expression::Yield(_) | ast::Expression::YieldFrom(_)) => {
+                self.is_generator = true;
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
 impl TraversalVisitorImmutGeneric<PythonType> for TypeEvaluator {
     fn visit_stmt(&self, s: &ast::Statement) -> PythonType {
         // map all statements and call visit
@@ -274,6 +340,7 @@ impl TraversalVisitorImmutGeneric<PythonType> for TypeEvaluator {
             ast::Statement::FunctionDef(f) => self.visit_function_def(f),
             ast::Statement::ClassDef(c) => self.visit_class_def(c),
             ast::Statement::Match(m) => self.visit_match(m),
+            ast::Statement::Invalid(i) => self.visit_invalid(i),
             Statement::AsyncForStatement(f) => self.visit_async_for(f),
             Statement::AsyncWithStatement(w) => self.visit_async_with(w),
             Statement::AsyncFunctionDef(f) => self.visit_async_function_def(f),
@@ -309,6 +376,7 @@ impl TraversalVisitorImmutGeneric<PythonType> for TypeEvaluator {
             ast::Expression::IfExp(i) => self.visit_if_exp(i),
             ast::Expression::JoinedStr(j) => self.visit_joined_str(j),
             ast::Expression::FormattedValue(f) => self.visit_formatted_value(f),
+            ast::Expression::Invalid(i) => self.visit_invalid(i),
         }
     }
 
@@ -488,6 +556,10 @@ impl TraversalVisitorImmutGeneric<PythonType> for TypeEvaluator {
         PythonType::Unknown
     }
 
+    fn visit_invalid(&self, _i: &ast::Invalid) -> PythonType {
+        PythonType::Unknown
+    }
+
     fn visit_delete(&self, _d: &ast::Delete) -> PythonType {
         PythonType::Unknown
     }
@@ -536,6 +608,8 @@ mod tests {
             "test".to_string(),
             "".to_string(),
             PathBuf::from("test.py"),
+            false,
+            parser.line_index,
         );
 
         let mut module = State::new(Box::new(enderpy_file));
@@ -55,6 +55,7 @@ pub fn get_type_from_annotation(type_annotation: &ast::Expression) -> PythonType
                 Expression::IfExp(_) => todo!(),
                 Expression::JoinedStr(_) => todo!(),
                 Expression::FormattedValue(_) => todo!(),
+                Expression::Invalid(_) => return PythonType::Unknown,
             }
             .to_string();
             PythonType::Class(super::types::ClassType {
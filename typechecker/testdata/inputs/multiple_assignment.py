@@ -0,0 +1,2 @@
+a = b = 1
+c, d = e = 1, 2
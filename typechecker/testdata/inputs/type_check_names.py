@@ -0,0 +1,14 @@
+import os
+import sys as sys
+from typing import List, Optional
+
+__all__ = ["Optional"]
+
+
+def f(items: List[int]):
+    total = [x * 2 for x in items]
+    try:
+        pass
+    except ValueError as e:
+        print(e)
+    return total + missing
@@ -0,0 +1,16 @@
+from typing import Any, Dict, Optional
+
+
+class A:
+    pass
+
+
+a: int = None
+b: Optional[int] = None
+c: A = None
+d: Dict[str, int] = None
+e: Any = None
+
+
+def f(x: str = None, y: object = None, *, z: A = None):
+    pass
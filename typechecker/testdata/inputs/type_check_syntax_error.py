@@ -0,0 +1,3 @@
+a: int = 1
+b = (1,
+c: int = 2